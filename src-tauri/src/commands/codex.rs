@@ -7,7 +7,7 @@ use crate::modules::{codex_account, codex_quota, codex_oauth, config, logger, op
 /// 列出所有 Codex 账号
 #[tauri::command]
 pub fn list_codex_accounts() -> Result<Vec<CodexAccount>, String> {
-    Ok(codex_account::list_accounts())
+    codex_account::try_list_accounts()
 }
 
 /// 获取当前激活的 Codex 账号
@@ -120,28 +120,80 @@ pub fn import_codex_from_json(json_content: String) -> Result<Vec<CodexAccount>,
     codex_account::import_from_json(&json_content)
 }
 
+/// 从 JSON 数组批量导入账号，返回逐项结果
+#[tauri::command]
+pub fn import_codex_accounts(
+    json_content: String,
+    on_conflict: Option<codex_account::ConflictPolicy>,
+) -> Result<codex_account::ImportReport, String> {
+    codex_account::import_accounts(&json_content, on_conflict.unwrap_or_default())
+}
+
 /// 导出 Codex 账号
 #[tauri::command]
 pub fn export_codex_accounts(account_ids: Vec<String>) -> Result<String, String> {
     codex_account::export_accounts(&account_ids)
 }
 
-/// 刷新单个账号配额
+/// 刷新单个账号配额；`max_age_ms` 内获取过的配额直接返回缓存（默认总是请求）
 #[tauri::command]
-pub async fn refresh_codex_quota(app: AppHandle, account_id: String) -> Result<CodexQuota, String> {
-    let result = codex_quota::refresh_account_quota(&account_id).await;
+pub async fn refresh_codex_quota(
+    app: AppHandle,
+    account_id: String,
+    max_age_ms: Option<u64>,
+) -> Result<CodexQuota, String> {
+    let result = codex_quota::refresh_account_quota(&account_id, max_age_ms.unwrap_or(0)).await;
     if result.is_ok() {
         let _ = crate::modules::tray::update_tray_menu(&app);
     }
     result
 }
 
+/// 探测单个账号配额（不写回账号存储）
+#[tauri::command]
+pub async fn probe_codex_quota(account_id: String) -> Result<CodexQuota, String> {
+    codex_quota::probe_quota(&account_id).await
+}
+
+/// 列出需要关注的账号（令牌失效、额度耗尽、连续唤醒失败等）
+#[tauri::command]
+pub fn get_codex_accounts_needing_attention() -> Vec<crate::modules::codex_quota::AttentionItem> {
+    crate::modules::codex_quota::accounts_needing_attention()
+}
+
+/// 读取账号的配额快照历史（按时间升序）
+#[tauri::command]
+pub fn get_codex_quota_history(
+    account_id: String,
+) -> Result<Vec<crate::modules::codex_quota_history::QuotaSample>, String> {
+    crate::modules::codex_quota_history::load_quota_history(&account_id)
+}
+
+/// 检测配额异常下降
+#[tauri::command]
+pub fn detect_codex_quota_anomalies(
+    account_id: String,
+    drop_threshold: i32,
+) -> Result<Vec<crate::modules::codex_quota_history::QuotaAnomaly>, String> {
+    crate::modules::codex_quota_history::detect_quota_anomalies(&account_id, drop_threshold)
+}
+
+/// 按近期消耗速率估算窗口耗尽时间（毫秒），数据不足或无消耗时返回 null
+#[tauri::command]
+pub fn estimate_codex_quota_time_to_empty(
+    account_id: String,
+    window: crate::modules::codex_quota_history::QuotaWindow,
+) -> Option<u64> {
+    crate::modules::codex_quota_history::estimate_time_to_empty(&account_id, window)
+        .map(|duration| duration.as_millis() as u64)
+}
+
 #[tauri::command]
 pub async fn refresh_current_codex_quota(app: AppHandle) -> Result<(), String> {
     let Some(account) = codex_account::get_current_account() else {
         return Err("未找到当前 Codex 账号".to_string());
     };
-    let result = codex_quota::refresh_account_quota(&account.id).await;
+    let result = codex_quota::refresh_account_quota(&account.id, 0).await;
     if result.is_ok() {
         let _ = crate::modules::tray::update_tray_menu(&app);
         Ok(())
@@ -159,16 +211,142 @@ pub async fn refresh_all_codex_quotas(app: AppHandle) -> Result<i32, String> {
     Ok(success_count as i32)
 }
 
+/// 刷新所有账号配额（返回耗时汇总）
+#[tauri::command]
+pub async fn refresh_all_codex_quotas_report(
+    app: AppHandle,
+) -> Result<codex_quota::RefreshAllReport, String> {
+    let report = codex_quota::refresh_all_quotas_report().await;
+    let _ = crate::modules::tray::update_tray_menu(&app);
+    Ok(report)
+}
+
+/// 汇总所有账号配额
+#[tauri::command]
+pub async fn get_codex_aggregate_quota(
+    refresh_first: Option<bool>,
+) -> Result<codex_quota::AggregateQuota, String> {
+    Ok(codex_quota::aggregate_quota(refresh_first.unwrap_or(false)).await)
+}
+
+/// 唤醒单个账号；其余可选参数通过 options 传入（字段均可省略）
 #[tauri::command]
 pub async fn codex_trigger_wakeup(
     account_id: String,
     model: String,
     prompt: Option<String>,
     max_output_tokens: Option<u32>,
+    trigger_source: Option<String>,
+    options: Option<crate::modules::codex_wakeup::WakeupOptions>,
 ) -> Result<crate::modules::codex_wakeup::WakeupResponse, String> {
     let final_prompt = prompt.unwrap_or_else(|| "hi".to_string());
     let final_tokens = max_output_tokens.unwrap_or(0);
-    crate::modules::codex_wakeup::trigger_wakeup(&account_id, &model, &final_prompt, final_tokens).await
+    let options = crate::modules::codex_wakeup::WakeupOptions {
+        history: crate::modules::codex_wakeup::WakeupHistoryContext {
+            trigger_source: trigger_source
+                .map(crate::modules::codex_wakeup_history::TriggerSource::from)
+                .unwrap_or(crate::modules::codex_wakeup_history::TriggerSource::Manual),
+            ..Default::default()
+        },
+        ..options.unwrap_or_default()
+    };
+    crate::modules::codex_wakeup::trigger_wakeup_with_options(
+        &account_id,
+        &model,
+        &final_prompt,
+        final_tokens,
+        &options,
+    )
+    .await
+}
+
+/// 一键唤醒目标窗口剩余额度低于阈值的所有账号
+#[tauri::command]
+pub async fn codex_wake_all_low(
+    threshold: i32,
+    model: String,
+) -> Result<crate::modules::codex_wakeup::WakeAllLowReport, String> {
+    crate::modules::codex_wakeup::wake_all_low(threshold, &model).await
+}
+
+/// 根据已保存的重置时间建议下一次唤醒时间（Unix 秒），缺少重置数据时返回 null
+#[tauri::command]
+pub fn codex_suggest_wakeup_time(account_id: String, window: String) -> Option<i64> {
+    crate::modules::codex_wakeup::suggest_wakeup_time(&account_id, &window)
+}
+
+/// 清除唤醒去重预约（传 account_id 时只清除该账号），返回清除数量
+#[tauri::command]
+pub fn codex_clear_wakeup_reservations(account_id: Option<String>) -> usize {
+    match account_id {
+        Some(account_id) => crate::modules::codex_wakeup::clear_wakeup_reservation(&account_id),
+        None => crate::modules::codex_wakeup::clear_wakeup_reservations(),
+    }
+}
+
+/// 仅生成唤醒将执行的 CLI 命令（不启动 CLI、不访问网络）
+#[tauri::command]
+pub fn codex_wakeup_dry_run(
+    account_id: String,
+    model: String,
+    prompt: Option<String>,
+    max_output_tokens: Option<u32>,
+    cli_model: Option<String>,
+    reasoning_effort: Option<String>,
+) -> Result<crate::modules::codex_wakeup::WakeupDryRun, String> {
+    let final_prompt = prompt.unwrap_or_else(|| "hi".to_string());
+    let options = crate::modules::codex_wakeup::WakeupOptions {
+        cli_model,
+        reasoning_effort,
+        ..Default::default()
+    };
+    crate::modules::codex_wakeup::dry_run_wakeup(
+        &account_id,
+        &model,
+        &final_prompt,
+        max_output_tokens.unwrap_or(0),
+        &options,
+    )
+}
+
+/// 批量唤醒指定账号，同时进行的唤醒数不超过 concurrency
+#[tauri::command]
+pub async fn codex_trigger_wakeup_batch(
+    account_ids: Vec<String>,
+    model: String,
+    prompt: Option<String>,
+    concurrency: Option<usize>,
+) -> Result<Vec<crate::modules::codex_wakeup::BatchWakeupOutcome>, String> {
+    let final_prompt = prompt.unwrap_or_else(|| "hi".to_string());
+    let concurrency = concurrency.unwrap_or_else(|| {
+        crate::modules::codex_wakeup_settings::get_settings().max_concurrent_wakeups
+    });
+    let results = crate::modules::codex_wakeup::trigger_wakeup_batch(
+        &account_ids,
+        &model,
+        &final_prompt,
+        concurrency,
+    )
+    .await;
+    Ok(results.into_iter().map(Into::into).collect())
+}
+
+/// 按历史记录重新执行一次唤醒（记录新的历史并关联原记录）
+#[tauri::command]
+pub async fn codex_wakeup_replay_history_item(
+    id: String,
+) -> Result<crate::modules::codex_wakeup::WakeupResponse, String> {
+    crate::modules::codex_wakeup::replay_history_item(&id).await
+}
+
+#[tauri::command]
+pub async fn codex_quick_test(
+    account_id: String,
+    prompt: Option<String>,
+    model: String,
+) -> Result<crate::modules::codex_wakeup::WakeupResponse, String> {
+    crate::modules::codex_wakeup::quick_test(&account_id, prompt.as_deref().unwrap_or_default(), &model)
+        .await
 }
 
 #[tauri::command]
@@ -177,6 +355,25 @@ pub async fn codex_fetch_available_models(
     crate::modules::codex_wakeup::fetch_available_models().await
 }
 
+/// 读取账号最近一次 verbose 唤醒的完整 CLI 输出（Token 已脱敏）
+#[tauri::command]
+pub fn codex_wakeup_last_debug(account_id: String) -> Option<crate::modules::codex_wakeup::WakeupDebug> {
+    crate::modules::codex_wakeup::last_wakeup_debug(&account_id)
+}
+
+/// 检查 Codex CLI 是否已安装且可运行
+#[tauri::command]
+pub async fn codex_check_cli() -> Result<crate::modules::codex_wakeup::CliInfo, String> {
+    crate::modules::codex_wakeup::check_codex_cli().await
+}
+
+#[tauri::command]
+pub async fn codex_list_cli_models(
+    refresh: Option<bool>,
+) -> Result<Vec<crate::modules::codex_wakeup::CliModel>, String> {
+    crate::modules::codex_wakeup::list_cli_models(refresh.unwrap_or(false)).await
+}
+
 #[tauri::command]
 pub async fn codex_wakeup_sync_state(
     app: AppHandle,
@@ -188,12 +385,55 @@ pub async fn codex_wakeup_sync_state(
     Ok(())
 }
 
+/// 原子替换单个唤醒任务并重新计算下次触发时间（毫秒）
+#[tauri::command]
+pub fn codex_wakeup_update_schedule_entry(
+    id: String,
+    entry: crate::modules::codex_wakeup_scheduler::WakeupTaskInput,
+) -> Result<Option<i64>, String> {
+    crate::modules::codex_wakeup_scheduler::update_schedule_entry(&id, entry)
+}
+
+#[tauri::command]
+pub fn codex_wakeup_set_scheduler_enabled(enabled: bool) -> Result<(), String> {
+    crate::modules::codex_wakeup_scheduler::set_scheduler_enabled(enabled)
+}
+
+#[tauri::command]
+pub fn codex_wakeup_scheduler_status(
+) -> Result<crate::modules::codex_wakeup_scheduler::SchedulerStatus, String> {
+    Ok(crate::modules::codex_wakeup_scheduler::scheduler_status())
+}
+
 #[tauri::command]
 pub fn codex_wakeup_load_history(
 ) -> Result<Vec<crate::modules::codex_wakeup_history::WakeupHistoryItem>, String> {
     crate::modules::codex_wakeup_history::load_history()
 }
 
+/// 最近 window_ms 内的唤醒成功率（可按账号邮箱过滤），无记录时返回 null
+#[tauri::command]
+pub fn codex_wakeup_success_rate(account_email: Option<String>, window_ms: u64) -> Option<f64> {
+    crate::modules::codex_wakeup_history::success_rate(
+        account_email.as_deref(),
+        std::time::Duration::from_millis(window_ms),
+    )
+}
+
+/// 按天统计最近 days 天的唤醒次数（含无记录的日期），用于活动热力图
+#[tauri::command]
+pub fn codex_wakeup_history_by_day(
+    timezone: Option<crate::modules::codex_wakeup_history::HistoryTimezone>,
+    days: usize,
+) -> Result<Vec<crate::modules::codex_wakeup_history::DayBucket>, String> {
+    crate::modules::codex_wakeup_history::history_by_day(timezone.unwrap_or_default(), days)
+}
+
+#[tauri::command]
+pub fn codex_wakeup_repair_history() -> Result<usize, String> {
+    crate::modules::codex_wakeup_history::repair_history()
+}
+
 #[tauri::command]
 pub fn codex_wakeup_clear_history() -> Result<(), String> {
     crate::modules::codex_wakeup_history::clear_history()
@@ -206,20 +446,57 @@ pub fn codex_wakeup_add_history_items(
     crate::modules::codex_wakeup_history::add_history_items(items)
 }
 
+#[tauri::command]
+pub fn codex_wakeup_import_history_csv(
+    path: String,
+) -> Result<crate::modules::codex_wakeup_history_csv::HistoryCsvImportReport, String> {
+    crate::modules::codex_wakeup_history_csv::import_history_csv(std::path::Path::new(&path))
+}
+
+#[tauri::command]
+pub fn codex_wakeup_export_history_csv(path: String) -> Result<usize, String> {
+    crate::modules::codex_wakeup_history_csv::export_history_csv(std::path::Path::new(&path))
+}
+
+/// 导出 Prometheus 文本格式的配额与唤醒指标（读取已存储数据，不触发刷新）
+#[tauri::command]
+pub fn get_codex_prometheus_metrics() -> String {
+    crate::modules::codex_metrics::prometheus_metrics()
+}
+
+#[tauri::command]
+pub fn export_codex_support_bundle(path: String, redact_messages: Option<bool>) -> Result<(), String> {
+    crate::modules::codex_support_bundle::export_support_bundle(
+        std::path::Path::new(&path),
+        redact_messages.unwrap_or(true),
+    )
+}
+
+#[tauri::command]
+pub fn codex_wakeup_get_settings(
+) -> Result<crate::modules::codex_wakeup_settings::CodexWakeupSettings, String> {
+    Ok(crate::modules::codex_wakeup_settings::get_settings())
+}
+
+#[tauri::command]
+pub fn codex_wakeup_save_settings(
+    settings: crate::modules::codex_wakeup_settings::CodexWakeupSettings,
+) -> Result<(), String> {
+    crate::modules::codex_wakeup_settings::save_settings(&settings)
+}
+
 async fn save_codex_oauth_tokens(tokens: CodexTokens) -> Result<CodexAccount, String> {
-    let account = codex_account::upsert_account(tokens)?;
+    let mut account = codex_account::upsert_account(tokens)?;
 
-    if let Err(e) = codex_quota::refresh_account_quota(&account.id).await {
+    if let Err(e) = codex_quota::refresh_quota_for(&mut account).await {
         logger::log_error(&format!("刷新配额失败: {}", e));
     }
 
-    let loaded =
-        codex_account::load_account(&account.id).ok_or_else(|| "账号保存后无法读取".to_string())?;
     logger::log_info(&format!(
         "Codex OAuth 账号已保存: account_id={}, email={}",
-        loaded.id, loaded.email
+        account.id, account.email
     ));
-    Ok(loaded)
+    Ok(account)
 }
 
 /// OAuth：开始登录（返回 loginId + authUrl）
@@ -291,15 +568,14 @@ pub async fn add_codex_account_with_token(id_token: String, access_token: String
         refresh_token,
     };
     
-    let account = codex_account::upsert_account(tokens)?;
+    let mut account = codex_account::upsert_account(tokens)?;
     
     // 刷新配额
-    if let Err(e) = codex_quota::refresh_account_quota(&account.id).await {
+    if let Err(e) = codex_quota::refresh_quota_for(&mut account).await {
         logger::log_error(&format!("刷新配额失败: {}", e));
     }
     
-    codex_account::load_account(&account.id)
-        .ok_or_else(|| "账号保存后无法读取".to_string())
+    Ok(account)
 }
 
 /// 检查 Codex OAuth 端口是否被占用
@@ -308,6 +584,90 @@ pub async fn update_codex_account_tags(account_id: String, tags: Vec<String>) ->
     codex_account::update_account_tags(&account_id, tags)
 }
 
+/// 设置账号级唤醒保护阈值
+#[tauri::command]
+pub fn set_codex_account_guards(
+    account_id: String,
+    guards: crate::models::codex::AccountGuards,
+) -> Result<CodexAccount, String> {
+    codex_account::set_account_guards(&account_id, guards)
+}
+
+/// 清除账号级唤醒保护阈值
+#[tauri::command]
+pub fn reset_codex_account_guards(account_id: String) -> Result<CodexAccount, String> {
+    codex_account::reset_account_guards(&account_id)
+}
+
+/// 设置配额请求的 ChatGPT-Account-Id 请求头策略
+#[tauri::command]
+pub fn set_codex_account_header_strategy(
+    account_id: String,
+    strategy: crate::models::codex::AccountHeaderStrategy,
+) -> Result<CodexAccount, String> {
+    codex_account::set_account_header_strategy(&account_id, strategy)
+}
+
+/// 设置账号级 Codex CLI 路径（传空则清除）
+#[tauri::command]
+pub fn set_codex_account_cli_path(
+    account_id: String,
+    cli_path: Option<String>,
+) -> Result<CodexAccount, String> {
+    codex_account::set_account_cli_path(&account_id, cli_path)
+}
+
+/// 设置账号暂停唤醒的截止时间（Unix 秒，传空则恢复）
+#[tauri::command]
+pub fn set_codex_account_paused_until(
+    account_id: String,
+    paused_until: Option<i64>,
+) -> Result<CodexAccount, String> {
+    codex_account::set_account_paused_until(&account_id, paused_until)
+}
+
+/// 将账号暂停到各自的周配额重置时间
+#[tauri::command]
+pub fn pause_codex_accounts_until_weekly_reset(
+    account_ids: Vec<String>,
+) -> Result<Vec<codex_account::PauseResult>, String> {
+    Ok(codex_account::pause_until_weekly_reset(&account_ids))
+}
+
+/// 检查账号存储完整性（只读）
+#[tauri::command]
+pub fn verify_codex_accounts_store() -> Result<codex_account::StoreReport, String> {
+    codex_account::verify_accounts_store()
+}
+
+/// 查询账号文件加密状态
+#[tauri::command]
+pub fn get_codex_account_encryption_status(
+) -> Result<crate::modules::codex_account_crypto::EncryptionStatus, String> {
+    crate::modules::codex_account_crypto::status()
+}
+
+/// 启用账号文件加密（keySource: keychain / passphrase）
+#[tauri::command]
+pub fn enable_codex_account_encryption(
+    key_source: crate::modules::codex_account_crypto::KeySource,
+    passphrase: Option<String>,
+) -> Result<(), String> {
+    codex_account::enable_account_encryption(key_source, passphrase.as_deref())
+}
+
+/// 关闭账号文件加密，所有账号写回明文
+#[tauri::command]
+pub fn disable_codex_account_encryption() -> Result<(), String> {
+    codex_account::disable_account_encryption()
+}
+
+/// 用口令解锁已加密的账号存储
+#[tauri::command]
+pub fn unlock_codex_accounts(passphrase: String) -> Result<(), String> {
+    crate::modules::codex_account_crypto::unlock(&passphrase)
+}
+
 #[tauri::command]
 pub fn is_codex_oauth_port_in_use() -> Result<bool, String> {
     let port = codex_oauth::get_callback_port();
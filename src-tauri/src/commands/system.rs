@@ -43,6 +43,21 @@ pub struct GeneralConfig {
     pub opencode_sync_on_switch: bool,
 }
 
+/// 读取内存中的最近日志（供“最近活动”面板使用）
+#[tauri::command]
+pub fn get_recent_logs(
+    level: Option<modules::logger::LogLevel>,
+    limit: Option<usize>,
+) -> Vec<modules::logger::LogEntry> {
+    modules::logger::recent_logs(level, limit.unwrap_or(200))
+}
+
+/// 检查数据目录是否可写，返回目录路径
+#[tauri::command]
+pub fn check_data_dir_writable() -> Result<String, String> {
+    modules::account::probe_data_dir_writable().map(|dir| dir.to_string_lossy().to_string())
+}
+
 #[tauri::command]
 pub async fn open_data_folder() -> Result<(), String> {
     let path = modules::account::get_data_dir()?;
@@ -126,6 +141,7 @@ pub fn save_network_config(ws_enabled: bool, ws_port: u16) -> Result<bool, Strin
         codex_app_path: current.codex_app_path,
         vscode_app_path: current.vscode_app_path,
         opencode_sync_on_switch: current.opencode_sync_on_switch,
+        log_buffer_capacity: current.log_buffer_capacity,
     };
     
     config::save_user_config(&new_config)?;
@@ -204,6 +220,7 @@ pub fn save_general_config(
         codex_app_path: normalized_codex_path,
         vscode_app_path: normalized_vscode_path,
         opencode_sync_on_switch,
+        log_buffer_capacity: current.log_buffer_capacity,
     };
     
     config::save_user_config(&new_config)?;
@@ -42,6 +42,23 @@ pub fn run() {
             
             // 存储全局 AppHandle
             let _ = APP_HANDLE.set(app.handle().clone());
+            logger::set_recent_log_capacity(modules::config::get_user_config().log_buffer_capacity);
+            modules::codex_http::log_active_user_agent();
+            modules::codex_quota_poller::ensure_started(app.handle().clone());
+            std::thread::spawn(|| {
+                let removed = modules::codex_wakeup::cleanup_stale_wakeup_dirs(
+                    modules::codex_wakeup::STALE_SESSION_MAX_AGE,
+                );
+                info!("[CodexWakeup] 已清理 {} 个残留的临时 CODEX_HOME 目录", removed);
+            });
+            if modules::codex_wakeup_settings::get_settings().warmup_on_launch {
+                tauri::async_runtime::spawn(modules::codex_wakeup::warmup());
+            }
+
+            match modules::account::probe_data_dir_writable() {
+                Ok(dir) => info!("数据目录可写: {}", dir.display()),
+                Err(e) => logger::log_error(&e),
+            }
             
             // 启动时同步：读取共享配置文件，与本地配置比较时间戳后合并
             {
@@ -156,6 +173,8 @@ pub fn run() {
             
             // System Commands
             commands::system::open_data_folder,
+            commands::system::check_data_dir_writable,
+            commands::system::get_recent_logs,
             commands::system::save_text_file,
             commands::system::get_downloads_dir,
             commands::system::get_network_config,
@@ -201,16 +220,46 @@ pub fn run() {
             commands::codex::delete_codex_accounts,
             commands::codex::import_codex_from_local,
             commands::codex::import_codex_from_json,
+            commands::codex::import_codex_accounts,
             commands::codex::export_codex_accounts,
             commands::codex::refresh_codex_quota,
             commands::codex::refresh_all_codex_quotas,
+            commands::codex::refresh_all_codex_quotas_report,
+            commands::codex::get_codex_aggregate_quota,
             commands::codex::refresh_current_codex_quota,
+            commands::codex::probe_codex_quota,
+            commands::codex::get_codex_accounts_needing_attention,
+            commands::codex::get_codex_quota_history,
+            commands::codex::detect_codex_quota_anomalies,
+            commands::codex::estimate_codex_quota_time_to_empty,
             commands::codex::codex_trigger_wakeup,
+            commands::codex::codex_quick_test,
+            commands::codex::codex_wakeup_replay_history_item,
+            commands::codex::codex_wake_all_low,
+            commands::codex::codex_trigger_wakeup_batch,
+            commands::codex::codex_wakeup_dry_run,
+            commands::codex::codex_clear_wakeup_reservations,
+            commands::codex::codex_suggest_wakeup_time,
             commands::codex::codex_fetch_available_models,
+            commands::codex::codex_list_cli_models,
+            commands::codex::codex_check_cli,
+            commands::codex::codex_wakeup_last_debug,
             commands::codex::codex_wakeup_sync_state,
+            commands::codex::codex_wakeup_update_schedule_entry,
+            commands::codex::codex_wakeup_set_scheduler_enabled,
+            commands::codex::codex_wakeup_scheduler_status,
             commands::codex::codex_wakeup_load_history,
             commands::codex::codex_wakeup_clear_history,
+            commands::codex::codex_wakeup_repair_history,
+            commands::codex::codex_wakeup_success_rate,
+            commands::codex::codex_wakeup_history_by_day,
             commands::codex::codex_wakeup_add_history_items,
+            commands::codex::codex_wakeup_import_history_csv,
+            commands::codex::codex_wakeup_export_history_csv,
+            commands::codex::export_codex_support_bundle,
+            commands::codex::get_codex_prometheus_metrics,
+            commands::codex::codex_wakeup_get_settings,
+            commands::codex::codex_wakeup_save_settings,
             commands::codex::codex_oauth_login_start,
             commands::codex::codex_oauth_login_completed,
             commands::codex::codex_oauth_login_cancel,
@@ -218,6 +267,17 @@ pub fn run() {
             commands::codex::is_codex_oauth_port_in_use,
             commands::codex::close_codex_oauth_port,
             commands::codex::update_codex_account_tags,
+            commands::codex::set_codex_account_guards,
+            commands::codex::reset_codex_account_guards,
+            commands::codex::set_codex_account_header_strategy,
+            commands::codex::set_codex_account_cli_path,
+            commands::codex::verify_codex_accounts_store,
+            commands::codex::set_codex_account_paused_until,
+            commands::codex::pause_codex_accounts_until_weekly_reset,
+            commands::codex::get_codex_account_encryption_status,
+            commands::codex::enable_codex_account_encryption,
+            commands::codex::disable_codex_account_encryption,
+            commands::codex::unlock_codex_accounts,
 
             // GitHub Copilot Commands
             commands::github_copilot::list_github_copilot_accounts,
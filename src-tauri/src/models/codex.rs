@@ -11,10 +11,92 @@ pub struct CodexAccount {
     pub tokens: CodexTokens,
     pub quota: Option<CodexQuota>,
     pub tags: Option<Vec<String>>,
+    /// 账号级唤醒保护阈值（为空则使用全局默认）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guards: Option<AccountGuards>,
+    /// 配额请求的 ChatGPT-Account-Id 请求头策略
+    #[serde(default, skip_serializing_if = "AccountHeaderStrategy::is_auto")]
+    pub account_header: AccountHeaderStrategy,
+    /// 账号级 Codex CLI 路径（优先于 CODEX_CLI_PATH 与 PATH 搜索）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cli_path: Option<String>,
+    /// 暂停唤醒直到该时间（Unix 秒），到期后自动恢复
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paused_until: Option<i64>,
+    /// 最近一次配额刷新失败的信息，刷新成功后清除
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota_error: Option<CodexQuotaError>,
     pub created_at: i64,
     pub last_used: i64,
 }
 
+/// 配额刷新失败的分类，前端据此提示重新登录或重试
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum CodexQuotaErrorKind {
+    /// 凭据失效或无法刷新，需要重新登录
+    AuthExpired,
+    /// 429 或服务端报告的限流
+    RateLimited,
+    /// 连接失败、5xx 等网络问题
+    Network,
+    /// 请求超时
+    Timeout,
+    /// 响应无法解析
+    Parse,
+    #[default]
+    Unknown,
+}
+
+/// 配额刷新失败记录
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CodexQuotaError {
+    /// 错误类别："timeout" 表示请求超时（可重试），其余为 "error"
+    pub code: String,
+    /// 细分的错误类型（旧记录缺失时为 Unknown）
+    #[serde(default)]
+    pub kind: CodexQuotaErrorKind,
+    pub message: String,
+    pub timestamp: i64,
+}
+
+/// 账号级唤醒保护阈值，未设置的字段回退到全局默认值
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountGuards {
+    /// 重复唤醒抑制窗口（毫秒）
+    #[serde(default)]
+    pub duplicate_window_ms: Option<i64>,
+    /// 每日最多唤醒次数（本地日期）
+    #[serde(default)]
+    pub daily_cap: Option<u32>,
+    /// 两次成功唤醒之间的最小间隔（毫秒）
+    #[serde(default)]
+    pub cooldown_ms: Option<i64>,
+    /// 目标窗口剩余配额低于该百分比时拒绝唤醒
+    #[serde(default)]
+    pub budget_floor_percent: Option<i32>,
+}
+
+/// 配额请求 ChatGPT-Account-Id 请求头策略
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "mode", content = "accountId", rename_all = "camelCase")]
+pub enum AccountHeaderStrategy {
+    /// 能推导出账号 id 时发送（默认行为）
+    #[default]
+    Auto,
+    /// 始终发送指定的账号 id
+    Always(String),
+    /// 从不发送（使用默认组织）
+    Never,
+}
+
+impl AccountHeaderStrategy {
+    pub fn is_auto(&self) -> bool {
+        *self == AccountHeaderStrategy::Auto
+    }
+}
+
 /// Codex Token 数据
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CodexTokens {
@@ -25,7 +107,10 @@ pub struct CodexTokens {
 }
 
 /// Codex 配额数据（5小时配额 + 周配额）
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// 序列化时额外输出 `hourly_reset_at_iso` / `weekly_reset_at_iso` / `code_review_reset_at_iso`
+/// （RFC3339 UTC，缺失为 null）
+#[derive(Debug, Clone, Deserialize)]
 pub struct CodexQuota {
     /// 5小时配额百分比 (0-100)
     pub hourly_percentage: i32,
@@ -36,8 +121,83 @@ pub struct CodexQuota {
     /// 周配额重置时间 (Unix timestamp)
     pub weekly_reset_time: Option<i64>,
     /// 原始响应数据
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
     pub raw_data: Option<serde_json::Value>,
+    /// 响应只包含部分窗口（缺少 5 小时或周窗口）
+    #[serde(default)]
+    pub partial: bool,
+    /// 缺失且标记为未知的窗口（"hourly" / "weekly"），对应百分比仅为占位值
+    #[serde(default)]
+    pub unknown_windows: Vec<String>,
+    /// 代码审查配额百分比 (0-100)，服务端未返回时为空
+    #[serde(default)]
+    pub code_review_percentage: Option<i32>,
+    /// 代码审查配额重置时间 (Unix timestamp)
+    #[serde(default)]
+    pub code_review_reset_time: Option<i64>,
+    /// 5小时配额距重置的秒数（获取时的相对值，不受本机时钟偏差影响）
+    #[serde(default)]
+    pub hourly_reset_after_seconds: Option<i64>,
+    /// 5小时窗口长度（秒）
+    #[serde(default)]
+    pub hourly_window_seconds: Option<i64>,
+    /// 周配额距重置的秒数
+    #[serde(default)]
+    pub weekly_reset_after_seconds: Option<i64>,
+    /// 周窗口长度（秒）
+    #[serde(default)]
+    pub weekly_window_seconds: Option<i64>,
+    /// 用量接口返回的订阅类型（plus / pro 等）
+    #[serde(default)]
+    pub plan_type: Option<String>,
+    /// 服务端是否允许继续请求（为 false 时账号被限流）
+    #[serde(default)]
+    pub allowed: Option<bool>,
+    /// 服务端报告已达到速率限制
+    #[serde(default)]
+    pub limit_reached: Option<bool>,
+}
+
+fn reset_time_iso(timestamp: Option<i64>) -> Option<String> {
+    timestamp
+        .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+        .map(|dt| dt.to_rfc3339_opts(chrono::SecondsFormat::Secs, true))
+}
+
+impl Serialize for CodexQuota {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let field_count = if self.raw_data.is_some() { 19 } else { 18 };
+        let mut state = serializer.serialize_struct("CodexQuota", field_count)?;
+        state.serialize_field("hourly_percentage", &self.hourly_percentage)?;
+        state.serialize_field("hourly_reset_time", &self.hourly_reset_time)?;
+        state.serialize_field("hourly_reset_at_iso", &reset_time_iso(self.hourly_reset_time))?;
+        state.serialize_field("weekly_percentage", &self.weekly_percentage)?;
+        state.serialize_field("weekly_reset_time", &self.weekly_reset_time)?;
+        state.serialize_field("weekly_reset_at_iso", &reset_time_iso(self.weekly_reset_time))?;
+        state.serialize_field("partial", &self.partial)?;
+        state.serialize_field("unknown_windows", &self.unknown_windows)?;
+        state.serialize_field("code_review_percentage", &self.code_review_percentage)?;
+        state.serialize_field("code_review_reset_time", &self.code_review_reset_time)?;
+        state.serialize_field(
+            "code_review_reset_at_iso",
+            &reset_time_iso(self.code_review_reset_time),
+        )?;
+        state.serialize_field("hourly_reset_after_seconds", &self.hourly_reset_after_seconds)?;
+        state.serialize_field("hourly_window_seconds", &self.hourly_window_seconds)?;
+        state.serialize_field("weekly_reset_after_seconds", &self.weekly_reset_after_seconds)?;
+        state.serialize_field("weekly_window_seconds", &self.weekly_window_seconds)?;
+        state.serialize_field("plan_type", &self.plan_type)?;
+        state.serialize_field("allowed", &self.allowed)?;
+        state.serialize_field("limit_reached", &self.limit_reached)?;
+        if let Some(raw_data) = &self.raw_data {
+            state.serialize_field("raw_data", raw_data)?;
+        } else {
+            state.skip_field("raw_data")?;
+        }
+        state.end()
+    }
 }
 
 /// ~/.codex/auth.json 文件格式
@@ -130,11 +290,32 @@ impl CodexAccount {
             tokens,
             quota: None,
             tags: None,
+            guards: None,
+            account_header: AccountHeaderStrategy::Auto,
+            cli_path: None,
+            paused_until: None,
+            quota_error: None,
             created_at: now,
             last_used: now,
         }
     }
 
+    /// 日志/历史中的账号标识：邮箱为空时回退为账号 id
+    pub fn display_label(&self) -> &str {
+        if self.email.trim().is_empty() {
+            &self.id
+        } else {
+            &self.email
+        }
+    }
+
+    /// 按历史/任务中保存的标识匹配账号（邮箱忽略大小写，或账号 id）
+    pub fn matches_label(&self, label: &str) -> bool {
+        let label = label.trim();
+        !label.is_empty()
+            && (self.display_label().eq_ignore_ascii_case(label) || self.id == label)
+    }
+
     pub fn update_last_used(&mut self) {
         self.last_used = chrono::Utc::now().timestamp();
     }
@@ -9,16 +9,22 @@ use crate::models::{Account, AccountIndex, AccountSummary, TokenData, QuotaData,
 use crate::modules;
 
 static ACCOUNT_INDEX_LOCK: std::sync::LazyLock<Mutex<()>> = std::sync::LazyLock::new(|| Mutex::new(()));
+/// 最近一次数据目录可写性探测结果（Some 表示不可写时的错误信息）
+static DATA_DIR_WRITE_ERROR: std::sync::RwLock<Option<String>> = std::sync::RwLock::new(None);
 
 // 使用与 AntigravityCockpit 插件相同的数据目录
 const DATA_DIR: &str = ".antigravity_cockpit";
 const ACCOUNTS_INDEX: &str = "accounts.json";
 const ACCOUNTS_DIR: &str = "accounts";
+/// 覆盖数据目录位置的环境变量
+const DATA_DIR_ENV: &str = "COCKPIT_TOOLS_DATA_DIR";
 
-/// 获取数据目录路径
+/// 获取数据目录路径（可通过 COCKPIT_TOOLS_DATA_DIR 覆盖）
 pub fn get_data_dir() -> Result<PathBuf, String> {
-    let home = dirs::home_dir().ok_or("无法获取用户主目录")?;
-    let data_dir = home.join(DATA_DIR);
+    let data_dir = match std::env::var_os(DATA_DIR_ENV).filter(|v| !v.is_empty()) {
+        Some(custom) => PathBuf::from(custom),
+        None => dirs::home_dir().ok_or("无法获取用户主目录")?.join(DATA_DIR),
+    };
     
     if !data_dir.exists() {
         fs::create_dir_all(&data_dir)
@@ -28,6 +34,33 @@ pub fn get_data_dir() -> Result<PathBuf, String> {
     Ok(data_dir)
 }
 
+/// 探测数据目录是否可写（启动时调用，结果会被缓存供写入前检查）
+pub fn probe_data_dir_writable() -> Result<PathBuf, String> {
+    let result = (|| -> Result<PathBuf, String> {
+        let data_dir = get_data_dir()?;
+        let probe_path = data_dir.join(format!(".write_probe_{}", std::process::id()));
+        fs::write(&probe_path, b"ok")
+            .and_then(|_| fs::remove_file(&probe_path))
+            .map_err(|e| format!("data directory is not writable: {} ({})", data_dir.display(), e))?;
+        Ok(data_dir)
+    })();
+    if let Ok(mut guard) = DATA_DIR_WRITE_ERROR.write() {
+        *guard = result.as_ref().err().cloned();
+    }
+    result
+}
+
+/// 写入前检查：若启动探测发现数据目录不可写，返回明确的错误
+pub fn ensure_data_dir_writable() -> Result<(), String> {
+    match DATA_DIR_WRITE_ERROR.read() {
+        Ok(guard) => match guard.as_ref() {
+            Some(err) => Err(err.clone()),
+            None => Ok(()),
+        },
+        Err(_) => Ok(()),
+    }
+}
+
 /// 获取账号目录路径
 pub fn get_accounts_dir() -> Result<PathBuf, String> {
     let data_dir = get_data_dir()?;
@@ -68,6 +101,7 @@ pub fn load_account_index() -> Result<AccountIndex, String> {
 
 /// 保存账号索引
 pub fn save_account_index(index: &AccountIndex) -> Result<(), String> {
+    ensure_data_dir_writable()?;
     let data_dir = get_data_dir()?;
     let index_path = data_dir.join(ACCOUNTS_INDEX);
     let temp_path = data_dir.join(format!("{}.tmp", ACCOUNTS_INDEX));
@@ -100,6 +134,7 @@ pub fn load_account(account_id: &str) -> Result<Account, String> {
 
 /// 保存账号数据
 pub fn save_account(account: &Account) -> Result<(), String> {
+    ensure_data_dir_writable()?;
     let accounts_dir = get_accounts_dir()?;
     let account_path = accounts_dir.join(format!("{}.json", account.id));
     
@@ -1,11 +1,28 @@
 use crate::models::codex::{
-    CodexAccount, CodexAccountIndex, CodexAccountSummary, CodexAuthFile, CodexAuthTokens,
+    AccountGuards, AccountHeaderStrategy, CodexAccount, CodexAccountIndex, CodexAccountSummary, CodexAuthFile, CodexAuthTokens,
     CodexJwtPayload, CodexTokens,
 };
-use crate::modules::{codex_oauth, logger};
+use crate::modules::{codex_account_crypto, codex_oauth, logger};
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// 每个账号一把写锁，串行化 load-modify-save，避免并发写入互相覆盖
+static ACCOUNT_WRITE_LOCKS: std::sync::LazyLock<Mutex<HashMap<String, Arc<Mutex<()>>>>> =
+    std::sync::LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn account_write_lock(account_id: &str) -> Arc<Mutex<()>> {
+    let mut locks = ACCOUNT_WRITE_LOCKS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    locks
+        .entry(account_id.to_string())
+        .or_insert_with(|| Arc::new(Mutex::new(())))
+        .clone()
+}
 
 /// 获取 Codex 数据目录
 pub fn get_codex_home() -> PathBuf {
@@ -17,21 +34,36 @@ pub fn get_auth_json_path() -> PathBuf {
     get_codex_home().join("auth.json")
 }
 
-/// 获取我们的多账号存储路径
-fn get_accounts_storage_path() -> PathBuf {
+/// 测试时替换数据根目录，避免读写开发者真实的账号存储
+#[cfg(test)]
+static TEST_DATA_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// 获取 cockpit-tools 数据根目录
+pub(crate) fn get_data_root() -> PathBuf {
+    #[cfg(test)]
+    if let Some(dir) = TEST_DATA_DIR
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+    {
+        fs::create_dir_all(&dir).ok();
+        return dir;
+    }
     let data_dir = dirs::data_local_dir()
         .unwrap_or_else(|| dirs::home_dir().expect("无法获取用户目录"))
         .join("com.antigravity.cockpit-tools");
     fs::create_dir_all(&data_dir).ok();
-    data_dir.join("codex_accounts.json")
+    data_dir
+}
+
+/// 获取我们的多账号存储路径
+fn get_accounts_storage_path() -> PathBuf {
+    get_data_root().join("codex_accounts.json")
 }
 
 /// 获取账号详情存储目录
 fn get_accounts_dir() -> PathBuf {
-    let data_dir = dirs::data_local_dir()
-        .unwrap_or_else(|| dirs::home_dir().expect("无法获取用户目录"))
-        .join("com.antigravity.cockpit-tools")
-        .join("codex_accounts");
+    let data_dir = get_data_root().join("codex_accounts");
     fs::create_dir_all(&data_dir).ok();
     data_dir
 }
@@ -54,7 +86,8 @@ pub fn decode_jwt_payload(token: &str) -> Result<CodexJwtPayload, String> {
     Ok(payload)
 }
 
-fn decode_jwt_payload_value(token: &str) -> Option<serde_json::Value> {
+/// 解析 JWT payload 为通用 JSON（格式不合法时返回 None）
+pub fn decode_jwt_payload_value(token: &str) -> Option<serde_json::Value> {
     let parts: Vec<&str> = token.split('.').collect();
     if parts.len() != 3 {
         return None;
@@ -74,6 +107,40 @@ pub fn extract_chatgpt_account_id_from_access_token(access_token: &str) -> Optio
         .map(|value| value.to_string())
 }
 
+fn jwt_subject(token: &str) -> Option<String> {
+    decode_jwt_payload_value(token)?
+        .get("sub")
+        .and_then(|v| v.as_str())
+        .map(|value| value.to_string())
+}
+
+/// 账号的规范凭据：配额请求与 CLI 唤醒都只从这里取 Token。
+///
+/// 能解析出 `sub` 的 Token（access/id，以及 JWT 形式的 refresh token）必须属于同一用户，
+/// 否则报错，避免发送混搭的凭据。
+pub fn canonical_tokens(account: &CodexAccount) -> Result<&CodexTokens, String> {
+    let tokens = &account.tokens;
+    let Some(access_sub) = jwt_subject(&tokens.access_token) else {
+        return Ok(tokens);
+    };
+    let others = [
+        ("id_token", Some(tokens.id_token.as_str())),
+        ("refresh_token", tokens.refresh_token.as_deref()),
+    ];
+    for (name, token) in others {
+        if let Some(sub) = token.and_then(jwt_subject) {
+            if sub != access_sub {
+                return Err(format!(
+                    "账号 {} 的 access_token 与 {} 属于不同用户，请重新登录该账号",
+                    account.display_label(),
+                    name
+                ));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
 /// 从 id_token 提取用户信息
 pub fn extract_user_info(
     id_token: &str,
@@ -115,28 +182,109 @@ pub fn save_account_index(index: &CodexAccountIndex) -> Result<(), String> {
     Ok(())
 }
 
-/// 读取单个账号详情
-pub fn load_account(account_id: &str) -> Option<CodexAccount> {
+/// 读取单个账号详情；加密存储无法解密时返回错误（文件保持不动）
+pub fn try_load_account(account_id: &str) -> Result<Option<CodexAccount>, String> {
     let path = get_accounts_dir().join(format!("{}.json", account_id));
     if !path.exists() {
-        return None;
+        return Ok(None);
     }
 
-    match fs::read_to_string(&path) {
-        Ok(content) => serde_json::from_str(&content).ok(),
-        Err(_) => None,
-    }
+    let content = fs::read_to_string(&path).map_err(|e| format!("读取文件失败: {}", e))?;
+    let content = codex_account_crypto::open(&content)?;
+    Ok(serde_json::from_str(&content).ok())
+}
+
+/// 读取单个账号详情
+pub fn load_account(account_id: &str) -> Option<CodexAccount> {
+    try_load_account(account_id).unwrap_or_else(|e| {
+        logger::log_error(&format!("读取 Codex 账号 {} 失败: {}", account_id, e));
+        None
+    })
 }
 
 /// 保存单个账号详情
 pub fn save_account(account: &CodexAccount) -> Result<(), String> {
+    if account.email.trim().is_empty() {
+        logger::log_warn(&format!("Codex 账号 {} 缺少邮箱，将以账号 id 作为标识", account.id));
+    }
     let path = get_accounts_dir().join(format!("{}.json", &account.id));
     let content =
         serde_json::to_string_pretty(account).map_err(|e| format!("序列化失败: {}", e))?;
+    // 密钥不可用时 seal 直接报错，不会用明文覆盖已加密的文件
+    let content = codex_account_crypto::seal(&content)?;
     fs::write(&path, content).map_err(|e| format!("写入文件失败: {}", e))?;
+    if codex_account_crypto::needs_migration() {
+        encrypt_plaintext_accounts()?;
+    }
     Ok(())
 }
 
+/// 启用加密后首次保存时，把仍为明文的账号文件全部加密
+fn encrypt_plaintext_accounts() -> Result<(), String> {
+    let entries = fs::read_dir(get_accounts_dir()).map_err(|e| format!("读取账号目录失败: {}", e))?;
+    let mut migrated = 0usize;
+    for path in entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+    {
+        let content = fs::read_to_string(&path).map_err(|e| format!("读取文件失败: {}", e))?;
+        if codex_account_crypto::is_sealed(&content) {
+            continue;
+        }
+        let sealed = codex_account_crypto::seal(&content)?;
+        fs::write(&path, sealed).map_err(|e| format!("写入文件失败: {}", e))?;
+        migrated += 1;
+    }
+    codex_account_crypto::mark_migrated()?;
+    logger::log_info(&format!("已加密 {} 个明文 Codex 账号文件", migrated));
+    Ok(())
+}
+
+/// 启用账号文件加密；已有明文文件立即迁移
+pub fn enable_account_encryption(
+    key_source: codex_account_crypto::KeySource,
+    passphrase: Option<&str>,
+) -> Result<(), String> {
+    codex_account_crypto::enable(key_source, passphrase)?;
+    encrypt_plaintext_accounts()
+}
+
+/// 关闭账号文件加密：先全部解密写回明文，成功后才删除加密配置
+pub fn disable_account_encryption() -> Result<(), String> {
+    if !codex_account_crypto::is_enabled() {
+        return Ok(());
+    }
+    let entries = fs::read_dir(get_accounts_dir()).map_err(|e| format!("读取账号目录失败: {}", e))?;
+    let mut decrypted = Vec::new();
+    for path in entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+    {
+        let content = fs::read_to_string(&path).map_err(|e| format!("读取文件失败: {}", e))?;
+        if codex_account_crypto::is_sealed(&content) {
+            decrypted.push((path, codex_account_crypto::open(&content)?));
+        }
+    }
+    for (path, content) in decrypted {
+        fs::write(&path, content).map_err(|e| format!("写入文件失败: {}", e))?;
+    }
+    codex_account_crypto::remove_config()
+}
+
+/// 在账号写锁内重新读取账号、应用修改并保存，返回保存后的账号
+pub fn update_account<F>(account_id: &str, mutate: F) -> Result<CodexAccount, String>
+where
+    F: FnOnce(&mut CodexAccount),
+{
+    let lock = account_write_lock(account_id);
+    let _guard = lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut account =
+        try_load_account(account_id)?.ok_or_else(|| format!("账号不存在: {}", account_id))?;
+    mutate(&mut account);
+    save_account(&account)?;
+    Ok(account)
+}
+
 /// 删除单个账号
 pub fn delete_account_file(account_id: &str) -> Result<(), String> {
     let path = get_accounts_dir().join(format!("{}.json", account_id));
@@ -146,6 +294,15 @@ pub fn delete_account_file(account_id: &str) -> Result<(), String> {
     Ok(())
 }
 
+/// 列出所有账号；口令加密的存储尚未解锁时返回 [`codex_account_crypto::LOCKED_ERROR`]，
+/// 而不是空列表，前端据此提示解锁
+pub fn try_list_accounts() -> Result<Vec<CodexAccount>, String> {
+    if codex_account_crypto::is_locked() {
+        return Err(codex_account_crypto::LOCKED_ERROR.to_string());
+    }
+    Ok(list_accounts())
+}
+
 /// 列出所有账号
 pub fn list_accounts() -> Vec<CodexAccount> {
     let index = load_account_index();
@@ -275,6 +432,7 @@ fn build_auth_file(account: &CodexAccount) -> CodexAuthFile {
 }
 
 pub fn write_auth_file_to_dir(base_dir: &Path, account: &CodexAccount) -> Result<(), String> {
+    canonical_tokens(account)?;
     let auth_path = base_dir.join("auth.json");
     if let Some(parent) = auth_path.parent() {
         fs::create_dir_all(parent).ok();
@@ -290,13 +448,12 @@ pub fn write_auth_file_to_dir(base_dir: &Path, account: &CodexAccount) -> Result
 pub async fn prepare_account_for_injection(account_id: &str) -> Result<CodexAccount, String> {
     let mut account = load_account(account_id).ok_or_else(|| format!("账号不存在: {}", account_id))?;
     if codex_oauth::is_token_expired(&account.tokens.access_token) {
-        logger::log_info(&format!("账号 {} 的 Token 已过期，尝试刷新", account.email));
+        logger::log_info(&format!("账号 {} 的 Token 已过期，尝试刷新", account.display_label()));
         if let Some(ref refresh_token) = account.tokens.refresh_token {
             match codex_oauth::refresh_access_token(refresh_token).await {
                 Ok(new_tokens) => {
-                    logger::log_info(&format!("账号 {} 的 Token 刷新成功", account.email));
-                    account.tokens = new_tokens;
-                    save_account(&account)?;
+                    logger::log_info(&format!("账号 {} 的 Token 刷新成功", account.display_label()));
+                    account = update_account(account_id, |acc| acc.tokens = new_tokens)?;
                 }
                 Err(e) => {
                     logger::log_error(&format!("账号 {} Token 刷新失败: {}", account.email, e));
@@ -321,9 +478,7 @@ pub fn switch_account(account_id: &str) -> Result<CodexAccount, String> {
     save_account_index(&index)?;
 
     // 更新账号的 last_used
-    let mut updated_account = account.clone();
-    updated_account.update_last_used();
-    save_account(&updated_account)?;
+    let updated_account = update_account(account_id, |acc| acc.update_last_used())?;
 
     logger::log_info(&format!("已切换到 Codex 账号: {}", account.email));
 
@@ -378,6 +533,245 @@ pub fn import_from_json(json_content: &str) -> Result<Vec<CodexAccount>, String>
     Err("无法解析 JSON 内容".to_string())
 }
 
+/// 批量导入时与已有账号（按邮箱匹配）冲突的处理方式
+#[derive(Debug, Clone, Copy, Default, Serialize, serde::Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// 保留已有账号，跳过导入项
+    #[default]
+    Skip,
+    /// 用导入项的 Token 和配置覆盖已有账号
+    Overwrite,
+    /// 保留两者：导入项以新 id 保存，邮箱不变，并加上 "copy n" 标签以示区分
+    Rename,
+}
+
+/// 批量导入的单个账号（与导出格式兼容，未知字段忽略）
+#[derive(Debug, Clone, serde::Deserialize)]
+struct ImportAccountEntry {
+    #[serde(default)]
+    email: Option<String>,
+    tokens: CodexTokens,
+    #[serde(default)]
+    tags: Option<Vec<String>>,
+    #[serde(default)]
+    guards: Option<AccountGuards>,
+    #[serde(default)]
+    account_header: Option<AccountHeaderStrategy>,
+    #[serde(default)]
+    cli_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportStatus {
+    Imported,
+    Overwritten,
+    Renamed,
+    Skipped,
+    Invalid,
+}
+
+/// 单个导入项的结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportResult {
+    /// 在输入数组中的位置
+    pub index: usize,
+    pub email: Option<String>,
+    pub account_id: Option<String>,
+    pub status: ImportStatus,
+    pub error: Option<String>,
+}
+
+/// 批量导入报告
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub imported: usize,
+    pub overwritten: usize,
+    pub renamed: usize,
+    pub skipped: usize,
+    pub invalid: usize,
+    pub results: Vec<ImportResult>,
+}
+
+/// 校验导入项的 Token 可解析且属于同一用户，返回 (邮箱, user_id, plan_type, account_id)
+fn validate_import_entry(
+    entry: &ImportAccountEntry,
+) -> Result<(String, Option<String>, Option<String>, Option<String>), String> {
+    if entry.tokens.access_token.trim().is_empty() {
+        return Err("缺少 access_token".to_string());
+    }
+    if decode_jwt_payload_value(&entry.tokens.access_token).is_none() {
+        return Err("access_token 不是可解析的 JWT".to_string());
+    }
+    let (email, user_id, plan_type, _) =
+        extract_user_info(&entry.tokens.id_token).map_err(|e| format!("id_token 无法解析: {}", e))?;
+    if let Some(declared) = entry.email.as_deref().map(str::trim).filter(|e| !e.is_empty()) {
+        if !declared.eq_ignore_ascii_case(&email) {
+            return Err(format!("声明的邮箱 {} 与 id_token 中的 {} 不一致", declared, email));
+        }
+    }
+    let probe = CodexAccount::new(String::new(), email.clone(), entry.tokens.clone());
+    canonical_tokens(&probe)?;
+    let account_id = extract_chatgpt_account_id_from_access_token(&entry.tokens.access_token);
+    Ok((email, user_id, plan_type, account_id))
+}
+
+fn apply_import_config(account: &mut CodexAccount, entry: &ImportAccountEntry) {
+    if entry.tags.is_some() {
+        account.tags = entry.tags.clone();
+    }
+    if entry.guards.is_some() {
+        account.guards = entry.guards.clone();
+    }
+    if let Some(strategy) = &entry.account_header {
+        account.account_header = strategy.clone();
+    }
+    if entry.cli_path.is_some() {
+        account.cli_path = entry.cli_path.clone();
+    }
+}
+
+/// 重命名导入副本的标签前缀，完整标签为 "copy n"
+const RENAMED_COPY_TAG_PREFIX: &str = "copy ";
+
+/// 是否为 Rename 策略导入的同邮箱副本（带 "copy n" 标签）
+fn is_renamed_copy(account: &CodexAccount) -> bool {
+    account.tags.iter().flatten().any(|tag| {
+        tag.strip_prefix(RENAMED_COPY_TAG_PREFIX)
+            .is_some_and(|n| n.parse::<u32>().is_ok())
+    })
+}
+
+/// 重命名导入的账号 id 与区分标签：取首个 id 未被索引占用的 "邮箱 (n)"（n 从 2 开始），
+/// 邮箱保持不变，标签 "copy n" 用于在列表中区分同邮箱账号
+fn renamed_identity(index: &CodexAccountIndex, email: &str) -> (String, String) {
+    (2..)
+        .map(|n| {
+            let id = format!("codex_{:x}", md5::compute(format!("{} ({})", email, n).as_bytes()));
+            (id, format!("{}{}", RENAMED_COPY_TAG_PREFIX, n))
+        })
+        .find(|(id, _)| !index.accounts.iter().any(|a| &a.id == id))
+        .expect("unbounded range always yields a free id")
+}
+
+/// 从 JSON 数组批量导入账号：逐项校验 Token，按冲突策略处理同邮箱账号。
+/// 无效项记录在报告中，不会中断其余导入；只有顶层不是数组或索引写入失败时返回错误。
+pub fn import_accounts(json_content: &str, on_conflict: ConflictPolicy) -> Result<ImportReport, String> {
+    let entries: Vec<serde_json::Value> =
+        serde_json::from_str(json_content).map_err(|e| format!("导入内容必须是账号数组: {}", e))?;
+    let mut index = load_account_index();
+    let mut results = Vec::new();
+
+    for (position, value) in entries.into_iter().enumerate() {
+        let outcome = serde_json::from_value::<ImportAccountEntry>(value)
+            .map_err(|e| format!("账号格式无效: {}", e))
+            .and_then(|entry| {
+                let (email, user_id, plan_type, account_id) = validate_import_entry(&entry)?;
+                let existing = index
+                    .accounts
+                    .iter()
+                    .find(|a| a.email.eq_ignore_ascii_case(&email))
+                    .map(|a| a.id.clone());
+                let fill = |account: &mut CodexAccount| {
+                    account.user_id = user_id.clone();
+                    account.plan_type = plan_type.clone();
+                    account.account_id = account_id.clone();
+                    apply_import_config(account, &entry);
+                };
+                let create = |id: String, tag: Option<String>| -> Result<CodexAccount, String> {
+                    if get_accounts_dir().join(format!("{}.json", id)).exists() {
+                        return Err(format!(
+                            "账号文件 {}.json 已存在但不在索引中，请先检查账号存储",
+                            id
+                        ));
+                    }
+                    let mut account = CodexAccount::new(id, email.clone(), entry.tokens.clone());
+                    fill(&mut account);
+                    if let Some(tag) = tag {
+                        account.tags.get_or_insert_with(Vec::new).push(tag);
+                    }
+                    save_account(&account)?;
+                    Ok(account)
+                };
+                let (account, status) = match (existing, on_conflict) {
+                    (Some(existing_id), ConflictPolicy::Skip) => {
+                        return Ok((Some(email), Some(existing_id), ImportStatus::Skipped));
+                    }
+                    (Some(existing_id), ConflictPolicy::Overwrite) => {
+                        let account = if try_load_account(&existing_id)?.is_some() {
+                            update_account(&existing_id, |account| {
+                                account.tokens = entry.tokens.clone();
+                                fill(account);
+                            })?
+                        } else {
+                            // 索引中有记录但详情文件缺失，按原 id 重新写入
+                            create(existing_id, None)?
+                        };
+                        (account, ImportStatus::Overwritten)
+                    }
+                    (Some(_), ConflictPolicy::Rename) => {
+                        let (id, tag) = renamed_identity(&index, &email);
+                        (create(id, Some(tag))?, ImportStatus::Renamed)
+                    }
+                    (None, _) => {
+                        let id = format!("codex_{:x}", md5::compute(email.as_bytes()));
+                        (create(id, None)?, ImportStatus::Imported)
+                    }
+                };
+
+                match index.accounts.iter_mut().find(|a| a.id == account.id) {
+                    Some(summary) => {
+                        summary.plan_type = account.plan_type.clone();
+                        summary.last_used = account.last_used;
+                    }
+                    None => index.accounts.push(CodexAccountSummary {
+                        id: account.id.clone(),
+                        email: account.email.clone(),
+                        plan_type: account.plan_type.clone(),
+                        created_at: account.created_at,
+                        last_used: account.last_used,
+                    }),
+                }
+                Ok((Some(account.email), Some(account.id), status))
+            });
+        results.push(match outcome {
+            Ok((email, account_id, status)) => ImportResult {
+                index: position,
+                email,
+                account_id,
+                status,
+                error: None,
+            },
+            Err(error) => ImportResult {
+                index: position,
+                email: None,
+                account_id: None,
+                status: ImportStatus::Invalid,
+                error: Some(error),
+            },
+        });
+    }
+
+    save_account_index(&index)?;
+    let count = |status: ImportStatus| results.iter().filter(|r| r.status == status).count();
+    let report = ImportReport {
+        imported: count(ImportStatus::Imported),
+        overwritten: count(ImportStatus::Overwritten),
+        renamed: count(ImportStatus::Renamed),
+        skipped: count(ImportStatus::Skipped),
+        invalid: count(ImportStatus::Invalid),
+        results,
+    };
+    logger::log_info(&format!(
+        "Codex 批量导入完成: 新增 {}, 覆盖 {}, 重命名 {}, 跳过 {}, 无效 {}",
+        report.imported, report.overwritten, report.renamed, report.skipped, report.invalid
+    ));
+    Ok(report)
+}
+
 /// 导出账号为 JSON
 pub fn export_accounts(account_ids: &[String]) -> Result<String, String> {
     let accounts: Vec<CodexAccount> = account_ids
@@ -389,11 +783,542 @@ pub fn export_accounts(account_ids: &[String]) -> Result<String, String> {
 }
 
 pub fn update_account_tags(account_id: &str, tags: Vec<String>) -> Result<CodexAccount, String> {
-    let mut account =
-        load_account(account_id).ok_or_else(|| format!("账号不存在: {}", account_id))?;
+    update_account(account_id, |account| account.tags = Some(tags))
+}
 
-    account.tags = Some(tags);
-    save_account(&account)?;
+/// 设置账号级唤醒保护阈值
+pub fn set_account_guards(account_id: &str, guards: AccountGuards) -> Result<CodexAccount, String> {
+    update_account(account_id, |account| account.guards = Some(guards))
+}
 
-    Ok(account)
+/// 清除账号级唤醒保护阈值，回退到全局默认
+pub fn reset_account_guards(account_id: &str) -> Result<CodexAccount, String> {
+    update_account(account_id, |account| account.guards = None)
+}
+
+/// 设置配额请求的 ChatGPT-Account-Id 请求头策略
+pub fn set_account_header_strategy(
+    account_id: &str,
+    strategy: AccountHeaderStrategy,
+) -> Result<CodexAccount, String> {
+    if let AccountHeaderStrategy::Always(id) = &strategy {
+        if id.trim().is_empty() {
+            return Err("Always 策略需要提供账号 id".to_string());
+        }
+    }
+    update_account(account_id, |account| account.account_header = strategy)
+}
+
+/// 设置账号级 Codex CLI 路径（传空则清除，回退到全局解析）
+pub fn set_account_cli_path(
+    account_id: &str,
+    cli_path: Option<String>,
+) -> Result<CodexAccount, String> {
+    let cli_path = cli_path
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty());
+    update_account(account_id, |account| account.cli_path = cli_path)
+}
+
+/// 设置账号暂停唤醒的截止时间（Unix 秒，传空则立即恢复）
+pub fn set_account_paused_until(
+    account_id: &str,
+    paused_until: Option<i64>,
+) -> Result<CodexAccount, String> {
+    update_account(account_id, |account| account.paused_until = paused_until)
+}
+
+/// 单个账号“暂停到周重置”的结果
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PauseResult {
+    pub account_id: String,
+    /// 设置的暂停截止时间（Unix 秒）；跳过时为空
+    pub paused_until: Option<i64>,
+    pub skipped_reason: Option<String>,
+}
+
+/// 账号已知且尚未过去的周配额重置时间
+fn upcoming_weekly_reset(account: &CodexAccount, now: i64) -> Result<i64, String> {
+    let reset = account
+        .quota
+        .as_ref()
+        .ok_or("没有已保存的配额数据")?
+        .weekly_reset_time
+        .ok_or("配额数据中没有周重置时间")?;
+    if reset <= now {
+        return Err("已保存的周重置时间已过期，请先刷新配额".to_string());
+    }
+    Ok(reset)
+}
+
+/// 将每个账号暂停到其已保存的周配额重置时间；没有可用重置时间的账号跳过并说明原因
+pub fn pause_until_weekly_reset(account_ids: &[String]) -> Vec<PauseResult> {
+    let now = chrono::Utc::now().timestamp();
+    account_ids
+        .iter()
+        .map(|account_id| {
+            let outcome = try_load_account(account_id)
+                .and_then(|account| account.ok_or_else(|| format!("账号不存在: {}", account_id)))
+                .and_then(|account| upcoming_weekly_reset(&account, now))
+                .and_then(|reset| set_account_paused_until(account_id, Some(reset)).map(|_| reset));
+            match outcome {
+                Ok(reset) => PauseResult {
+                    account_id: account_id.clone(),
+                    paused_until: Some(reset),
+                    skipped_reason: None,
+                },
+                Err(reason) => PauseResult {
+                    account_id: account_id.clone(),
+                    paused_until: None,
+                    skipped_reason: Some(reason),
+                },
+            }
+        })
+        .collect()
+}
+
+/// 单个账号文件的完整性检查结果
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StoreEntryReport {
+    /// 文件名（去掉 .json）对应的账号 id
+    pub id: String,
+    pub email: Option<String>,
+    /// 文件可读、可解密且能解析为账号
+    pub valid: bool,
+    pub error: Option<String>,
+    /// 缺失或为空的必需 Token 字段
+    pub missing_fields: Vec<String>,
+    /// 索引中有记录但账号文件不存在
+    pub missing_file: bool,
+    /// 账号文件存在但索引中没有记录
+    pub not_in_index: bool,
+}
+
+/// 账号存储完整性报告（只读检查，不修改任何文件）
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StoreReport {
+    /// 索引文件解析失败的原因（文件不存在不算错误）
+    pub index_error: Option<String>,
+    pub entries: Vec<StoreEntryReport>,
+    pub valid_count: usize,
+    pub corrupt_count: usize,
+    /// 在索引或账号文件中出现多次的账号 id
+    pub duplicate_ids: Vec<String>,
+    /// 被多个账号共用的邮箱（不区分大小写，不含带 "copy n" 标签的重命名副本）
+    pub duplicate_emails: Vec<String>,
+}
+
+impl StoreReport {
+    pub fn is_healthy(&self) -> bool {
+        self.index_error.is_none()
+            && self.corrupt_count == 0
+            && self.duplicate_ids.is_empty()
+            && self.duplicate_emails.is_empty()
+            && self
+                .entries
+                .iter()
+                .all(|e| e.missing_fields.is_empty() && !e.missing_file && !e.not_in_index)
+    }
+}
+
+fn missing_token_fields(tokens: &CodexTokens) -> Vec<String> {
+    let mut missing = Vec::new();
+    if tokens.id_token.trim().is_empty() {
+        missing.push("id_token".to_string());
+    }
+    if tokens.access_token.trim().is_empty() {
+        missing.push("access_token".to_string());
+    }
+    if tokens.refresh_token.as_deref().is_none_or(|t| t.trim().is_empty()) {
+        missing.push("refresh_token".to_string());
+    }
+    missing
+}
+
+fn sorted_duplicates<'a>(values: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut duplicates: Vec<String> = values
+        .filter(|v| !seen.insert(*v))
+        .map(str::to_string)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    duplicates.sort();
+    duplicates
+}
+
+/// 根据索引内容与各账号文件内容（文件 id -> 已解密内容或读取错误）生成报告
+fn build_store_report(
+    index_content: Option<&str>,
+    files: Vec<(String, Result<String, String>)>,
+) -> StoreReport {
+    let (index, index_error) = match index_content {
+        None => (CodexAccountIndex::new(), None),
+        Some(content) => match serde_json::from_str::<CodexAccountIndex>(content) {
+            Ok(index) => (index, None),
+            Err(e) => (CodexAccountIndex::new(), Some(format!("索引解析失败: {}", e))),
+        },
+    };
+    let index_ids: HashSet<&str> = index.accounts.iter().map(|a| a.id.as_str()).collect();
+
+    let mut entries = Vec::new();
+    let mut stored_ids: Vec<String> = Vec::new();
+    let mut emails: Vec<String> = Vec::new();
+    for (id, content) in files {
+        let parsed = content.and_then(|content| {
+            serde_json::from_str::<CodexAccount>(&content).map_err(|e| format!("JSON 解析失败: {}", e))
+        });
+        let not_in_index = !index_ids.contains(id.as_str());
+        let entry = match parsed {
+            Ok(account) => {
+                let mut error = None;
+                if account.id != id {
+                    error = Some(format!("文件内账号 id 为 {}，与文件名不一致", account.id));
+                }
+                stored_ids.push(account.id.clone());
+                // 重命名导入的副本有意与原账号共用邮箱
+                if !account.email.trim().is_empty() && !is_renamed_copy(&account) {
+                    emails.push(account.email.trim().to_lowercase());
+                }
+                StoreEntryReport {
+                    id,
+                    email: Some(account.email.clone()),
+                    valid: error.is_none(),
+                    error,
+                    missing_fields: missing_token_fields(&account.tokens),
+                    missing_file: false,
+                    not_in_index,
+                }
+            }
+            Err(error) => StoreEntryReport {
+                id,
+                email: None,
+                valid: false,
+                error: Some(error),
+                missing_fields: Vec::new(),
+                missing_file: false,
+                not_in_index,
+            },
+        };
+        entries.push(entry);
+    }
+
+    for summary in &index.accounts {
+        if !entries.iter().any(|e| e.id == summary.id) {
+            entries.push(StoreEntryReport {
+                id: summary.id.clone(),
+                email: Some(summary.email.clone()),
+                valid: false,
+                error: Some("账号文件不存在".to_string()),
+                missing_fields: Vec::new(),
+                missing_file: true,
+                not_in_index: false,
+            });
+        }
+    }
+    entries.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let mut duplicate_ids = sorted_duplicates(index.accounts.iter().map(|a| a.id.as_str()));
+    duplicate_ids.extend(sorted_duplicates(stored_ids.iter().map(String::as_str)));
+    duplicate_ids.sort();
+    duplicate_ids.dedup();
+
+    let valid_count = entries.iter().filter(|e| e.valid).count();
+    StoreReport {
+        index_error,
+        corrupt_count: entries.len() - valid_count,
+        valid_count,
+        entries,
+        duplicate_ids,
+        duplicate_emails: sorted_duplicates(emails.iter().map(String::as_str)),
+    }
+}
+
+/// 检查账号存储完整性：逐个解析账号文件，报告损坏文件、重复 id/邮箱与缺失的 Token 字段
+pub fn verify_accounts_store() -> Result<StoreReport, String> {
+    let index_path = get_accounts_storage_path();
+    let index_content = if index_path.exists() {
+        Some(fs::read_to_string(&index_path).map_err(|e| format!("读取索引文件失败: {}", e))?)
+    } else {
+        None
+    };
+
+    let entries = fs::read_dir(get_accounts_dir()).map_err(|e| format!("读取账号目录失败: {}", e))?;
+    let mut files = Vec::new();
+    for path in entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+    {
+        let Some(id) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("读取文件失败: {}", e))
+            .and_then(|content| codex_account_crypto::open(&content));
+        files.push((id.to_string(), content));
+    }
+
+    Ok(build_store_report(index_content.as_deref(), files))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 串行化使用临时数据目录的测试（数据目录与加密密钥都是全局状态）
+    static TEMP_DATA_DIR_LOCK: Mutex<()> = Mutex::new(());
+
+    /// 把数据根目录指向临时目录，drop 时恢复并清理
+    struct TempDataDir {
+        dir: PathBuf,
+        _serial: std::sync::MutexGuard<'static, ()>,
+    }
+
+    impl TempDataDir {
+        fn new() -> Self {
+            let serial = TEMP_DATA_DIR_LOCK
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let dir = std::env::temp_dir()
+                .join(format!("cockpit-tools-test-{}", uuid::Uuid::new_v4().simple()));
+            *TEST_DATA_DIR
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(dir.clone());
+            Self {
+                dir,
+                _serial: serial,
+            }
+        }
+    }
+
+    impl Drop for TempDataDir {
+        fn drop(&mut self) {
+            // 清掉测试中启用的加密配置与缓存密钥
+            let _ = codex_account_crypto::remove_config();
+            *TEST_DATA_DIR
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn test_try_list_accounts_reports_locked_passphrase_store() {
+        let _data_dir = TempDataDir::new();
+        codex_account_crypto::enable(codex_account_crypto::KeySource::Passphrase, Some("pw"))
+            .unwrap();
+        assert!(try_list_accounts().is_ok());
+
+        // 模拟重启：内存中的密钥丢失
+        codex_account_crypto::forget_unlocked_key();
+        assert_eq!(
+            try_list_accounts().unwrap_err(),
+            codex_account_crypto::LOCKED_ERROR
+        );
+
+        codex_account_crypto::unlock("pw").unwrap();
+        assert!(try_list_accounts().is_ok());
+    }
+
+    #[test]
+    fn test_concurrent_updates_do_not_clobber_each_other() {
+        let _data_dir = TempDataDir::new();
+        let account_id = format!("codex_test_{}", uuid::Uuid::new_v4().simple());
+        let tokens = CodexTokens {
+            id_token: String::new(),
+            access_token: String::new(),
+            refresh_token: None,
+        };
+        let mut account = CodexAccount::new(account_id.clone(), "stress@example.com".to_string(), tokens);
+        account.tags = Some(Vec::new());
+        save_account(&account).unwrap();
+
+        let handles: Vec<_> = (0..16)
+            .map(|i| {
+                let id = account_id.clone();
+                std::thread::spawn(move || {
+                    update_account(&id, |acc| {
+                        acc.tags.get_or_insert_with(Vec::new).push(format!("tag-{}", i));
+                    })
+                    .unwrap();
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let stored = load_account(&account_id).unwrap();
+        let mut tags = stored.tags.unwrap_or_default();
+        tags.sort();
+        let mut expected: Vec<String> = (0..16).map(|i| format!("tag-{}", i)).collect();
+        expected.sort();
+        assert_eq!(tags, expected);
+    }
+
+    fn fake_jwt(sub: &str) -> String {
+        let payload = URL_SAFE_NO_PAD.encode(format!("{{\"sub\":\"{}\"}}", sub));
+        format!("e30.{}.sig", payload)
+    }
+
+    #[test]
+    fn test_canonical_tokens_rejects_mismatched_subjects() {
+        let tokens = CodexTokens {
+            id_token: fake_jwt("user-a"),
+            access_token: fake_jwt("user-a"),
+            refresh_token: Some("rt_opaque".to_string()),
+        };
+        let mut account = CodexAccount::new("codex_a".to_string(), "a@example.com".to_string(), tokens);
+        assert!(canonical_tokens(&account).is_ok());
+
+        account.tokens.id_token = fake_jwt("user-b");
+        assert!(canonical_tokens(&account).is_err());
+    }
+
+    #[test]
+    fn test_store_report_flags_corrupt_duplicate_and_incomplete_accounts() {
+        let account_json = |id: &str, email: &str, refresh: Option<&str>| {
+            let tokens = CodexTokens {
+                id_token: "id".to_string(),
+                access_token: "at".to_string(),
+                refresh_token: refresh.map(str::to_string),
+            };
+            serde_json::to_string(&CodexAccount::new(id.to_string(), email.to_string(), tokens)).unwrap()
+        };
+        let index = r#"{"version":"1.0","current_account_id":null,"accounts":[
+            {"id":"codex_a","email":"a@example.com","plan_type":null,"created_at":0,"last_used":0},
+            {"id":"codex_b","email":"b@example.com","plan_type":null,"created_at":0,"last_used":0},
+            {"id":"codex_gone","email":"g@example.com","plan_type":null,"created_at":0,"last_used":0}
+        ]}"#;
+        let files = vec![
+            ("codex_a".to_string(), Ok(account_json("codex_a", "a@example.com", Some("rt")))),
+            ("codex_b".to_string(), Ok(account_json("codex_b", "A@example.com", None))),
+            ("codex_c".to_string(), Ok("{\"id\": \"codex_c\"".to_string())),
+        ];
+
+        let report = build_store_report(Some(index), files);
+        assert_eq!(report.index_error, None);
+        assert_eq!(report.valid_count, 2);
+        assert_eq!(report.corrupt_count, 2);
+        assert_eq!(report.duplicate_emails, vec!["a@example.com".to_string()]);
+        let entry = |id: &str| report.entries.iter().find(|e| e.id == id).unwrap();
+        assert_eq!(entry("codex_b").missing_fields, vec!["refresh_token".to_string()]);
+        assert!(entry("codex_c").not_in_index && !entry("codex_c").valid);
+        assert!(entry("codex_gone").missing_file);
+        assert!(!report.is_healthy());
+
+        let report = build_store_report(Some("not json"), Vec::new());
+        assert!(report.index_error.is_some());
+    }
+
+    #[test]
+    fn test_upcoming_weekly_reset_requires_future_reset() {
+        use crate::models::codex::CodexQuota;
+
+        let tokens = CodexTokens {
+            id_token: String::new(),
+            access_token: String::new(),
+            refresh_token: None,
+        };
+        let mut account = CodexAccount::new("codex_p".to_string(), "p@example.com".to_string(), tokens);
+        assert!(upcoming_weekly_reset(&account, 1_000).is_err());
+
+        account.quota = Some(CodexQuota {
+            hourly_percentage: 50,
+            hourly_reset_time: None,
+            weekly_percentage: 50,
+            weekly_reset_time: Some(2_000),
+            raw_data: None,
+            partial: false,
+            unknown_windows: Vec::new(),
+            code_review_percentage: None,
+            code_review_reset_time: None,
+            plan_type: None,
+            hourly_reset_after_seconds: None,
+            hourly_window_seconds: None,
+            weekly_reset_after_seconds: None,
+            weekly_window_seconds: None,
+            allowed: None,
+            limit_reached: None,
+        });
+        assert_eq!(upcoming_weekly_reset(&account, 1_000), Ok(2_000));
+        assert!(upcoming_weekly_reset(&account, 2_000).is_err());
+    }
+
+    #[test]
+    fn test_store_report_ignores_renamed_copies_in_duplicate_emails() {
+        let account_json = |id: &str, tags: Option<Vec<String>>| {
+            let tokens = CodexTokens {
+                id_token: "id".to_string(),
+                access_token: "at".to_string(),
+                refresh_token: Some("rt".to_string()),
+            };
+            let mut account = CodexAccount::new(id.to_string(), "a@example.com".to_string(), tokens);
+            account.tags = tags;
+            serde_json::to_string(&account).unwrap()
+        };
+        let copy = Some(vec!["work".to_string(), "copy 2".to_string()]);
+        let files = vec![
+            ("codex_a".to_string(), Ok(account_json("codex_a", None))),
+            ("codex_a2".to_string(), Ok(account_json("codex_a2", copy))),
+        ];
+        assert!(build_store_report(None, files).duplicate_emails.is_empty());
+
+        let files = vec![
+            ("codex_a".to_string(), Ok(account_json("codex_a", None))),
+            ("codex_b".to_string(), Ok(account_json("codex_b", Some(vec!["copy".to_string()])))),
+        ];
+        assert_eq!(
+            build_store_report(None, files).duplicate_emails,
+            vec!["a@example.com".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_renamed_identity_keeps_email_and_skips_taken_ids() {
+        let mut index = CodexAccountIndex::new();
+        let (first_id, first_tag) = renamed_identity(&index, "a@example.com");
+        assert_eq!(first_tag, "copy 2");
+        assert_ne!(first_id, format!("codex_{:x}", md5::compute("a@example.com")));
+
+        index.accounts.push(CodexAccountSummary {
+            id: first_id.clone(),
+            email: "a@example.com".to_string(),
+            plan_type: None,
+            created_at: 0,
+            last_used: 0,
+        });
+        let (second_id, second_tag) = renamed_identity(&index, "a@example.com");
+        assert_eq!(second_tag, "copy 3");
+        assert_ne!(second_id, first_id);
+    }
+
+    #[test]
+    fn test_validate_import_entry_requires_parseable_matching_tokens() {
+        let id_payload = URL_SAFE_NO_PAD.encode(r#"{"aud":"app","sub":"user-a","email":"a@example.com"}"#);
+        let entry = |email: Option<&str>, id_token: String, access_token: String| ImportAccountEntry {
+            email: email.map(str::to_string),
+            tokens: CodexTokens {
+                id_token,
+                access_token,
+                refresh_token: Some("rt".to_string()),
+            },
+            tags: None,
+            guards: None,
+            account_header: None,
+            cli_path: None,
+        };
+        let id_token = format!("e30.{}.sig", id_payload);
+
+        let (email, ..) =
+            validate_import_entry(&entry(Some("A@example.com"), id_token.clone(), fake_jwt("user-a"))).unwrap();
+        assert_eq!(email, "a@example.com");
+        assert!(validate_import_entry(&entry(None, id_token.clone(), "opaque".to_string())).is_err());
+        assert!(validate_import_entry(&entry(None, "broken".to_string(), fake_jwt("user-a"))).is_err());
+        assert!(validate_import_entry(&entry(Some("b@example.com"), id_token.clone(), fake_jwt("user-a"))).is_err());
+        assert!(validate_import_entry(&entry(None, id_token, fake_jwt("user-b"))).is_err());
+    }
 }
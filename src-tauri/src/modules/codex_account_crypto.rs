@@ -0,0 +1,348 @@
+//! Codex 账号详情文件的静态加密（可选，默认关闭）
+//!
+//! 启用后，`codex_accounts/<id>.json` 以 AES-256-GCM 信封格式写入；密钥来自系统钥匙串，
+//! 或由用户口令经 PBKDF2-HMAC-SHA256 派生。加密配置保存在
+//! `codex_accounts_crypto.json`，不存在即视为未启用，读写行为与之前完全一致。
+//! 账号索引只含邮箱/套餐等摘要，不加密。
+
+use crate::modules::codex_account;
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+const CONFIG_FILE: &str = "codex_accounts_crypto.json";
+const ENVELOPE_VERSION: u32 = 1;
+const KEYCHAIN_SERVICE: &str = "com.antigravity.cockpit-tools";
+const KEYCHAIN_USER: &str = "codex-accounts-key";
+const PBKDF2_ROUNDS: u32 = 210_000;
+/// 用于校验密钥是否正确的固定明文
+const VERIFIER_PLAINTEXT: &str = "cockpit-tools codex accounts";
+
+/// 口令模式未解锁时读写账号返回的错误，前端据此弹出解锁提示
+pub const LOCKED_ERROR: &str = "Codex 账号存储已加密，请先输入口令解锁";
+
+/// 解锁后的密钥，仅保存在内存中
+static UNLOCKED_KEY: Mutex<Option<[u8; 32]>> = Mutex::new(None);
+/// 已读取的加密配置（配置文件路径, 内容）；路径变化时重新读取
+static CONFIG_CACHE: Mutex<Option<(PathBuf, Option<CryptoConfig>)>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum KeySource {
+    /// 随机密钥保存在系统钥匙串中，无需用户交互
+    Keychain,
+    /// 由用户口令派生，每次启动后需先解锁
+    Passphrase,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CryptoConfig {
+    key_source: KeySource,
+    /// 口令模式的 PBKDF2 盐（base64）
+    #[serde(default)]
+    salt: Option<String>,
+    /// 用当前密钥加密的固定明文，用于区分"密钥错误"与"文件损坏"
+    verifier: EncryptedEnvelope,
+    /// 已存在的明文账号文件是否已全部加密
+    #[serde(default)]
+    migrated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EncryptedEnvelope {
+    encrypted: u32,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// 加密状态，供前端展示
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptionStatus {
+    pub enabled: bool,
+    pub key_source: Option<KeySource>,
+    pub unlocked: bool,
+}
+
+fn config_path() -> PathBuf {
+    codex_account::get_data_root().join(CONFIG_FILE)
+}
+
+fn load_config() -> Result<Option<CryptoConfig>, String> {
+    let path = config_path();
+    let mut cache = CONFIG_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some((cached_path, config)) = cache.as_ref() {
+        if *cached_path == path {
+            return Ok(config.clone());
+        }
+    }
+    let config = if path.exists() {
+        let content =
+            fs::read_to_string(&path).map_err(|e| format!("读取加密配置失败: {}", e))?;
+        Some(
+            serde_json::from_str::<CryptoConfig>(&content)
+                .map_err(|e| format!("解析加密配置失败: {}", e))?,
+        )
+    } else {
+        None
+    };
+    *cache = Some((path, config.clone()));
+    Ok(config)
+}
+
+fn cache_config(config: Option<CryptoConfig>) {
+    *CONFIG_CACHE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some((config_path(), config));
+}
+
+/// 先写临时文件再替换，避免写入中断导致配置损坏、所有账号无法解密
+fn save_config(config: &CryptoConfig) -> Result<(), String> {
+    let path = config_path();
+    let temp_path = path.with_extension("json.tmp");
+    let content =
+        serde_json::to_string_pretty(config).map_err(|e| format!("序列化加密配置失败: {}", e))?;
+    fs::write(&temp_path, content).map_err(|e| format!("写入加密配置失败: {}", e))?;
+    fs::rename(&temp_path, &path).map_err(|e| format!("替换加密配置失败: {}", e))?;
+    cache_config(Some(config.clone()));
+    Ok(())
+}
+
+fn cached_key() -> Option<[u8; 32]> {
+    *UNLOCKED_KEY.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn cache_key(key: Option<[u8; 32]>) {
+    *UNLOCKED_KEY.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = key;
+}
+
+/// 丢弃内存中的密钥，模拟重启后的未解锁状态
+#[cfg(test)]
+pub(crate) fn forget_unlocked_key() {
+    cache_key(None);
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+    key
+}
+
+fn keychain_entry() -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_USER)
+        .map_err(|e| format!("无法访问系统钥匙串: {}", e))
+}
+
+fn read_keychain_key() -> Result<[u8; 32], String> {
+    let encoded = keychain_entry()?
+        .get_password()
+        .map_err(|e| format!("无法从系统钥匙串读取账号加密密钥: {}", e))?;
+    STANDARD
+        .decode(encoded.trim())
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes.as_slice()).ok())
+        .ok_or_else(|| "系统钥匙串中的账号加密密钥格式无效".to_string())
+}
+
+fn encrypt_with(key: &[u8; 32], plaintext: &str) -> Result<EncryptedEnvelope, String> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| format!("初始化加密失败: {}", e))?;
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_bytes())
+        .map_err(|_| "加密账号数据失败".to_string())?;
+    Ok(EncryptedEnvelope {
+        encrypted: ENVELOPE_VERSION,
+        nonce: STANDARD.encode(nonce),
+        ciphertext: STANDARD.encode(ciphertext),
+    })
+}
+
+/// 解密失败统一返回 `None`：GCM 无法区分密钥错误和数据被篡改
+fn decrypt_with(key: &[u8; 32], envelope: &EncryptedEnvelope) -> Option<String> {
+    let cipher = Aes256Gcm::new_from_slice(key).ok()?;
+    let nonce = STANDARD.decode(&envelope.nonce).ok()?;
+    if nonce.len() != 12 {
+        return None;
+    }
+    let ciphertext = STANDARD.decode(&envelope.ciphertext).ok()?;
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .ok()?;
+    String::from_utf8(plaintext).ok()
+}
+
+fn parse_envelope(content: &str) -> Option<EncryptedEnvelope> {
+    serde_json::from_str::<EncryptedEnvelope>(content).ok()
+}
+
+/// 返回当前可用的密钥；口令模式未解锁、钥匙串读取失败或密钥与存储不匹配时报错
+fn active_key(config: &CryptoConfig) -> Result<[u8; 32], String> {
+    if let Some(key) = cached_key() {
+        return Ok(key);
+    }
+    let key = match config.key_source {
+        KeySource::Passphrase => return Err(LOCKED_ERROR.to_string()),
+        KeySource::Keychain => read_keychain_key()?,
+    };
+    if decrypt_with(&key, &config.verifier).as_deref() != Some(VERIFIER_PLAINTEXT) {
+        return Err("系统钥匙串中的密钥与已加密的 Codex 账号存储不匹配".to_string());
+    }
+    cache_key(Some(key));
+    Ok(key)
+}
+
+pub fn is_enabled() -> bool {
+    config_path().exists()
+}
+
+/// 口令模式已启用但本次启动尚未解锁
+pub fn is_locked() -> bool {
+    matches!(load_config(), Ok(Some(config)) if config.key_source == KeySource::Passphrase)
+        && cached_key().is_none()
+}
+
+pub fn status() -> Result<EncryptionStatus, String> {
+    let config = load_config()?;
+    Ok(EncryptionStatus {
+        enabled: config.is_some(),
+        key_source: config.as_ref().map(|c| c.key_source),
+        unlocked: config.is_some() && cached_key().is_some(),
+    })
+}
+
+/// 加密后写盘内容；未启用加密时原样返回明文
+pub fn seal(plaintext: &str) -> Result<String, String> {
+    let Some(config) = load_config()? else {
+        return Ok(plaintext.to_string());
+    };
+    let key = active_key(&config)?;
+    serde_json::to_string_pretty(&encrypt_with(&key, plaintext)?)
+        .map_err(|e| format!("序列化加密数据失败: {}", e))
+}
+
+/// 读取磁盘内容并在需要时解密；明文内容（含迁移前的旧文件）原样返回
+pub fn open(content: &str) -> Result<String, String> {
+    let Some(envelope) = parse_envelope(content) else {
+        return Ok(content.to_string());
+    };
+    let config = load_config()?
+        .ok_or_else(|| "Codex 账号文件已加密，但找不到加密配置，无法解密".to_string())?;
+    let key = active_key(&config)?;
+    decrypt_with(&key, &envelope)
+        .ok_or_else(|| "Codex 账号文件解密失败：密钥不正确或文件已损坏".to_string())
+}
+
+/// 内容是否为加密信封
+pub fn is_sealed(content: &str) -> bool {
+    parse_envelope(content).is_some()
+}
+
+/// 明文存储是否仍待迁移（启用加密后首次保存时执行）
+pub fn needs_migration() -> bool {
+    matches!(load_config(), Ok(Some(config)) if !config.migrated)
+}
+
+pub fn mark_migrated() -> Result<(), String> {
+    if let Some(mut config) = load_config()? {
+        config.migrated = true;
+        save_config(&config)?;
+    }
+    Ok(())
+}
+
+/// 用口令解锁；口令错误时不缓存任何密钥
+pub fn unlock(passphrase: &str) -> Result<(), String> {
+    let config = load_config()?.ok_or_else(|| "Codex 账号存储未启用加密".to_string())?;
+    if config.key_source != KeySource::Passphrase {
+        return active_key(&config).map(|_| ());
+    }
+    let salt = config
+        .salt
+        .as_deref()
+        .and_then(|salt| STANDARD.decode(salt).ok())
+        .ok_or_else(|| "加密配置缺少口令盐值".to_string())?;
+    let key = derive_key(passphrase, &salt);
+    if decrypt_with(&key, &config.verifier).as_deref() != Some(VERIFIER_PLAINTEXT) {
+        return Err("口令不正确".to_string());
+    }
+    cache_key(Some(key));
+    Ok(())
+}
+
+/// 写入加密配置并缓存密钥；已有账号文件在下一次保存时迁移
+pub fn enable(key_source: KeySource, passphrase: Option<&str>) -> Result<(), String> {
+    if is_enabled() {
+        return Err("Codex 账号存储已启用加密".to_string());
+    }
+    let (key, salt) = match key_source {
+        KeySource::Passphrase => {
+            let passphrase = passphrase
+                .filter(|p| !p.is_empty())
+                .ok_or_else(|| "口令模式需要提供非空口令".to_string())?;
+            let mut salt = [0u8; 16];
+            rand::thread_rng().fill_bytes(&mut salt);
+            (derive_key(passphrase, &salt), Some(STANDARD.encode(salt)))
+        }
+        KeySource::Keychain => {
+            let mut key = [0u8; 32];
+            rand::thread_rng().fill_bytes(&mut key);
+            keychain_entry()?
+                .set_password(&STANDARD.encode(key))
+                .map_err(|e| format!("写入系统钥匙串失败: {}", e))?;
+            (key, None)
+        }
+    };
+    save_config(&CryptoConfig {
+        key_source,
+        salt,
+        verifier: encrypt_with(&key, VERIFIER_PLAINTEXT)?,
+        migrated: false,
+    })?;
+    cache_key(Some(key));
+    Ok(())
+}
+
+/// 删除加密配置与缓存密钥；调用方需先把所有账号文件写回明文
+pub fn remove_config() -> Result<(), String> {
+    let config = load_config()?;
+    let path = config_path();
+    if path.exists() {
+        fs::remove_file(&path).map_err(|e| format!("删除加密配置失败: {}", e))?;
+    }
+    cache_config(None);
+    if matches!(config, Some(CryptoConfig { key_source: KeySource::Keychain, .. })) {
+        if let Ok(entry) = keychain_entry() {
+            let _ = entry.delete_credential();
+        }
+    }
+    cache_key(None);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_round_trip_and_wrong_key() {
+        let key = [7u8; 32];
+        let envelope = encrypt_with(&key, "{\"id\":\"codex_1\"}").unwrap();
+        let content = serde_json::to_string(&envelope).unwrap();
+        assert!(is_sealed(&content));
+        assert!(!is_sealed("{\"id\":\"codex_1\",\"email\":\"a@b.c\"}"));
+        assert_eq!(decrypt_with(&key, &envelope).as_deref(), Some("{\"id\":\"codex_1\"}"));
+
+        let wrong = [8u8; 32];
+        assert_eq!(decrypt_with(&wrong, &envelope), None);
+    }
+}
@@ -0,0 +1,209 @@
+//! Prometheus text exposition of stored Codex quota and wakeup counters.
+//!
+//! Quota gauges come from what is already on disk; never triggers a quota refresh. Wakeup
+//! counts are kept in memory since the capped history file cannot back a monotonic counter.
+//! Wire it into whatever HTTP endpoint the scraper should hit.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+use crate::models::codex::CodexAccount;
+use crate::modules::codex_account;
+
+/// Wakeup runs since startup, keyed by account label and result ("success" / "failure").
+type WakeupCounts = BTreeMap<(String, &'static str), u64>;
+
+static WAKEUPS: Mutex<WakeupCounts> = Mutex::new(BTreeMap::new());
+
+/// Counts a finished wakeup run towards `codex_wakeups_total`.
+pub fn record_wakeup(account_label: &str, success: bool) {
+    let result = if success { "success" } else { "failure" };
+    *WAKEUPS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .entry((account_label.to_string(), result))
+        .or_insert(0) += 1;
+}
+
+/// Escapes a label value per the exposition format (`\`, `"` and newlines).
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn render_metrics(accounts: &[CodexAccount], wakeups: &WakeupCounts) -> String {
+    let mut out = String::new();
+    let quotas: Vec<_> = accounts
+        .iter()
+        .filter_map(|account| {
+            account
+                .quota
+                .as_ref()
+                .map(|quota| (escape_label(account.display_label()), quota))
+        })
+        .collect();
+
+    write_header(
+        &mut out,
+        "codex_hourly_remaining_percent",
+        "gauge",
+        "Remaining 5h quota percentage as last stored.",
+    );
+    for (label, quota) in &quotas {
+        if !quota.unknown_windows.iter().any(|w| w == "hourly") {
+            let _ = writeln!(
+                out,
+                "codex_hourly_remaining_percent{{account=\"{}\"}} {}",
+                label, quota.hourly_percentage
+            );
+        }
+    }
+
+    write_header(
+        &mut out,
+        "codex_weekly_remaining_percent",
+        "gauge",
+        "Remaining weekly quota percentage as last stored.",
+    );
+    for (label, quota) in &quotas {
+        if !quota.unknown_windows.iter().any(|w| w == "weekly") {
+            let _ = writeln!(
+                out,
+                "codex_weekly_remaining_percent{{account=\"{}\"}} {}",
+                label, quota.weekly_percentage
+            );
+        }
+    }
+
+    write_header(
+        &mut out,
+        "codex_hourly_reset_timestamp_seconds",
+        "gauge",
+        "Unix time the 5h quota window resets.",
+    );
+    for (label, quota) in &quotas {
+        if let Some(reset) = quota.hourly_reset_time {
+            let _ = writeln!(
+                out,
+                "codex_hourly_reset_timestamp_seconds{{account=\"{}\"}} {}",
+                label, reset
+            );
+        }
+    }
+
+    write_header(
+        &mut out,
+        "codex_weekly_reset_timestamp_seconds",
+        "gauge",
+        "Unix time the weekly quota window resets.",
+    );
+    for (label, quota) in &quotas {
+        if let Some(reset) = quota.weekly_reset_time {
+            let _ = writeln!(
+                out,
+                "codex_weekly_reset_timestamp_seconds{{account=\"{}\"}} {}",
+                label, reset
+            );
+        }
+    }
+
+    write_header(
+        &mut out,
+        "codex_wakeups_total",
+        "counter",
+        "Wakeups run since the app started, by account and result.",
+    );
+    for ((label, result), count) in wakeups {
+        let _ = writeln!(
+            out,
+            "codex_wakeups_total{{account=\"{}\",result=\"{}\"}} {}",
+            escape_label(label),
+            result,
+            count
+        );
+    }
+
+    out
+}
+
+/// Renders per-account quota gauges and wakeup counters in Prometheus text format.
+pub fn prometheus_metrics() -> String {
+    let accounts = codex_account::list_accounts();
+    let wakeups = WAKEUPS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone();
+    render_metrics(&accounts, &wakeups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::codex::{CodexQuota, CodexTokens};
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+
+    #[test]
+    fn test_render_metrics_without_data_emits_headers_only() {
+        let out = render_metrics(&[], &WakeupCounts::new());
+        assert!(out.contains("# TYPE codex_hourly_remaining_percent gauge"));
+        assert!(out.contains("# TYPE codex_wakeups_total counter"));
+        assert!(out.lines().all(|line| line.starts_with('#')));
+    }
+
+    #[test]
+    fn test_render_metrics_with_quota_and_wakeups() {
+        let tokens = CodexTokens {
+            id_token: String::new(),
+            access_token: String::new(),
+            refresh_token: None,
+        };
+        let mut account =
+            CodexAccount::new("codex_a".to_string(), "a@example.com".to_string(), tokens);
+        account.quota = Some(CodexQuota {
+            hourly_percentage: 40,
+            hourly_reset_time: Some(1_000),
+            weekly_percentage: 75,
+            weekly_reset_time: None,
+            raw_data: None,
+            partial: true,
+            unknown_windows: vec!["weekly".to_string()],
+            code_review_percentage: None,
+            code_review_reset_time: None,
+            plan_type: None,
+            hourly_reset_after_seconds: None,
+            hourly_window_seconds: None,
+            weekly_reset_after_seconds: None,
+            weekly_window_seconds: None,
+            allowed: None,
+            limit_reached: None,
+        });
+        let mut wakeups = WakeupCounts::new();
+        wakeups.insert(("a@example.com".to_string(), "success"), 3);
+        wakeups.insert(("a@example.com".to_string(), "failure"), 1);
+
+        let out = render_metrics(&[account], &wakeups);
+        assert!(out.contains("codex_hourly_remaining_percent{account=\"a@example.com\"} 40\n"));
+        assert!(
+            out.contains("codex_hourly_reset_timestamp_seconds{account=\"a@example.com\"} 1000\n")
+        );
+        assert!(!out.contains("codex_weekly_remaining_percent{"));
+        assert!(
+            out.contains("codex_wakeups_total{account=\"a@example.com\",result=\"success\"} 3\n")
+        );
+        assert!(
+            out.contains("codex_wakeups_total{account=\"a@example.com\",result=\"failure\"} 1\n")
+        );
+    }
+}
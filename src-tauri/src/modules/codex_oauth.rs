@@ -18,6 +18,8 @@ const SCOPES: &str = "openid profile email offline_access";
 const ORIGINATOR: &str = "codex_vscode";
 const OAUTH_CALLBACK_PORT: u16 = 1455;
 const OAUTH_PORT_IN_USE_CODE: &str = "CODEX_OAUTH_PORT_IN_USE";
+/// Token 刷新重试的初始退避（毫秒），之后每次翻倍
+const TOKEN_REFRESH_BACKOFF_MS: u64 = 500;
 
 pub fn get_callback_port() -> u16 {
     OAUTH_CALLBACK_PORT
@@ -415,7 +417,7 @@ async fn exchange_code_for_token_internal(
     port: u16,
 ) -> Result<CodexTokens, String> {
     let redirect_uri = format!("http://localhost:{}/auth/callback", port);
-    let client = reqwest::Client::new();
+    let client = crate::modules::codex_http::client();
 
     let params = [
         ("grant_type", "authorization_code"),
@@ -600,9 +602,35 @@ pub fn is_token_expired(access_token: &str) -> bool {
     let now = chrono::Utc::now().timestamp();
     exp < now + 60
 }
+/// Token 刷新失败类型，用于区分失效的 refresh_token 与网络抖动
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshErrorKind {
+    /// 网络错误、5xx、429，可重试
+    Transient,
+    /// refresh_token 已失效（invalid_grant），需要重新登录
+    InvalidGrant,
+    /// 其他不可重试错误（响应格式异常等）
+    Other,
+}
 
-pub async fn refresh_access_token(refresh_token: &str) -> Result<CodexTokens, String> {
-    let client = reqwest::Client::new();
+#[derive(Debug, Clone)]
+pub struct RefreshError {
+    pub kind: RefreshErrorKind,
+    pub message: String,
+}
+
+impl RefreshError {
+    fn transient(message: String) -> Self {
+        Self { kind: RefreshErrorKind::Transient, message }
+    }
+
+    fn other(message: String) -> Self {
+        Self { kind: RefreshErrorKind::Other, message }
+    }
+}
+
+async fn request_token_refresh(refresh_token: &str) -> Result<CodexTokens, RefreshError> {
+    let client = crate::modules::codex_http::client();
 
     let params = [
         ("grant_type", "refresh_token"),
@@ -617,13 +645,13 @@ pub async fn refresh_access_token(refresh_token: &str) -> Result<CodexTokens, St
         .form(&params)
         .send()
         .await
-        .map_err(|e| format!("Token 刷新请求失败: {}", e))?;
+        .map_err(|e| RefreshError::transient(format!("Token 刷新请求失败: {}", e)))?;
 
     let status = response.status();
     let body = response
         .text()
         .await
-        .map_err(|e| format!("读取响应失败: {}", e))?;
+        .map_err(|e| RefreshError::transient(format!("读取响应失败: {}", e)))?;
 
     if !status.is_success() {
         logger::log_error(&format!(
@@ -631,24 +659,32 @@ pub async fn refresh_access_token(refresh_token: &str) -> Result<CodexTokens, St
             status,
             &body[..body.len().min(200)]
         ));
-        return Err(format!("Token 刷新失败: {}", status));
+        let message = format!("Token 刷新失败: {}", status);
+        let kind = if body.contains("invalid_grant") {
+            RefreshErrorKind::InvalidGrant
+        } else if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            RefreshErrorKind::Transient
+        } else {
+            RefreshErrorKind::Other
+        };
+        return Err(RefreshError { kind, message });
     }
 
     logger::log_info("Codex Token 刷新成功");
 
-    let token_response: serde_json::Value =
-        serde_json::from_str(&body).map_err(|e| format!("解析 Token 响应失败: {}", e))?;
+    let token_response: serde_json::Value = serde_json::from_str(&body)
+        .map_err(|e| RefreshError::other(format!("解析 Token 响应失败: {}", e)))?;
 
     let id_token = token_response
         .get("id_token")
         .and_then(|v| v.as_str())
-        .ok_or("响应中缺少 id_token")?
+        .ok_or_else(|| RefreshError::other("响应中缺少 id_token".to_string()))?
         .to_string();
 
     let access_token = token_response
         .get("access_token")
         .and_then(|v| v.as_str())
-        .ok_or("响应中缺少 access_token")?
+        .ok_or_else(|| RefreshError::other("响应中缺少 access_token".to_string()))?
         .to_string();
 
     let new_refresh_token = token_response
@@ -663,3 +699,32 @@ pub async fn refresh_access_token(refresh_token: &str) -> Result<CodexTokens, St
         refresh_token: new_refresh_token,
     })
 }
+
+/// 刷新 access_token（单次请求）
+pub async fn refresh_access_token(refresh_token: &str) -> Result<CodexTokens, String> {
+    request_token_refresh(refresh_token).await.map_err(|e| e.message)
+}
+
+/// 刷新 access_token，网络类瞬时错误按退避重试；invalid_grant 等确定性错误立即返回
+pub async fn refresh_access_token_with_retry(
+    refresh_token: &str,
+    max_attempts: u32,
+) -> Result<CodexTokens, RefreshError> {
+    let max_attempts = max_attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        match request_token_refresh(refresh_token).await {
+            Ok(tokens) => return Ok(tokens),
+            Err(err) if err.kind == RefreshErrorKind::Transient && attempt < max_attempts => {
+                let backoff_ms = TOKEN_REFRESH_BACKOFF_MS * 2u64.pow(attempt - 1);
+                logger::log_warn(&format!(
+                    "Codex Token 刷新瞬时失败（第 {}/{} 次），{}ms 后重试: {}",
+                    attempt, max_attempts, backoff_ms, err.message
+                ));
+                tokio::time::sleep(std::time::Duration::from_millis(backoff_ms)).await;
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
@@ -1,10 +1,183 @@
-use crate::models::codex::{CodexQuota, CodexAccount};
+use crate::models::codex::{
+    AccountHeaderStrategy, CodexAccount, CodexQuota, CodexQuotaError, CodexQuotaErrorKind,
+};
 use crate::modules::{codex_account, logger};
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, ACCEPT};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, ACCEPT, RETRY_AFTER};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
 
 // Uses the same usage endpoint as Quotio.
 const USAGE_URL: &str = "https://chatgpt.com/backend-api/wham/usage";
+/// Env var overriding the usage endpoint (takes precedence over the `usage_url` setting).
+const USAGE_URL_ENV: &str = "CODEX_USAGE_URL";
+
+/// First valid absolute https override, or the default endpoint. Invalid overrides are logged.
+fn resolve_usage_url(env_override: Option<String>, setting_override: Option<String>) -> String {
+    let candidates = [(USAGE_URL_ENV, env_override), ("usage_url setting", setting_override)];
+    for (source, value) in candidates {
+        let Some(value) = value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty()) else {
+            continue;
+        };
+        match reqwest::Url::parse(&value) {
+            Ok(url) if url.scheme() == "https" && url.has_host() => return value,
+            Ok(_) => logger::log_warn(&format!(
+                "Ignoring Codex usage URL from {}: {} is not an https URL",
+                source, value
+            )),
+            Err(e) => logger::log_warn(&format!(
+                "Ignoring Codex usage URL from {}: {} ({})",
+                source, value, e
+            )),
+        }
+    }
+    USAGE_URL.to_string()
+}
+
+/// Usage endpoint in effect for this request.
+fn usage_url() -> String {
+    resolve_usage_url(
+        std::env::var(USAGE_URL_ENV).ok(),
+        crate::modules::codex_wakeup_settings::get_settings().usage_url,
+    )
+}
+
+/// Prefix of errors caused by the quota request timing out.
+const QUOTA_TIMEOUT_ERROR_PREFIX: &str = "Quota request timed out";
+/// `CodexQuotaError::code` values.
+pub const QUOTA_ERROR_CODE_TIMEOUT: &str = "timeout";
+pub const QUOTA_ERROR_CODE_ERROR: &str = "error";
+
+fn request_error(context: &str, e: reqwest::Error) -> String {
+    if e.is_timeout() {
+        format!("{}: {}", QUOTA_TIMEOUT_ERROR_PREFIX, e)
+    } else {
+        format!("{}: {}", context, e)
+    }
+}
+
+/// `CodexQuotaError::code` for a quota refresh error message.
+fn quota_error_code(err: &str) -> &'static str {
+    if err.starts_with(QUOTA_TIMEOUT_ERROR_PREFIX) {
+        QUOTA_ERROR_CODE_TIMEOUT
+    } else {
+        QUOTA_ERROR_CODE_ERROR
+    }
+}
+
+/// Prefix of errors for a non-success HTTP status, followed by the status and a body preview.
+const QUOTA_HTTP_ERROR_PREFIX: &str = "API returned ";
+const QUOTA_NETWORK_ERROR_PREFIX: &str = "Quota request failed";
+const QUOTA_BODY_ERROR_PREFIX: &str = "Failed to read quota response body";
+const QUOTA_PARSE_ERROR_PREFIX: &str = "Failed to parse quota JSON";
+/// Prefix of every `ensure_fresh_token` failure; only the transient one is not an auth problem.
+const TOKEN_EXPIRED_ERROR_PREFIX: &str = "Token expired";
+/// Transient refresh failure (network, 5xx, 429); the refresh token may still be good.
+const TOKEN_REFRESH_FAILED_ERROR_PREFIX: &str = "Token expired and refresh failed";
+/// Non-retryable refresh failure other than `invalid_grant`; classified as an auth problem.
+const TOKEN_REFRESH_REJECTED_ERROR_PREFIX: &str = "Token expired and refresh was rejected";
+/// `detail.code` values in an error body that mean the credentials are no longer usable.
+const AUTH_DETAIL_CODES: &[&str] = &["token_expired", "invalid_token", "token_invalidated", "account_deactivated"];
+/// `detail.code` values in an error body that mean the account is throttled.
+const RATE_LIMIT_DETAIL_CODES: &[&str] = &["rate_limit_exceeded", "usage_limit_reached"];
+
+/// `code` from an error body shaped like `{"detail": {"code": ..}}`, `{"error": {"code": ..}}`
+/// or `{"detail": "<code>"}`.
+fn extract_detail_code_from_body(body: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(body.trim()).ok()?;
+    let code = value
+        .get("detail")
+        .and_then(|detail| detail.get("code").or(Some(detail)))
+        .and_then(|code| code.as_str())
+        .or_else(|| {
+            value
+                .get("error")
+                .and_then(|error| error.get("code"))
+                .and_then(|code| code.as_str())
+        })?;
+    Some(code.trim().to_ascii_lowercase())
+}
+
+/// Classifies a quota refresh error message by HTTP status and error body detail code.
+fn classify_quota_error(err: &str) -> CodexQuotaErrorKind {
+    if let Some(rest) = err.strip_prefix(QUOTA_HTTP_ERROR_PREFIX) {
+        let status: Option<u16> = rest.split_whitespace().next().and_then(|code| code.parse().ok());
+        let detail_code = rest
+            .split_once(" - ")
+            .and_then(|(_, body)| extract_detail_code_from_body(body));
+        if let Some(code) = detail_code.as_deref() {
+            if AUTH_DETAIL_CODES.contains(&code) {
+                return CodexQuotaErrorKind::AuthExpired;
+            }
+            if RATE_LIMIT_DETAIL_CODES.contains(&code) {
+                return CodexQuotaErrorKind::RateLimited;
+            }
+        }
+        return match status {
+            Some(401) | Some(403) => CodexQuotaErrorKind::AuthExpired,
+            Some(429) => CodexQuotaErrorKind::RateLimited,
+            Some(code) if code >= 500 => CodexQuotaErrorKind::Network,
+            _ => CodexQuotaErrorKind::Unknown,
+        };
+    }
+    if err.starts_with(QUOTA_TIMEOUT_ERROR_PREFIX) {
+        CodexQuotaErrorKind::Timeout
+    } else if err.starts_with(QUOTA_NETWORK_ERROR_PREFIX)
+        || err.starts_with(QUOTA_BODY_ERROR_PREFIX)
+        || err.starts_with(TOKEN_REFRESH_FAILED_ERROR_PREFIX)
+    {
+        CodexQuotaErrorKind::Network
+    } else if err.starts_with(TOKEN_EXPIRED_ERROR_PREFIX) {
+        CodexQuotaErrorKind::AuthExpired
+    } else if err.starts_with(QUOTA_PARSE_ERROR_PREFIX) {
+        CodexQuotaErrorKind::Parse
+    } else {
+        CodexQuotaErrorKind::Unknown
+    }
+}
+
+/// Records the failed refresh on the account so the UI can show it (and offer a retry on
+/// timeout, or a re-login for auth errors).
+fn write_quota_error(account_id: &str, err: &str) {
+    let quota_error = CodexQuotaError {
+        code: quota_error_code(err).to_string(),
+        kind: classify_quota_error(err),
+        message: err.to_string(),
+        timestamp: chrono::Utc::now().timestamp(),
+    };
+    if let Err(e) = codex_account::update_account(account_id, |acc| acc.quota_error = Some(quota_error)) {
+        logger::log_warn(&format!("Failed to record Codex quota error for {}: {}", account_id, e));
+    }
+}
+
+/// Base delay before retrying a 429/5xx quota response; doubles per attempt.
+const QUOTA_RETRY_BACKOFF_MS: u64 = 500;
+/// Upper bound for any single retry wait, including server-sent `Retry-After`.
+const QUOTA_RETRY_MAX_DELAY: std::time::Duration = std::time::Duration::from_secs(30);
+
+fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// `Retry-After` as delay-seconds or an HTTP date (relative to `now`).
+fn parse_retry_after(value: &str, now: chrono::DateTime<chrono::Utc>) -> Option<std::time::Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(std::time::Duration::from_secs(seconds));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let millis = (at.with_timezone(&chrono::Utc) - now).num_milliseconds().max(0);
+    Some(std::time::Duration::from_millis(millis as u64))
+}
+
+/// Wait before retry `attempt` (1-based): the server's `Retry-After` when given, otherwise
+/// exponential backoff; capped at `QUOTA_RETRY_MAX_DELAY`.
+fn retry_delay(attempt: u32, retry_after: Option<std::time::Duration>) -> std::time::Duration {
+    let backoff = std::time::Duration::from_millis(
+        QUOTA_RETRY_BACKOFF_MS.saturating_mul(2u64.saturating_pow(attempt.saturating_sub(1))),
+    );
+    retry_after.unwrap_or(backoff).min(QUOTA_RETRY_MAX_DELAY)
+}
 
 /// Usage window metadata (5-hour / weekly).
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,22 +217,18 @@ struct UsageResponse {
 
 /// Fetches quota for one account.
 pub async fn fetch_quota(account: &CodexAccount) -> Result<CodexQuota, String> {
-    let client = reqwest::Client::new();
+    let tokens = codex_account::canonical_tokens(account)?;
+    let client = crate::modules::codex_http::client();
     
     let mut headers = HeaderMap::new();
     headers.insert(
         AUTHORIZATION,
-        HeaderValue::from_str(&format!("Bearer {}", account.tokens.access_token))
+        HeaderValue::from_str(&format!("Bearer {}", tokens.access_token))
             .map_err(|e| format!("Failed to build Authorization header: {}", e))?,
     );
     headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
     
-    // Add ChatGPT-Account-Id header when available.
-    let account_id = account
-        .account_id
-        .clone()
-        .or_else(|| codex_account::extract_chatgpt_account_id_from_access_token(&account.tokens.access_token));
-    
+    let account_id = account_header_value(account);
     if let Some(ref acc_id) = account_id {
         if !acc_id.is_empty() {
             headers.insert(
@@ -70,59 +239,132 @@ pub async fn fetch_quota(account: &CodexAccount) -> Result<CodexQuota, String> {
         }
     }
     
-    logger::log_info(&format!("Codex quota request: {} (account_id: {:?})", USAGE_URL, account_id));
-    
-    let response = client
-        .get(USAGE_URL)
-        .headers(headers)
-        .send()
-        .await
-        .map_err(|e| format!("Quota request failed: {}", e))?;
+    let url = usage_url();
+    logger::log_info_sampled(
+        "Codex quota request",
+        &format!("Codex quota request: {} (account_id: {:?})", url, account_id),
+    );
     
-    let status = response.status();
-    if !status.is_success() {
+    let max_attempts = crate::modules::codex_wakeup_settings::get_settings()
+        .quota_retry_attempts
+        .max(1);
+    let mut attempt = 1;
+    let response = loop {
+        let response = client
+            .get(&url)
+            .headers(headers.clone())
+            .send()
+            .await
+            .map_err(|e| request_error(QUOTA_NETWORK_ERROR_PREFIX, e))?;
+        
+        let status = response.status();
+        if status.is_success() {
+            break response;
+        }
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_retry_after(value, chrono::Utc::now()));
         let body = response.text().await.unwrap_or_default();
         // Truncate large response body to keep logs short.
         let body_preview = if body.len() > 200 { &body[..200] } else { &body };
-        return Err(format!("API returned {} - {}", status, body_preview));
-    }
+        if is_retryable_status(status) && attempt < max_attempts {
+            let delay = retry_delay(attempt, retry_after);
+            logger::log_warn(&format!(
+                "Codex quota request returned {} (attempt {}/{}), retrying in {}ms",
+                status,
+                attempt,
+                max_attempts,
+                delay.as_millis()
+            ));
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+            continue;
+        }
+        return Err(format!("{}{} - {}", QUOTA_HTTP_ERROR_PREFIX, status, body_preview));
+    };
     
     let body = response.text().await
-        .map_err(|e| format!("Failed to read quota response body: {}", e))?;
+        .map_err(|e| request_error(QUOTA_BODY_ERROR_PREFIX, e))?;
     
-    logger::log_info(&format!("Codex quota response: {}", &body[..body.len().min(500)]));
+    logger::log_info_sampled(
+        "Codex quota response",
+        &format!("Codex quota response: {}", &body[..body.len().min(500)]),
+    );
     
     // Parse response.
     let usage: UsageResponse = serde_json::from_str(&body)
-        .map_err(|e| format!("Failed to parse quota JSON: {}", e))?;
+        .map_err(|e| format!("{}: {}", QUOTA_PARSE_ERROR_PREFIX, e))?;
     
     parse_quota_from_usage(&usage, &body)
 }
 
+/// `reset_at`, or `now + reset_after_seconds` when the server only sent the relative value.
+fn window_reset_at(window: &WindowInfo, now: i64) -> Option<i64> {
+    window.reset_at.or_else(|| {
+        window
+            .reset_after_seconds
+            .map(|after| now.saturating_add(after.max(0)))
+    })
+}
+
 /// Parses quota from usage response.
 fn parse_quota_from_usage(usage: &UsageResponse, raw_body: &str) -> Result<CodexQuota, String> {
     let rate_limit = usage.rate_limit.as_ref();
+    let now = chrono::Utc::now().timestamp();
+    let primary_window = rate_limit.and_then(|r| r.primary_window.as_ref());
+    let secondary_window = rate_limit.and_then(|r| r.secondary_window.as_ref());
     
     // Primary window = 5-hour quota.
-    let (hourly_percentage, hourly_reset_time) = if let Some(primary) = rate_limit.and_then(|r| r.primary_window.as_ref()) {
+    let (hourly_percentage, hourly_reset_time) = if let Some(primary) = primary_window {
         let used = primary.used_percent.unwrap_or(0);
         let remaining = 100 - used;
-        let reset_at = primary.reset_at;
+        let reset_at = window_reset_at(primary, now);
         (remaining, reset_at)
     } else {
         (100, None)
     };
     
     // Secondary window = weekly quota.
-    let (weekly_percentage, weekly_reset_time) = if let Some(secondary) = rate_limit.and_then(|r| r.secondary_window.as_ref()) {
+    let (weekly_percentage, weekly_reset_time) = if let Some(secondary) = secondary_window {
         let used = secondary.used_percent.unwrap_or(0);
         let remaining = 100 - used;
-        let reset_at = secondary.reset_at;
+        let reset_at = window_reset_at(secondary, now);
         (remaining, reset_at)
     } else {
         (100, None)
     };
     
+    // A window missing from the payload is filled with 100%; in `Unknown` mode it is also flagged.
+    let missing_windows: Vec<String> = [
+        ("hourly", rate_limit.and_then(|r| r.primary_window.as_ref()).is_none()),
+        ("weekly", rate_limit.and_then(|r| r.secondary_window.as_ref()).is_none()),
+    ]
+    .into_iter()
+    .filter(|(_, missing)| *missing)
+    .map(|(window, _)| window.to_string())
+    .collect();
+    let partial = missing_windows.len() == 1;
+    let unknown_windows = if partial
+        && crate::modules::codex_wakeup_settings::get_settings().partial_quota_mode
+            == crate::modules::codex_wakeup_settings::PartialQuotaMode::Unknown
+    {
+        missing_windows
+    } else {
+        Vec::new()
+    };
+    
+    // Code review budget is tracked separately; absent when the server omits the block.
+    let (code_review_percentage, code_review_reset_time) = match usage
+        .code_review_rate_limit
+        .as_ref()
+        .and_then(|r| r.primary_window.as_ref())
+    {
+        Some(window) => (Some(100 - window.used_percent.unwrap_or(0)), window_reset_at(window, now)),
+        None => (None, None),
+    };
+    
     // Preserve raw payload.
     let raw_data: Option<serde_json::Value> = serde_json::from_str(raw_body).ok();
     
@@ -132,52 +374,888 @@ fn parse_quota_from_usage(usage: &UsageResponse, raw_body: &str) -> Result<Codex
         weekly_percentage,
         weekly_reset_time,
         raw_data,
+        partial,
+        unknown_windows,
+        code_review_percentage,
+        code_review_reset_time,
+        plan_type: usage.plan_type.clone(),
+        hourly_reset_after_seconds: primary_window.and_then(|w| w.reset_after_seconds),
+        hourly_window_seconds: primary_window.and_then(|w| w.limit_window_seconds),
+        weekly_reset_after_seconds: secondary_window.and_then(|w| w.reset_after_seconds),
+        weekly_window_seconds: secondary_window.and_then(|w| w.limit_window_seconds),
+        allowed: rate_limit.and_then(|r| r.allowed),
+        limit_reached: rate_limit.and_then(|r| r.limit_reached),
     })
 }
 
+/// Opens a pooled connection to the usage host without credentials (no quota is spent).
+/// Any HTTP status counts as success; only transport failures are errors.
+pub async fn warm_connection() -> Result<(), String> {
+    let url = usage_url();
+    crate::modules::codex_http::client()
+        .head(&url)
+        .timeout(std::time::Duration::from_secs(10))
+        .send()
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("Failed to reach {}: {}", url, e))
+}
+
+/// ChatGPT-Account-Id header value for the account's header strategy; `Auto` sends it
+/// whenever an id is stored or derivable from the access token.
+fn account_header_value(account: &CodexAccount) -> Option<String> {
+    match &account.account_header {
+        AccountHeaderStrategy::Auto => account.account_id.clone().or_else(|| {
+            codex_account::extract_chatgpt_account_id_from_access_token(
+                &account.tokens.access_token,
+            )
+        }),
+        AccountHeaderStrategy::Always(id) => Some(id.trim().to_string()),
+        AccountHeaderStrategy::Never => None,
+    }
+}
+
+/// Refreshes the access token when expired, persisting only the new tokens.
+async fn ensure_fresh_token(account: &mut CodexAccount) -> Result<(), String> {
+    if !crate::modules::codex_oauth::is_token_expired(&account.tokens.access_token) {
+        return Ok(());
+    }
+
+    logger::log_info(&format!("Token expired for {}, attempting refresh", account.display_label()));
+
+    let Some(refresh_token) = account.tokens.refresh_token.clone() else {
+        return Err(format!("{} and no refresh_token is available", TOKEN_EXPIRED_ERROR_PREFIX));
+    };
+    let max_attempts = crate::modules::codex_wakeup_settings::get_settings().token_refresh_attempts;
+    match crate::modules::codex_oauth::refresh_access_token_with_retry(&refresh_token, max_attempts).await {
+        Ok(new_tokens) => {
+            logger::log_info(&format!("Token refresh succeeded for {}", account.display_label()));
+            *account = codex_account::update_account(&account.id, |acc| acc.tokens = new_tokens)?;
+            Ok(())
+        }
+        Err(e) => {
+            logger::log_error(&format!("Token refresh failed for {}: {}", account.display_label(), e.message));
+            Err(token_refresh_error(&e))
+        }
+    }
+}
+
+/// Error message for a failed token refresh, keeping its kind recoverable by
+/// [`classify_quota_error`]: only a transient failure is reported as retryable.
+fn token_refresh_error(err: &crate::modules::codex_oauth::RefreshError) -> String {
+    use crate::modules::codex_oauth::RefreshErrorKind;
+
+    match err.kind {
+        RefreshErrorKind::InvalidGrant => format!(
+            "{} and the refresh token is no longer valid; re-login required: {}",
+            TOKEN_EXPIRED_ERROR_PREFIX, err.message
+        ),
+        RefreshErrorKind::Other => format!("{}: {}", TOKEN_REFRESH_REJECTED_ERROR_PREFIX, err.message),
+        RefreshErrorKind::Transient => format!("{}: {}", TOKEN_REFRESH_FAILED_ERROR_PREFIX, err.message),
+    }
+}
+
+/// Last fetched quota per account id, with the fetch time in ms.
+static QUOTA_CACHE: OnceLock<Mutex<HashMap<String, (i64, CodexQuota)>>> = OnceLock::new();
+
+fn quota_cache() -> &'static Mutex<HashMap<String, (i64, CodexQuota)>> {
+    QUOTA_CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// The cached quota when it was fetched at most `max_age_ms` ago; `max_age_ms == 0` never hits.
+fn fresh_cached_quota(entry: Option<&(i64, CodexQuota)>, max_age_ms: u64, now_ms: i64) -> Option<CodexQuota> {
+    let (fetched_at, quota) = entry?;
+    let max_age_ms = i64::try_from(max_age_ms).unwrap_or(i64::MAX);
+    (max_age_ms > 0 && now_ms.saturating_sub(*fetched_at) <= max_age_ms).then(|| quota.clone())
+}
+
 /// Refreshes one account quota and persists it (includes token auto-refresh).
-pub async fn refresh_account_quota(account_id: &str) -> Result<CodexQuota, String> {
+///
+/// A quota fetched within `max_age_ms` is returned from memory instead of calling the usage
+/// endpoint; an expired token is still refreshed. Pass 0 to always fetch.
+pub async fn refresh_account_quota(account_id: &str, max_age_ms: u64) -> Result<CodexQuota, String> {
     let mut account = codex_account::load_account(account_id)
         .ok_or_else(|| format!("Account not found: {}", account_id))?;
-    
-    // Refresh token before quota call if needed.
-    if crate::modules::codex_oauth::is_token_expired(&account.tokens.access_token) {
-        logger::log_info(&format!("Token expired for {}, attempting refresh", account.email));
-        
-        if let Some(ref refresh_token) = account.tokens.refresh_token {
-            match crate::modules::codex_oauth::refresh_access_token(refresh_token).await {
-                Ok(new_tokens) => {
-                    logger::log_info(&format!("Token refresh succeeded for {}", account.email));
-                    account.tokens = new_tokens;
-                    codex_account::save_account(&account)?;
-                }
-                Err(e) => {
-                    logger::log_error(&format!("Token refresh failed for {}: {}", account.email, e));
-                    return Err(format!("Token expired and refresh failed: {}", e));
-                }
-            }
-        } else {
-            return Err("Token expired and no refresh_token is available".to_string());
+    let cached = {
+        let cache = quota_cache().lock().expect("codex quota cache lock");
+        fresh_cached_quota(cache.get(account_id), max_age_ms, chrono::Utc::now().timestamp_millis())
+    };
+    if let Some(quota) = cached {
+        if let Err(e) = ensure_fresh_token(&mut account).await {
+            write_quota_error(&account.id, &e);
+            return Err(e);
         }
+        return Ok(quota);
+    }
+    refresh_quota_for(&mut account).await
+}
+
+/// Refreshes quota for an account the caller already holds, skipping the disk reload.
+/// `account` is updated in place with any refreshed tokens and the new quota.
+pub async fn refresh_quota_for(account: &mut CodexAccount) -> Result<CodexQuota, String> {
+    // Refresh token before quota call if needed.
+    if let Err(e) = ensure_fresh_token(account).await {
+        write_quota_error(&account.id, &e);
+        return Err(e);
     }
     
-    let quota = fetch_quota(&account).await?;
+    let quota = match fetch_quota(account).await {
+        Ok(quota) => quota,
+        Err(e) => {
+            write_quota_error(&account.id, &e);
+            return Err(e);
+        }
+    };
     
-    account.quota = Some(quota.clone());
-    codex_account::save_account(&account)?;
+    quota_cache()
+        .lock()
+        .expect("codex quota cache lock")
+        .insert(account.id.clone(), (chrono::Utc::now().timestamp_millis(), quota.clone()));
+    let stored_quota = quota.clone();
+    *account = codex_account::update_account(&account.id, |acc| {
+        acc.quota = Some(stored_quota);
+        acc.quota_error = None;
+    })?;
+
+    if let Err(e) = crate::modules::codex_quota_history::record_sample(&account.id, &quota) {
+        logger::log_warn(&format!("Failed to record Codex quota sample for {}: {}", account.display_label(), e));
+    }
+    crate::modules::codex_quota_alerts::check_thresholds(account, &quota);
     
     Ok(quota)
 }
 
+/// Fetches quota without storing it (a refreshed token is still persisted).
+pub async fn probe_quota(account_id: &str) -> Result<CodexQuota, String> {
+    let mut account = codex_account::load_account(account_id)
+        .ok_or_else(|| format!("Account not found: {}", account_id))?;
+
+    ensure_fresh_token(&mut account).await?;
+
+    fetch_quota(&account).await
+}
+
+/// Per-account outcome inside a [`RefreshAllReport`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountRefreshResult {
+    pub account_id: String,
+    pub email: String,
+    pub quota: Option<CodexQuota>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// Summary of a bulk quota refresh.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshAllReport {
+    pub results: Vec<AccountRefreshResult>,
+    pub total_duration_ms: u64,
+    pub success_count: usize,
+    pub failure_count: usize,
+    pub slowest_account_id: Option<String>,
+    pub slowest_duration_ms: Option<u64>,
+}
+
+/// Refreshes quota for all accounts and reports timing per account.
+///
+/// Up to `quota_refresh_concurrency` accounts refresh at once; results keep the account order.
+pub async fn refresh_all_quotas_report() -> RefreshAllReport {
+    use futures::stream::{self, StreamExt};
+
+    let started = std::time::Instant::now();
+    let sampled_logs = logger::begin_sampled_batch();
+    let accounts = codex_account::list_accounts();
+    let concurrency = crate::modules::codex_wakeup_settings::get_settings()
+        .quota_refresh_concurrency
+        .max(1);
+
+    let results: Vec<AccountRefreshResult> = stream::iter(accounts)
+        .map(|account| async move {
+            let account_started = std::time::Instant::now();
+            let result = refresh_account_quota(&account.id, 0).await;
+            let (quota, error) = match result {
+                Ok(quota) => (Some(quota), None),
+                Err(e) => (None, Some(e)),
+            };
+            AccountRefreshResult {
+                email: account.display_label().to_string(),
+                account_id: account.id,
+                quota,
+                error,
+                duration_ms: account_started.elapsed().as_millis() as u64,
+            }
+        })
+        .buffered(concurrency)
+        .collect()
+        .await;
+
+    let success_count = results.iter().filter(|r| r.error.is_none()).count();
+    let slowest = results.iter().max_by_key(|r| r.duration_ms);
+    let report = RefreshAllReport {
+        total_duration_ms: started.elapsed().as_millis() as u64,
+        success_count,
+        failure_count: results.len() - success_count,
+        slowest_account_id: slowest.map(|r| r.account_id.clone()),
+        slowest_duration_ms: slowest.map(|r| r.duration_ms),
+        results,
+    };
+
+    drop(sampled_logs);
+    logger::log_info(&format!(
+        "Codex bulk quota refresh finished: success={}, failed={}, duration={}ms",
+        report.success_count, report.failure_count, report.total_duration_ms
+    ));
+    report
+}
+
 /// Refreshes quota for all accounts.
 pub async fn refresh_all_quotas() -> Result<Vec<(String, Result<CodexQuota, String>)>, String> {
+    let report = refresh_all_quotas_report().await;
+    Ok(report
+        .results
+        .into_iter()
+        .map(|r| {
+            let result = match (r.quota, r.error) {
+                (Some(quota), _) => Ok(quota),
+                (None, error) => Err(error.unwrap_or_default()),
+            };
+            (r.account_id, result)
+        })
+        .collect())
+}
+
+/// Per-window summary inside an [`AggregateQuota`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowAggregate {
+    pub min_remaining: Option<i32>,
+    pub avg_remaining: Option<f64>,
+    /// Accounts with 0% remaining in this window.
+    pub at_limit_count: usize,
+    pub soonest_reset: Option<i64>,
+}
+
+/// Quota summary across all Codex accounts.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregateQuota {
+    pub account_count: usize,
+    /// Accounts with no usable quota (never fetched, or failed when `refresh_first`).
+    pub error_count: usize,
+    /// Accounts at limit in either window.
+    pub at_limit_count: usize,
+    pub hourly: WindowAggregate,
+    pub weekly: WindowAggregate,
+}
+
+fn aggregate_window(samples: &[(i32, Option<i64>)]) -> WindowAggregate {
+    let now = chrono::Utc::now().timestamp();
+    WindowAggregate {
+        min_remaining: samples.iter().map(|(pct, _)| *pct).min(),
+        avg_remaining: if samples.is_empty() {
+            None
+        } else {
+            Some(samples.iter().map(|(pct, _)| *pct as f64).sum::<f64>() / samples.len() as f64)
+        },
+        at_limit_count: samples.iter().filter(|(pct, _)| *pct <= 0).count(),
+        soonest_reset: samples
+            .iter()
+            .filter_map(|(_, reset)| *reset)
+            .filter(|reset| *reset > now)
+            .min(),
+    }
+}
+
+/// Summarizes stored quotas (no network unless `refresh_first`).
+pub async fn aggregate_quota(refresh_first: bool) -> AggregateQuota {
+    let mut failed_ids = std::collections::HashSet::new();
+    if refresh_first {
+        let report = refresh_all_quotas_report().await;
+        failed_ids.extend(
+            report
+                .results
+                .into_iter()
+                .filter(|r| r.error.is_some())
+                .map(|r| r.account_id),
+        );
+    }
+
     let accounts = codex_account::list_accounts();
-    let mut results = Vec::new();
-    
-    for account in accounts {
-        let result = refresh_account_quota(&account.id).await;
-        results.push((account.id.clone(), result));
+    let quotas: Vec<&CodexQuota> = accounts
+        .iter()
+        .filter(|account| !failed_ids.contains(&account.id))
+        .filter_map(|account| account.quota.as_ref())
+        .collect();
+
+    let hourly: Vec<(i32, Option<i64>)> = quotas
+        .iter()
+        .map(|q| (q.hourly_percentage, q.hourly_reset_time))
+        .collect();
+    let weekly: Vec<(i32, Option<i64>)> = quotas
+        .iter()
+        .map(|q| (q.weekly_percentage, q.weekly_reset_time))
+        .collect();
+
+    AggregateQuota {
+        account_count: accounts.len(),
+        error_count: accounts.len() - quotas.len(),
+        at_limit_count: quotas
+            .iter()
+            .filter(|q| q.hourly_percentage <= 0 || q.weekly_percentage <= 0)
+            .count(),
+        hourly: aggregate_window(&hourly),
+        weekly: aggregate_window(&weekly),
+    }
+}
+
+/// Why an account shows up in [`accounts_needing_attention`], most urgent first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AttentionReason {
+    /// Access token expired and there is no refresh token to renew it.
+    TokenUnrefreshable,
+    /// Scheduled wakeups failed several times in a row.
+    RepeatedFailures,
+    /// The last quota refresh failed (see `quota_error`).
+    QuotaError,
+    /// A window reports `limit_reached` or 0% remaining.
+    LimitReached,
+    /// Quota has never been fetched for this account.
+    QuotaMissing,
+    /// Access token expired but can still be refreshed.
+    TokenExpired,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttentionItem {
+    pub account_id: String,
+    pub email: String,
+    pub reason: AttentionReason,
+    pub message: String,
+    pub suggested_action: String,
+}
+
+fn limit_reached(quota: &CodexQuota) -> bool {
+    // Snapshots stored before the flags were parsed only carry them in `raw_data`.
+    let flagged = quota.limit_reached.unwrap_or_else(|| {
+        quota
+            .raw_data
+            .as_ref()
+            .and_then(|raw| raw.get("rate_limit"))
+            .and_then(|rate_limit| rate_limit.get("limit_reached"))
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false)
+    });
+    flagged
+        || quota.allowed == Some(false)
+        || quota.hourly_percentage <= 0
+        || quota.weekly_percentage <= 0
+}
+
+/// Change in one quota window between two snapshots. Percentages are `None` when the
+/// window was absent (listed in `unknown_windows`) in that snapshot.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowDiff {
+    pub old_remaining: Option<i32>,
+    pub new_remaining: Option<i32>,
+    /// `new - old`; `None` unless both sides are known.
+    pub delta: Option<i32>,
+    pub old_reset_time: Option<i64>,
+    pub new_reset_time: Option<i64>,
+    pub reset_changed: bool,
+}
+
+impl WindowDiff {
+    fn between(
+        old_remaining: Option<i32>,
+        new_remaining: Option<i32>,
+        old_reset_time: Option<i64>,
+        new_reset_time: Option<i64>,
+    ) -> Self {
+        Self {
+            old_remaining,
+            new_remaining,
+            delta: old_remaining.zip(new_remaining).map(|(old, new)| new - old),
+            old_reset_time,
+            new_reset_time,
+            reset_changed: old_reset_time != new_reset_time,
+        }
+    }
+
+    pub fn is_unchanged(&self) -> bool {
+        self.old_remaining == self.new_remaining && !self.reset_changed
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaDiff {
+    pub hourly: WindowDiff,
+    pub weekly: WindowDiff,
+    pub was_limit_reached: bool,
+    pub is_limit_reached: bool,
+}
+
+impl QuotaDiff {
+    pub fn is_unchanged(&self) -> bool {
+        self.hourly.is_unchanged()
+            && self.weekly.is_unchanged()
+            && self.was_limit_reached == self.is_limit_reached
+    }
+}
+
+fn known_remaining(quota: &CodexQuota, window: &str, percentage: i32) -> Option<i32> {
+    (!quota.unknown_windows.iter().any(|w| w == window)).then_some(percentage)
+}
+
+/// Compares two quota snapshots window by window.
+pub fn diff_quota(old: &CodexQuota, new: &CodexQuota) -> QuotaDiff {
+    QuotaDiff {
+        hourly: WindowDiff::between(
+            known_remaining(old, "hourly", old.hourly_percentage),
+            known_remaining(new, "hourly", new.hourly_percentage),
+            old.hourly_reset_time,
+            new.hourly_reset_time,
+        ),
+        weekly: WindowDiff::between(
+            known_remaining(old, "weekly", old.weekly_percentage),
+            known_remaining(new, "weekly", new.weekly_percentage),
+            old.weekly_reset_time,
+            new.weekly_reset_time,
+        ),
+        was_limit_reached: limit_reached(old),
+        is_limit_reached: limit_reached(new),
+    }
+}
+
+fn attention_items_for(
+    account: &CodexAccount,
+    consecutive_failures: u32,
+    failure_threshold: u32,
+) -> Vec<AttentionItem> {
+    let item = |reason: AttentionReason, message: String, suggested_action: &str| AttentionItem {
+        account_id: account.id.clone(),
+        email: account.display_label().to_string(),
+        reason,
+        message,
+        suggested_action: suggested_action.to_string(),
+    };
+    let mut items = Vec::new();
+
+    if crate::modules::codex_oauth::is_token_expired(&account.tokens.access_token) {
+        let refreshable = account
+            .tokens
+            .refresh_token
+            .as_deref()
+            .map(|token| !token.trim().is_empty())
+            .unwrap_or(false);
+        if refreshable {
+            items.push(item(
+                AttentionReason::TokenExpired,
+                "Access token expired".to_string(),
+                "Refresh quota to renew the token",
+            ));
+        } else {
+            items.push(item(
+                AttentionReason::TokenUnrefreshable,
+                "Access token expired and no refresh token is stored".to_string(),
+                "Log in to this account again",
+            ));
+        }
+    }
+
+    if failure_threshold > 0 && consecutive_failures >= failure_threshold {
+        items.push(item(
+            AttentionReason::RepeatedFailures,
+            format!("{} consecutive wakeup failures", consecutive_failures),
+            "Check the wakeup history for this account and re-login if it asks for authentication",
+        ));
+    }
+
+    if let Some(error) = account.quota_error.as_ref() {
+        let suggested_action = if error.kind == CodexQuotaErrorKind::AuthExpired {
+            "Log in to this account again"
+        } else {
+            "Retry the quota refresh"
+        };
+        items.push(item(
+            AttentionReason::QuotaError,
+            format!("Last quota refresh failed: {}", error.message),
+            suggested_action,
+        ));
+    }
+
+    match account.quota.as_ref() {
+        Some(quota) if limit_reached(quota) => items.push(item(
+            AttentionReason::LimitReached,
+            format!(
+                "Quota limit reached (5h {}%, weekly {}%)",
+                quota.hourly_percentage, quota.weekly_percentage
+            ),
+            "Switch to another account until the window resets",
+        )),
+        Some(_) => {}
+        None => items.push(item(
+            AttentionReason::QuotaMissing,
+            "Quota has not been fetched yet".to_string(),
+            "Refresh quota for this account",
+        )),
+    }
+
+    items
+}
+
+/// Collects accounts with health problems, most urgent reason first.
+pub fn accounts_needing_attention() -> Vec<AttentionItem> {
+    let failure_threshold =
+        crate::modules::codex_wakeup_settings::get_settings().failure_alert_threshold;
+    let mut items: Vec<AttentionItem> = codex_account::list_accounts()
+        .iter()
+        .flat_map(|account| {
+            let failures = crate::modules::codex_wakeup_scheduler::consecutive_failures(&account.id);
+            attention_items_for(account, failures, failure_threshold)
+        })
+        .collect();
+    items.sort_by(|a, b| a.reason.cmp(&b.reason).then_with(|| a.email.cmp(&b.email)));
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quota(hourly: i32, weekly: i32, weekly_reset: Option<i64>) -> CodexQuota {
+        CodexQuota {
+            hourly_percentage: hourly,
+            hourly_reset_time: Some(1_000),
+            weekly_percentage: weekly,
+            weekly_reset_time: weekly_reset,
+            raw_data: None,
+            partial: false,
+            unknown_windows: Vec::new(),
+            code_review_percentage: None,
+            code_review_reset_time: None,
+            plan_type: None,
+            hourly_reset_after_seconds: None,
+            hourly_window_seconds: None,
+            weekly_reset_after_seconds: None,
+            weekly_window_seconds: None,
+            allowed: None,
+            limit_reached: None,
+        }
+    }
+
+    #[test]
+    fn test_account_header_value_follows_strategy() {
+        use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+        let payload = URL_SAFE_NO_PAD
+            .encode(r#"{"https://api.openai.com/auth":{"chatgpt_account_id":"acct-jwt"}}"#);
+        let tokens = crate::models::codex::CodexTokens {
+            id_token: String::new(),
+            access_token: format!("e30.{}.sig", payload),
+            refresh_token: None,
+        };
+        let mut account =
+            CodexAccount::new("codex_a".to_string(), "a@example.com".to_string(), tokens);
+        assert_eq!(account_header_value(&account).as_deref(), Some("acct-jwt"));
+
+        account.account_id = Some("acct-stored".to_string());
+        assert_eq!(account_header_value(&account).as_deref(), Some("acct-stored"));
+
+        account.account_header = AccountHeaderStrategy::Always(" acct-fixed ".to_string());
+        assert_eq!(account_header_value(&account).as_deref(), Some("acct-fixed"));
+
+        account.account_header = AccountHeaderStrategy::Never;
+        assert_eq!(account_header_value(&account), None);
+    }
+
+    fn attention_account(expired: bool, refresh_token: Option<&str>) -> CodexAccount {
+        use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+
+        let exp = chrono::Utc::now().timestamp() + if expired { -3_600 } else { 3_600 };
+        let payload = URL_SAFE_NO_PAD.encode(format!("{{\"exp\":{}}}", exp));
+        let tokens = crate::models::codex::CodexTokens {
+            id_token: String::new(),
+            access_token: format!("e30.{}.sig", payload),
+            refresh_token: refresh_token.map(str::to_string),
+        };
+        let mut account =
+            CodexAccount::new("codex_a".to_string(), "a@example.com".to_string(), tokens);
+        account.quota = Some(quota(50, 50, None));
+        account
+    }
+
+    fn quota_error(kind: CodexQuotaErrorKind) -> CodexQuotaError {
+        CodexQuotaError {
+            code: "error".to_string(),
+            kind,
+            message: "boom".to_string(),
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn test_attention_items_for_each_reason() {
+        let healthy = attention_account(false, Some("rt"));
+        let mut limited = healthy.clone();
+        limited.quota = Some(quota(0, 40, None));
+        let mut missing = healthy.clone();
+        missing.quota = None;
+        let mut auth_error = healthy.clone();
+        auth_error.quota_error = Some(quota_error(CodexQuotaErrorKind::AuthExpired));
+        let mut network_error = healthy.clone();
+        network_error.quota_error = Some(quota_error(CodexQuotaErrorKind::Network));
+        let expired = attention_account(true, Some("rt"));
+        let unrefreshable = attention_account(true, None);
+
+        let cases = [
+            (healthy.clone(), 0, 3, vec![]),
+            (healthy.clone(), 2, 3, vec![]),
+            (healthy.clone(), 5, 0, vec![]),
+            (healthy, 3, 3, vec![AttentionReason::RepeatedFailures]),
+            (expired, 0, 3, vec![AttentionReason::TokenExpired]),
+            (
+                unrefreshable,
+                0,
+                3,
+                vec![AttentionReason::TokenUnrefreshable],
+            ),
+            (auth_error.clone(), 0, 3, vec![AttentionReason::QuotaError]),
+            (limited, 0, 3, vec![AttentionReason::LimitReached]),
+            (missing, 0, 3, vec![AttentionReason::QuotaMissing]),
+        ];
+        for (account, failures, threshold, expected) in cases {
+            let reasons: Vec<_> = attention_items_for(&account, failures, threshold)
+                .into_iter()
+                .map(|item| item.reason)
+                .collect();
+            assert_eq!(
+                reasons, expected,
+                "failures={} threshold={}",
+                failures, threshold
+            );
+        }
+
+        let action = |account: &CodexAccount| {
+            attention_items_for(account, 0, 3)
+                .into_iter()
+                .find(|item| item.reason == AttentionReason::QuotaError)
+                .map(|item| item.suggested_action)
+        };
+        assert_eq!(
+            action(&auth_error).as_deref(),
+            Some("Log in to this account again")
+        );
+        assert_eq!(
+            action(&network_error).as_deref(),
+            Some("Retry the quota refresh")
+        );
+    }
+
+    #[test]
+    fn test_attention_reasons_sort_by_urgency() {
+        let mut account = attention_account(true, None);
+        account.quota = None;
+        account.quota_error = Some(quota_error(CodexQuotaErrorKind::Timeout));
+        let mut reasons: Vec<_> = attention_items_for(&account, 4, 3)
+            .into_iter()
+            .map(|item| item.reason)
+            .collect();
+        reasons.push(AttentionReason::LimitReached);
+        reasons.push(AttentionReason::TokenExpired);
+        reasons.sort();
+        assert_eq!(
+            reasons,
+            vec![
+                AttentionReason::TokenUnrefreshable,
+                AttentionReason::RepeatedFailures,
+                AttentionReason::QuotaError,
+                AttentionReason::LimitReached,
+                AttentionReason::QuotaMissing,
+                AttentionReason::TokenExpired,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_quota_reports_deltas_and_limit_transition() {
+        let diff = diff_quota(&quota(40, 10, Some(5_000)), &quota(0, 10, Some(9_000)));
+        assert_eq!(diff.hourly.delta, Some(-40));
+        assert!(!diff.hourly.reset_changed);
+        assert_eq!(diff.weekly.delta, Some(0));
+        assert!(diff.weekly.reset_changed);
+        assert!(!diff.was_limit_reached);
+        assert!(diff.is_limit_reached);
+        assert!(!diff.is_unchanged());
+        assert!(diff_quota(&quota(40, 10, None), &quota(40, 10, None)).is_unchanged());
+    }
+
+    #[test]
+    fn test_diff_quota_treats_unknown_windows_as_absent() {
+        let mut new = quota(55, 100, None);
+        new.partial = true;
+        new.unknown_windows = vec!["weekly".to_string()];
+        let diff = diff_quota(&quota(50, 80, None), &new);
+        assert_eq!(diff.weekly.old_remaining, Some(80));
+        assert_eq!(diff.weekly.new_remaining, None);
+        assert_eq!(diff.weekly.delta, None);
+        assert_eq!(diff.hourly.delta, Some(5));
+    }
+
+    #[test]
+    fn test_parse_quota_reads_code_review_window() {
+        let body = r#"{
+            "plan_type": "pro",
+            "rate_limit": {
+                "allowed": false,
+                "limit_reached": true,
+                "primary_window": {"used_percent": 10, "reset_at": 100},
+                "secondary_window": {"used_percent": 20, "reset_at": 200}
+            },
+            "code_review_rate_limit": {
+                "primary_window": {"used_percent": 35, "reset_at": 300}
+            }
+        }"#;
+        let usage: UsageResponse = serde_json::from_str(body).unwrap();
+        let quota = parse_quota_from_usage(&usage, body).unwrap();
+        assert_eq!(quota.code_review_percentage, Some(65));
+        assert_eq!(quota.code_review_reset_time, Some(300));
+        assert_eq!(quota.plan_type.as_deref(), Some("pro"));
+        assert_eq!(quota.allowed, Some(false));
+        assert_eq!(quota.limit_reached, Some(true));
+
+        let body = r#"{"rate_limit": {"primary_window": {}, "secondary_window": {}}}"#;
+        let usage: UsageResponse = serde_json::from_str(body).unwrap();
+        let quota = parse_quota_from_usage(&usage, body).unwrap();
+        assert_eq!(quota.code_review_percentage, None);
+        assert_eq!(quota.code_review_reset_time, None);
+        assert_eq!(quota.plan_type, None);
+        assert_eq!(quota.allowed, None);
+    }
+
+    #[test]
+    fn test_quota_error_code_flags_timeouts() {
+        assert_eq!(
+            quota_error_code(&format!("{}: operation timed out", QUOTA_TIMEOUT_ERROR_PREFIX)),
+            QUOTA_ERROR_CODE_TIMEOUT
+        );
+        assert_eq!(quota_error_code("API returned 500 - oops"), QUOTA_ERROR_CODE_ERROR);
+    }
+
+    #[test]
+    fn test_fresh_cached_quota_respects_max_age() {
+        let entry = (1_000, quota(50, 60, None));
+        assert!(fresh_cached_quota(Some(&entry), 500, 1_400).is_some());
+        assert!(fresh_cached_quota(Some(&entry), 500, 1_600).is_none());
+        assert!(fresh_cached_quota(Some(&entry), 0, 1_000).is_none());
+        assert!(fresh_cached_quota(None, 500, 1_000).is_none());
+    }
+
+    #[test]
+    fn test_resolve_usage_url_accepts_only_https_overrides() {
+        let mock = "https://mock.example.com/usage".to_string();
+        assert_eq!(resolve_usage_url(Some(mock.clone()), None), mock);
+        assert_eq!(resolve_usage_url(None, Some(mock.clone())), mock);
+        assert_eq!(
+            resolve_usage_url(Some("http://mock.example.com/usage".to_string()), Some(mock.clone())),
+            mock
+        );
+        assert_eq!(resolve_usage_url(Some("not a url".to_string()), None), USAGE_URL);
+        assert_eq!(resolve_usage_url(Some("  ".to_string()), None), USAGE_URL);
+        assert_eq!(resolve_usage_url(None, None), USAGE_URL);
+    }
+
+    #[test]
+    fn test_window_reset_at_derives_from_relative_seconds() {
+        let window = |reset_at: Option<i64>, reset_after_seconds: Option<i64>| WindowInfo {
+            used_percent: Some(10),
+            limit_window_seconds: Some(18_000),
+            reset_after_seconds,
+            reset_at,
+        };
+        assert_eq!(window_reset_at(&window(Some(500), Some(60)), 1_000), Some(500));
+        assert_eq!(window_reset_at(&window(None, Some(60)), 1_000), Some(1_060));
+        assert_eq!(window_reset_at(&window(None, None), 1_000), None);
+    }
+
+    #[test]
+    fn test_retry_delay_prefers_retry_after_and_caps() {
+        use std::time::Duration;
+
+        assert!(is_retryable_status(reqwest::StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_retryable_status(reqwest::StatusCode::BAD_GATEWAY));
+        assert!(!is_retryable_status(reqwest::StatusCode::UNAUTHORIZED));
+
+        assert_eq!(retry_delay(1, None), Duration::from_millis(500));
+        assert_eq!(retry_delay(3, None), Duration::from_millis(2_000));
+        assert_eq!(retry_delay(2, Some(Duration::from_secs(7))), Duration::from_secs(7));
+        assert_eq!(retry_delay(1, Some(Duration::from_secs(600))), QUOTA_RETRY_MAX_DELAY);
+
+        let now = chrono::DateTime::parse_from_rfc3339("2015-10-21T07:28:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        assert_eq!(parse_retry_after("12", now), Some(Duration::from_secs(12)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:05 GMT", now),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_classify_quota_error_separates_auth_from_other_failures() {
+        use CodexQuotaErrorKind::*;
+
+        assert_eq!(classify_quota_error("API returned 401 Unauthorized - {}"), AuthExpired);
+        assert_eq!(
+            classify_quota_error(r#"API returned 400 Bad Request - {"detail": {"code": "token_expired"}}"#),
+            AuthExpired
+        );
+        assert_eq!(classify_quota_error("API returned 429 Too Many Requests - slow down"), RateLimited);
+        assert_eq!(
+            classify_quota_error(r#"API returned 403 Forbidden - {"detail": "usage_limit_reached"}"#),
+            RateLimited
+        );
+        assert_eq!(classify_quota_error("API returned 502 Bad Gateway - <html>"), Network);
+        assert_eq!(classify_quota_error("Quota request failed: dns error"), Network);
+        assert_eq!(classify_quota_error("Quota request timed out: deadline"), Timeout);
+        assert_eq!(classify_quota_error("Token expired and refresh failed: 503"), Network);
+        assert_eq!(classify_quota_error("Token expired and no refresh_token is available"), AuthExpired);
+        assert_eq!(classify_quota_error("Failed to parse quota JSON: eof"), Parse);
+        assert_eq!(classify_quota_error("something else"), Unknown);
+    }
+
+    #[test]
+    fn test_token_refresh_error_keeps_refresh_kind() {
+        use crate::modules::codex_oauth::{RefreshError, RefreshErrorKind};
+
+        let classify = |kind: RefreshErrorKind| {
+            classify_quota_error(&token_refresh_error(&RefreshError {
+                kind,
+                message: "boom".to_string(),
+            }))
+        };
+        assert_eq!(classify(RefreshErrorKind::Transient), CodexQuotaErrorKind::Network);
+        assert_eq!(classify(RefreshErrorKind::Other), CodexQuotaErrorKind::AuthExpired);
+        assert_eq!(classify(RefreshErrorKind::InvalidGrant), CodexQuotaErrorKind::AuthExpired);
+    }
+
+    #[test]
+    fn test_extract_detail_code_from_body_shapes() {
+        assert_eq!(
+            extract_detail_code_from_body(r#"{"detail": {"code": "Token_Expired"}}"#).as_deref(),
+            Some("token_expired")
+        );
+        assert_eq!(
+            extract_detail_code_from_body(r#"{"error": {"code": "invalid_token"}}"#).as_deref(),
+            Some("invalid_token")
+        );
+        assert_eq!(extract_detail_code_from_body(r#"{"detail": "rate_limit_exceeded"}"#).as_deref(), Some("rate_limit_exceeded"));
+        assert_eq!(extract_detail_code_from_body("not json"), None);
     }
-    
-    Ok(results)
 }
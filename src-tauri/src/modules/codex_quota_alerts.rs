@@ -0,0 +1,201 @@
+//! Codex quota threshold alerts.
+//!
+//! After each successful quota refresh the remaining percentages are compared against the
+//! `quota_alert_warn_percent` / `quota_alert_critical_percent` settings. `codex-quota-alert`
+//! is emitted only when a window newly reaches a (more severe) level; the last level per
+//! account and window is persisted in <data_dir>/codex_quota_alerts.json so a restart does
+//! not re-alert.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::models::codex::{CodexAccount, CodexQuota};
+use crate::modules::{self, codex_wakeup_settings, logger};
+
+const ALERT_STATE_FILE: &str = "codex_quota_alerts.json";
+
+static ALERT_STATE_LOCK: std::sync::LazyLock<Mutex<()>> =
+    std::sync::LazyLock::new(|| Mutex::new(()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuotaAlertLevel {
+    Warning,
+    Critical,
+}
+
+/// Last notified level per account id, then per window ("hourly" / "weekly").
+type AlertState = HashMap<String, HashMap<String, QuotaAlertLevel>>;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct QuotaAlertPayload {
+    account_id: String,
+    account_email: String,
+    window: String,
+    level: QuotaAlertLevel,
+    remaining_percentage: i32,
+    timestamp: i64,
+}
+
+fn state_file() -> Result<PathBuf, String> {
+    Ok(modules::account::get_data_dir()?.join(ALERT_STATE_FILE))
+}
+
+fn load_state() -> AlertState {
+    state_file()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(state: &AlertState) -> Result<(), String> {
+    modules::account::ensure_data_dir_writable()?;
+    let path = state_file()?;
+    let temp_path = path.with_extension("json.tmp");
+    let content = serde_json::to_string_pretty(state)
+        .map_err(|e| format!("Failed to serialize Codex quota alert state: {}", e))?;
+    fs::write(&temp_path, content)
+        .map_err(|e| format!("Failed to write temporary quota alert file: {}", e))?;
+    fs::rename(temp_path, path).map_err(|e| format!("Failed to replace quota alert file: {}", e))
+}
+
+/// Level for `remaining` percent; a threshold of 0 or less disables that level.
+fn level_for(remaining: i32, warn_percent: i32, critical_percent: i32) -> Option<QuotaAlertLevel> {
+    if critical_percent > 0 && remaining <= critical_percent {
+        Some(QuotaAlertLevel::Critical)
+    } else if warn_percent > 0 && remaining <= warn_percent {
+        Some(QuotaAlertLevel::Warning)
+    } else {
+        None
+    }
+}
+
+/// Updates `levels` with the current window levels and returns the windows that newly reached
+/// a more severe level. Recovering (or staying put) updates the state without an alert.
+fn evaluate_windows(
+    levels: &mut HashMap<String, QuotaAlertLevel>,
+    windows: &[(&str, i32)],
+    warn_percent: i32,
+    critical_percent: i32,
+) -> Vec<(String, QuotaAlertLevel, i32)> {
+    let mut alerts = Vec::new();
+    for (window, remaining) in windows {
+        let current = level_for(*remaining, warn_percent, critical_percent);
+        let previous = levels.get(*window).copied();
+        if let Some(level) = current {
+            if previous.is_none_or(|previous| level > previous) {
+                alerts.push((window.to_string(), level, *remaining));
+            }
+            levels.insert(window.to_string(), level);
+        } else {
+            levels.remove(*window);
+        }
+    }
+    alerts
+}
+
+/// Compares a freshly fetched quota against the alert thresholds and emits
+/// `codex-quota-alert` for windows that newly crossed one. Windows flagged as unknown are skipped.
+pub fn check_thresholds(account: &CodexAccount, quota: &CodexQuota) {
+    let settings = codex_wakeup_settings::get_settings();
+    let windows: Vec<(&str, i32)> = [
+        ("hourly", quota.hourly_percentage),
+        ("weekly", quota.weekly_percentage),
+    ]
+    .into_iter()
+    .filter(|(window, _)| !quota.unknown_windows.iter().any(|w| w == window))
+    .collect();
+
+    let alerts = {
+        let Ok(_lock) = ALERT_STATE_LOCK.lock() else {
+            return;
+        };
+        let mut state = load_state();
+        let levels = state.entry(account.id.clone()).or_default();
+        let before = levels.clone();
+        let alerts = evaluate_windows(
+            levels,
+            &windows,
+            settings.quota_alert_warn_percent,
+            settings.quota_alert_critical_percent,
+        );
+        if *levels != before {
+            if levels.is_empty() {
+                state.remove(&account.id);
+            }
+            if let Err(e) = save_state(&state) {
+                logger::log_warn(&format!("Failed to save Codex quota alert state: {}", e));
+            }
+        }
+        alerts
+    };
+    if alerts.is_empty() {
+        return;
+    }
+
+    let app = crate::get_app_handle();
+    for (window, level, remaining) in alerts {
+        logger::log_warn(&format!(
+            "[CodexQuotaAlert] {:?}: email={}, window={}, remaining={}%",
+            level,
+            account.display_label(),
+            window,
+            remaining
+        ));
+        if let Some(app) = app {
+            use tauri::Emitter;
+
+            let _ = app.emit(
+                "codex-quota-alert",
+                QuotaAlertPayload {
+                    account_id: account.id.clone(),
+                    account_email: account.display_label().to_string(),
+                    window,
+                    level,
+                    remaining_percentage: remaining,
+                    timestamp: chrono::Utc::now().timestamp_millis(),
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate_windows_alerts_only_on_new_or_escalated_levels() {
+        let mut levels = HashMap::new();
+        assert!(evaluate_windows(&mut levels, &[("hourly", 50), ("weekly", 80)], 20, 5).is_empty());
+
+        let alerts = evaluate_windows(&mut levels, &[("hourly", 15), ("weekly", 80)], 20, 5);
+        assert_eq!(alerts, vec![("hourly".to_string(), QuotaAlertLevel::Warning, 15)]);
+        // Still below the warning threshold: no repeat.
+        assert!(evaluate_windows(&mut levels, &[("hourly", 12)], 20, 5).is_empty());
+
+        let alerts = evaluate_windows(&mut levels, &[("hourly", 3)], 20, 5);
+        assert_eq!(alerts, vec![("hourly".to_string(), QuotaAlertLevel::Critical, 3)]);
+        // Easing back to warning does not alert, but a later drop to critical does.
+        assert!(evaluate_windows(&mut levels, &[("hourly", 10)], 20, 5).is_empty());
+        assert_eq!(evaluate_windows(&mut levels, &[("hourly", 4)], 20, 5).len(), 1);
+
+        // Recovery clears the state so the next crossing alerts again.
+        assert!(evaluate_windows(&mut levels, &[("hourly", 100)], 20, 5).is_empty());
+        assert!(levels.is_empty());
+        assert_eq!(evaluate_windows(&mut levels, &[("hourly", 19)], 20, 5).len(), 1);
+    }
+
+    #[test]
+    fn test_level_for_ignores_disabled_thresholds() {
+        assert_eq!(level_for(5, 20, 5), Some(QuotaAlertLevel::Critical));
+        assert_eq!(level_for(5, 20, 0), Some(QuotaAlertLevel::Warning));
+        assert_eq!(level_for(5, 0, 0), None);
+        assert_eq!(level_for(21, 20, 5), None);
+    }
+}
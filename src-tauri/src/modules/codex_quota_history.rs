@@ -0,0 +1,259 @@
+//! Per-account Codex quota time-series.
+//!
+//! Directory: <data_dir>/codex_quota_history/<account_id>.json
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::models::codex::CodexQuota;
+use crate::modules;
+
+const HISTORY_DIR: &str = "codex_quota_history";
+/// Most recent samples considered when fitting a consumption rate.
+const ESTIMATE_MAX_SAMPLES: usize = 20;
+const ESTIMATE_MIN_SAMPLES: usize = 3;
+/// Minimum time covered by the fitted samples, so a single burst is not extrapolated.
+const ESTIMATE_MIN_SPAN_MS: i64 = 10 * 60 * 1000;
+
+static QUOTA_HISTORY_LOCK: std::sync::LazyLock<Mutex<()>> =
+    std::sync::LazyLock::new(|| Mutex::new(()));
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaSample {
+    /// Unix timestamp in milliseconds.
+    pub timestamp: i64,
+    pub hourly_percentage: i32,
+    pub weekly_percentage: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaAnomaly {
+    pub window: String,
+    pub previous_timestamp: i64,
+    pub timestamp: i64,
+    pub previous_percentage: i32,
+    pub current_percentage: i32,
+    /// Percentage points lost between the two samples.
+    pub delta: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuotaWindow {
+    Hourly,
+    Weekly,
+}
+
+impl QuotaWindow {
+    fn percentage(self, sample: &QuotaSample) -> i32 {
+        match self {
+            QuotaWindow::Hourly => sample.hourly_percentage,
+            QuotaWindow::Weekly => sample.weekly_percentage,
+        }
+    }
+}
+
+fn history_file(account_id: &str) -> Result<PathBuf, String> {
+    let dir = modules::account::get_data_dir()?.join(HISTORY_DIR);
+    if !dir.exists() {
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create Codex quota history dir: {}", e))?;
+    }
+    Ok(dir.join(format!("{}.json", account_id)))
+}
+
+/// Loads the quota history for one account, oldest first.
+pub fn load_quota_history(account_id: &str) -> Result<Vec<QuotaSample>, String> {
+    let path = history_file(account_id)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read Codex quota history: {}", e))?;
+    if content.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse Codex quota history: {}", e))
+}
+
+fn save_samples(account_id: &str, samples: &[QuotaSample]) -> Result<(), String> {
+    modules::account::ensure_data_dir_writable()?;
+    let path = history_file(account_id)?;
+    let temp_path = path.with_extension("json.tmp");
+
+    let content = serde_json::to_string(samples)
+        .map_err(|e| format!("Failed to serialize Codex quota history: {}", e))?;
+    fs::write(&temp_path, content)
+        .map_err(|e| format!("Failed to write temporary quota history file: {}", e))?;
+    fs::rename(temp_path, path).map_err(|e| format!("Failed to replace quota history file: {}", e))
+}
+
+/// Appends a snapshot for `account_id`, keeping the newest `quota_history_max_samples` only.
+pub fn record_sample(account_id: &str, quota: &CodexQuota) -> Result<(), String> {
+    let _lock = QUOTA_HISTORY_LOCK
+        .lock()
+        .map_err(|_| "Failed to acquire Codex quota history lock")?;
+    let mut samples = load_quota_history(account_id).unwrap_or_default();
+    samples.push(QuotaSample {
+        timestamp: chrono::Utc::now().timestamp_millis(),
+        hourly_percentage: quota.hourly_percentage,
+        weekly_percentage: quota.weekly_percentage,
+    });
+    let max_samples = modules::codex_wakeup_settings::get_settings().quota_history_max_samples;
+    trim_to_retention(&mut samples, max_samples);
+    save_samples(account_id, &samples)
+}
+
+/// Drops the oldest samples beyond `max_samples` (at least one sample is kept).
+fn trim_to_retention(samples: &mut Vec<QuotaSample>, max_samples: usize) {
+    let max_samples = max_samples.max(1);
+    if samples.len() > max_samples {
+        let overflow = samples.len() - max_samples;
+        samples.drain(..overflow);
+    }
+}
+
+fn find_anomalies(samples: &[QuotaSample], drop_threshold: i32) -> Vec<QuotaAnomaly> {
+    let mut anomalies = Vec::new();
+    for pair in samples.windows(2) {
+        let (prev, cur) = (&pair[0], &pair[1]);
+        for (window, prev_pct, cur_pct) in [
+            ("hourly", prev.hourly_percentage, cur.hourly_percentage),
+            ("weekly", prev.weekly_percentage, cur.weekly_percentage),
+        ] {
+            let delta = prev_pct - cur_pct;
+            if delta > drop_threshold {
+                anomalies.push(QuotaAnomaly {
+                    window: window.to_string(),
+                    previous_timestamp: prev.timestamp,
+                    timestamp: cur.timestamp,
+                    previous_percentage: prev_pct,
+                    current_percentage: cur_pct,
+                    delta,
+                });
+            }
+        }
+    }
+    anomalies
+}
+
+/// Flags consecutive samples where remaining quota dropped by more than `drop_threshold` points.
+pub fn detect_quota_anomalies(
+    account_id: &str,
+    drop_threshold: i32,
+) -> Result<Vec<QuotaAnomaly>, String> {
+    let mut samples = load_quota_history(account_id)?;
+    if samples.len() < 2 {
+        return Ok(Vec::new());
+    }
+    samples.sort_by_key(|sample| sample.timestamp);
+    Ok(find_anomalies(&samples, drop_threshold.max(0)))
+}
+
+/// Projects when `window` reaches 0% from `samples` (oldest first).
+///
+/// Only the samples since the window last went up (i.e. since its latest reset) are used,
+/// capped at the newest [`ESTIMATE_MAX_SAMPLES`]. A least-squares line is fitted over them
+/// and the time to empty is measured from the newest sample's remaining percentage.
+/// The upcoming reset is ignored, so the estimate may exceed the time left in the window.
+fn project_time_to_empty(samples: &[QuotaSample], window: QuotaWindow) -> Option<std::time::Duration> {
+    let latest = samples.last()?;
+    let mut start = samples.len() - 1;
+    while start > 0 && window.percentage(&samples[start - 1]) >= window.percentage(&samples[start]) {
+        start -= 1;
+    }
+    let recent = &samples[start.max(samples.len().saturating_sub(ESTIMATE_MAX_SAMPLES))..];
+    if recent.len() < ESTIMATE_MIN_SAMPLES
+        || latest.timestamp - recent[0].timestamp < ESTIMATE_MIN_SPAN_MS
+    {
+        return None;
+    }
+
+    let n = recent.len() as f64;
+    let origin = recent[0].timestamp;
+    let xs: Vec<f64> = recent.iter().map(|s| (s.timestamp - origin) as f64).collect();
+    let ys: Vec<f64> = recent.iter().map(|s| window.percentage(s) as f64).collect();
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().sum::<f64>() / n;
+    let covariance: f64 = xs.iter().zip(&ys).map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let variance: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    if variance <= 0.0 {
+        return None;
+    }
+    // Percentage points per millisecond; only a downward trend can empty the window.
+    let slope = covariance / variance;
+    if slope >= 0.0 {
+        return None;
+    }
+
+    let remaining = window.percentage(latest).max(0) as f64;
+    let millis = remaining / -slope;
+    Some(std::time::Duration::from_millis(millis.round() as u64))
+}
+
+/// Estimates how long until `window` runs out for `account_id` at the recent consumption rate.
+/// Returns `None` when there is too little data or usage is flat.
+pub fn estimate_time_to_empty(account_id: &str, window: QuotaWindow) -> Option<std::time::Duration> {
+    let mut samples = load_quota_history(account_id).ok()?;
+    samples.sort_by_key(|sample| sample.timestamp);
+    project_time_to_empty(&samples, window)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: i64 = 60 * 1000;
+
+    fn sample(minute: i64, hourly: i32) -> QuotaSample {
+        QuotaSample {
+            timestamp: minute * MINUTE,
+            hourly_percentage: hourly,
+            weekly_percentage: 80,
+        }
+    }
+
+    #[test]
+    fn test_projects_linear_consumption_since_last_reset() {
+        // 30 -> 100 is a reset; afterwards 1 point per minute is consumed.
+        let samples = vec![
+            sample(0, 40),
+            sample(10, 30),
+            sample(20, 100),
+            sample(30, 90),
+            sample(40, 80),
+        ];
+        let eta = project_time_to_empty(&samples, QuotaWindow::Hourly).unwrap();
+        assert_eq!(eta, std::time::Duration::from_millis((80 * MINUTE) as u64));
+    }
+
+    #[test]
+    fn test_returns_none_for_flat_or_insufficient_data() {
+        let flat = vec![sample(0, 50), sample(10, 50), sample(20, 50)];
+        assert_eq!(project_time_to_empty(&flat, QuotaWindow::Hourly), None);
+        assert_eq!(project_time_to_empty(&flat, QuotaWindow::Weekly), None);
+
+        let short = vec![sample(0, 50), sample(1, 49), sample(2, 48)];
+        assert_eq!(project_time_to_empty(&short, QuotaWindow::Hourly), None);
+        assert_eq!(project_time_to_empty(&[], QuotaWindow::Hourly), None);
+    }
+
+    #[test]
+    fn test_trim_to_retention_keeps_newest_samples() {
+        let mut samples: Vec<QuotaSample> = (0..5).map(|minute| sample(minute, 100)).collect();
+        trim_to_retention(&mut samples, 3);
+        assert_eq!(
+            samples.iter().map(|s| s.timestamp).collect::<Vec<_>>(),
+            vec![2 * MINUTE, 3 * MINUTE, 4 * MINUTE]
+        );
+        trim_to_retention(&mut samples, 0);
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].timestamp, 4 * MINUTE);
+    }
+}
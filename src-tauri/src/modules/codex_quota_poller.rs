@@ -0,0 +1,135 @@
+//! Periodic Codex quota refresh, independent of the wakeup scheduler.
+//!
+//! Enabled via `quota_poll_enabled` in the Codex wakeup settings. Each pass refreshes the
+//! selected accounts whose last recorded quota sample is older than the poll interval and
+//! emits `codex-quota://updated` with the ids whose percentages changed.
+
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+use crate::modules::{codex_account, codex_quota, codex_quota_history, codex_wakeup_settings, logger};
+
+const POLL_TICK_SECS: u64 = 30;
+const MIN_POLL_INTERVAL_SECS: u64 = 60;
+/// Gap between two accounts within one pass so polling never bursts the usage endpoint.
+const ACCOUNT_GAP_MS: u64 = 1_000;
+
+static STARTED: OnceLock<Mutex<bool>> = OnceLock::new();
+static LAST_POLL_AT: OnceLock<Mutex<i64>> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct QuotaUpdatedPayload {
+    account_ids: Vec<String>,
+    polled_at: i64,
+}
+
+pub fn ensure_started(app: AppHandle) {
+    let mut started = STARTED
+        .get_or_init(|| Mutex::new(false))
+        .lock()
+        .expect("codex quota poller started lock");
+    if *started {
+        return;
+    }
+    *started = true;
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            poll_once(&app).await;
+            tokio::time::sleep(Duration::from_secs(POLL_TICK_SECS)).await;
+        }
+    });
+}
+
+fn last_sample_at(account_id: &str) -> Option<i64> {
+    codex_quota_history::load_quota_history(account_id)
+        .ok()
+        .and_then(|samples| samples.iter().map(|sample| sample.timestamp).max())
+}
+
+async fn poll_once(app: &AppHandle) {
+    let settings = codex_wakeup_settings::get_settings();
+    if !settings.quota_poll_enabled {
+        return;
+    }
+
+    let interval_ms = settings.quota_poll_interval_secs.max(MIN_POLL_INTERVAL_SECS) as i64 * 1000;
+    let now = chrono::Utc::now().timestamp_millis();
+    {
+        let mut last = LAST_POLL_AT
+            .get_or_init(|| Mutex::new(0))
+            .lock()
+            .expect("codex quota poller last poll lock");
+        if now - *last < interval_ms {
+            return;
+        }
+        *last = now;
+    }
+
+    // Wakeups and manual refreshes also record samples; only poll accounts that went stale.
+    let accounts: Vec<_> = codex_account::list_accounts()
+        .into_iter()
+        .filter(|account| {
+            settings.quota_poll_accounts.is_empty()
+                || settings
+                    .quota_poll_accounts
+                    .iter()
+                    .any(|email| account.matches_label(email))
+        })
+        .filter(|account| {
+            last_sample_at(&account.id)
+                .map(|at| now - at >= interval_ms)
+                .unwrap_or(true)
+        })
+        .collect();
+    if accounts.is_empty() {
+        return;
+    }
+
+    let mut changed = Vec::new();
+    let mut failed = 0usize;
+    for (index, mut account) in accounts.into_iter().enumerate() {
+        if index > 0 {
+            tokio::time::sleep(Duration::from_millis(ACCOUNT_GAP_MS)).await;
+        }
+        let previous = account.quota.clone();
+        match codex_quota::refresh_quota_for(&mut account).await {
+            Ok(quota) => {
+                let unchanged = previous
+                    .as_ref()
+                    .map(|old| codex_quota::diff_quota(old, &quota).is_unchanged())
+                    .unwrap_or(false);
+                if !unchanged {
+                    changed.push(account.id.clone());
+                }
+            }
+            Err(e) => {
+                failed += 1;
+                logger::log_warn(&format!(
+                    "[CodexQuotaPoll] Refresh failed: email={}, error={}",
+                    account.display_label(), e
+                ));
+            }
+        }
+    }
+
+    logger::log_info(&format!(
+        "[CodexQuotaPoll] Pass finished: changed={}, failed={}",
+        changed.len(),
+        failed
+    ));
+    if !changed.is_empty() {
+        let _ = crate::modules::tray::update_tray_menu(app);
+        let _ = app.emit(
+            "codex-quota://updated",
+            QuotaUpdatedPayload {
+                account_ids: changed,
+                polled_at: now,
+            },
+        );
+    }
+}
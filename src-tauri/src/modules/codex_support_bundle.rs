@@ -0,0 +1,141 @@
+//! Redacted Codex support bundle for issue reports.
+//!
+//! Tokens are never written: only their presence and length are recorded.
+
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+use crate::models::codex::{CodexAccount, CodexQuota};
+use crate::modules::codex_wakeup_history::WakeupHistoryItem;
+use crate::modules::codex_wakeup_settings::CodexWakeupSettings;
+use crate::modules::{codex_account, codex_wakeup, codex_wakeup_history, codex_wakeup_settings, logger};
+
+const MAX_BUNDLE_HISTORY_ITEMS: usize = 50;
+const REDACTED: &str = "[redacted]";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenSummary {
+    present: bool,
+    length: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BundleAccount {
+    id: String,
+    email: String,
+    plan_type: Option<String>,
+    has_chatgpt_account_id: bool,
+    tags: Option<Vec<String>>,
+    created_at: i64,
+    last_used: i64,
+    id_token: TokenSummary,
+    access_token: TokenSummary,
+    access_token_expired: bool,
+    refresh_token: TokenSummary,
+    quota: Option<CodexQuota>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BundleCli {
+    path: Option<String>,
+    version: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SupportBundle {
+    generated_at: String,
+    app_version: String,
+    os: String,
+    arch: String,
+    cli: BundleCli,
+    settings: CodexWakeupSettings,
+    accounts: Vec<BundleAccount>,
+    history: Vec<WakeupHistoryItem>,
+}
+
+fn summarize_token(token: Option<&str>) -> TokenSummary {
+    let token = token.unwrap_or_default();
+    TokenSummary {
+        present: !token.is_empty(),
+        length: token.len(),
+    }
+}
+
+fn bundle_account(account: CodexAccount) -> BundleAccount {
+    let quota = account.quota.map(|quota| CodexQuota {
+        // Raw payloads can echo account identifiers; keep only parsed values.
+        raw_data: None,
+        ..quota
+    });
+    BundleAccount {
+        access_token_expired: crate::modules::codex_oauth::is_token_expired(
+            &account.tokens.access_token,
+        ),
+        id_token: summarize_token(Some(&account.tokens.id_token)),
+        access_token: summarize_token(Some(&account.tokens.access_token)),
+        refresh_token: summarize_token(account.tokens.refresh_token.as_deref()),
+        id: account.id,
+        email: account.email,
+        plan_type: account.plan_type,
+        has_chatgpt_account_id: account.account_id.is_some(),
+        tags: account.tags,
+        created_at: account.created_at,
+        last_used: account.last_used,
+        quota,
+    }
+}
+
+/// Writes a JSON support bundle to `path`; `redact_messages` blanks history prompts and replies.
+pub fn export_support_bundle(path: &Path, redact_messages: bool) -> Result<(), String> {
+    let cli = match codex_wakeup::codex_cli_version() {
+        Ok((cli_path, version)) => BundleCli {
+            path: Some(cli_path.to_string_lossy().to_string()),
+            version: Some(version),
+            error: None,
+        },
+        Err(err) => BundleCli {
+            path: None,
+            version: None,
+            error: Some(err),
+        },
+    };
+
+    let mut history = codex_wakeup_history::load_history().unwrap_or_default();
+    history.truncate(MAX_BUNDLE_HISTORY_ITEMS);
+    if redact_messages {
+        for item in &mut history {
+            item.prompt = item.prompt.as_ref().map(|_| REDACTED.to_string());
+            item.message = item.message.as_ref().map(|_| REDACTED.to_string());
+        }
+    }
+
+    let bundle = SupportBundle {
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        cli,
+        settings: codex_wakeup_settings::get_settings(),
+        accounts: codex_account::list_accounts()
+            .into_iter()
+            .map(bundle_account)
+            .collect(),
+        history,
+    };
+
+    let content = serde_json::to_string_pretty(&bundle)
+        .map_err(|e| format!("Failed to serialize support bundle: {}", e))?;
+    fs::write(path, content).map_err(|e| format!("Failed to write support bundle: {}", e))?;
+    logger::log_info(&format!(
+        "[CodexSupport] Support bundle exported: path={}, accounts={}",
+        path.display(),
+        bundle.accounts.len()
+    ));
+    Ok(())
+}
@@ -1,29 +1,120 @@
 use chrono::{Local, TimeZone};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::sync::{Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 
 use crate::models::codex::{CodexAccount, CodexQuota};
-use crate::modules::{codex_account, codex_quota, logger};
+use crate::modules::codex_wakeup_settings::ReplyWindowOrder;
+use crate::utils::ansi::strip_ansi;
+use crate::modules::{codex_account, codex_metrics, codex_quota, codex_wakeup_history, codex_wakeup_settings, logger};
 
 const MODEL_HOURLY: &str = "codex-hourly";
 const MODEL_WEEKLY: &str = "codex-weekly";
+/// Resolved per call to whichever window is closer to exhaustion.
+const MODEL_AUTO: &str = "codex-auto";
 const CLI_MODEL: &str = "gpt-5.3-codex";
 const CLI_REASONING_LEVEL: &str = "low";
-const CLI_REASONING_CONFIG: &str = "model_reasoning_effort=\"low\"";
+const CLI_REASONING_LEVELS: [&str; 4] = ["minimal", "low", "medium", "high"];
+const QUICK_TEST_REASONING_LEVEL: &str = "minimal";
+const QUICK_TEST_TIMEOUT_SECS: u64 = 20;
+const CLI_TIMEOUT_ERROR_PREFIX: &str = "Codex CLI wakeup timed out after ";
+/// Marks an error from spawning the CLI process (as opposed to the CLI running and failing).
+const CLI_SPAWN_ERROR_PREFIX: &str = "spawn failed: ";
+const CLI_LAUNCH_ERROR_PREFIX: &str = "Failed to launch codex CLI wakeup";
+const CLI_AUTH_REQUIRED_ERROR: &str = "CLI requires re-authentication for this account";
+/// Start of the error for a CLI run whose exit code maps to failure; the code follows.
+const CLI_EXIT_ERROR_PREFIX: &str = "Codex CLI wakeup failed (exit=";
+/// Lowercased starts of CLI stderr lines that mean the auth file was rejected and the
+/// CLI is waiting for an interactive login it will never get.
+const CLI_LOGIN_PROMPT_MARKERS: [&str; 9] = [
+    "not logged in",
+    "please log in",
+    "please login",
+    "please run `codex login`",
+    "run `codex login`",
+    "sign in with chatgpt",
+    "authentication required",
+    "your access token could not be refreshed",
+    "401 unauthorized",
+];
+/// Lowercased stderr lines that open the session transcript (echoed prompt, model output).
+/// Login prompts only appear before them, so scanning stops at the first one.
+const CLI_TRANSCRIPT_START_LINES: [&str; 4] = ["user", "user instructions:", "codex", "thinking"];
+/// History `failure_kind` for wakeups aborted because the account must re-authenticate.
+pub const FAILURE_KIND_AUTH_REQUIRED: &str = "auth_required";
+/// History `failure_kind` for wakeups killed by the CLI timeout.
+pub const FAILURE_KIND_TIMEOUT: &str = "timeout";
+/// History `failure_kind` for `require_reply` wakeups whose normalized reply was empty.
+pub const FAILURE_KIND_EMPTY_REPLY: &str = "empty_reply";
+const CLI_EMPTY_REPLY_ERROR: &str = "Codex CLI returned an empty reply";
+/// Stand-in reply when neither the last-message file nor stdout held a message.
+const NO_REPLY_PLACEHOLDER: &str = "Wakeup request sent.";
 const DEFAULT_WAKEUP_PROMPT: &str = "Reply with exactly: OK";
 const DUPLICATE_WAKEUP_WINDOW_MS: i64 = 8_000;
+const MAX_PROMPT_FILE_BYTES: u64 = 64 * 1024;
+/// Prompts longer than this (in bytes) are piped to `codex exec -` via stdin instead of
+/// passed as an argument. Windows caps a whole command line at 32767 chars (8191 via
+/// `cmd /C`, which npm shims go through), so long prompts must not ride on argv.
+const PROMPT_STDIN_THRESHOLD_BYTES: usize = 4 * 1024;
+/// Variables kept when the CLI runs with an isolated environment (`CODEX_HOME` is always set).
+#[cfg(target_os = "windows")]
+const ISOLATED_ENV_PASSTHROUGH: [&str; 3] = ["PATH", "USERPROFILE", "SYSTEMROOT"];
+#[cfg(not(target_os = "windows"))]
+const ISOLATED_ENV_PASSTHROUGH: [&str; 2] = ["PATH", "HOME"];
 
-static LAST_WAKEUP_EXEC_AT: OnceLock<Mutex<HashMap<String, i64>>> = OnceLock::new();
+/// Dedup key -> (launched_at_ms, duplicate window in ms used for that launch).
+static LAST_WAKEUP_EXEC_AT: OnceLock<Mutex<HashMap<String, (i64, i64)>>> = OnceLock::new();
+static LAST_GLOBAL_LAUNCH_AT: OnceLock<Mutex<i64>> = OnceLock::new();
+static LAST_WAKEUP_SUCCESS_AT: OnceLock<Mutex<HashMap<String, i64>>> = OnceLock::new();
+static DAILY_WAKEUP_COUNTS: OnceLock<Mutex<HashMap<String, (chrono::NaiveDate, u32)>>> =
+    OnceLock::new();
+static CLI_MODELS_CACHE: OnceLock<Mutex<Option<Vec<CliModel>>>> = OnceLock::new();
+static REPLY_CACHE: OnceLock<Mutex<HashMap<ReplyCacheKey, (i64, WakeupResponse)>>> = OnceLock::new();
+static WAKEUP_LIMITER: OnceLock<WakeupLimiter> = OnceLock::new();
+static CLI_PATH_CACHE: OnceLock<Mutex<Option<PathBuf>>> = OnceLock::new();
+/// Last verbose CLI capture per account id.
+static LAST_CLI_DEBUG: OnceLock<Mutex<HashMap<String, WakeupDebug>>> = OnceLock::new();
 
-fn wakeup_state() -> &'static Mutex<HashMap<String, i64>> {
+/// (account_id, prompt, model)
+type ReplyCacheKey = (String, String, String);
+
+fn wakeup_state() -> &'static Mutex<HashMap<String, (i64, i64)>> {
     LAST_WAKEUP_EXEC_AT.get_or_init(|| Mutex::new(HashMap::new()))
 }
 
-#[derive(Debug, Serialize)]
+fn global_launch_state() -> &'static Mutex<i64> {
+    LAST_GLOBAL_LAUNCH_AT.get_or_init(|| Mutex::new(0))
+}
+
+fn reply_cache() -> &'static Mutex<HashMap<ReplyCacheKey, (i64, WakeupResponse)>> {
+    REPLY_CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn cached_reply(key: &ReplyCacheKey, ttl_ms: i64) -> Option<WakeupResponse> {
+    let now = chrono::Utc::now().timestamp_millis();
+    let mut guard = reply_cache().lock().expect("codex reply cache lock");
+    guard.retain(|_, (stored_at, _)| now - *stored_at < ttl_ms);
+    guard.get(key).map(|(_, response)| {
+        let mut cached = response.clone();
+        cached.from_cache = true;
+        if let Some(details) = cached.details.as_mut() {
+            details.cli_executed = false;
+        }
+        cached
+    })
+}
+
+fn store_cached_reply(key: ReplyCacheKey, response: &WakeupResponse) {
+    let now = chrono::Utc::now().timestamp_millis();
+    let mut guard = reply_cache().lock().expect("codex reply cache lock");
+    guard.insert(key, (now, response.clone()));
+}
+
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct WakeupResponse {
     pub reply: String,
@@ -33,6 +124,217 @@ pub struct WakeupResponse {
     pub trace_id: Option<String>,
     pub response_id: Option<String>,
     pub duration_ms: u64,
+    /// Time spent in the CLI process alone; `None` when the CLI did not run.
+    pub cli_duration_ms: Option<u64>,
+    /// The CLI round-trip exceeded the `latency_slo_ms` setting.
+    pub slo_violation: bool,
+    /// Character count of the raw CLI reply before any trimming.
+    pub reply_length: usize,
+    /// Outcome of `expect_reply_regex`; `None` when no pattern was given or the CLI was skipped.
+    pub reply_matched: Option<bool>,
+    pub warning: Option<String>,
+    /// True when served from the opt-in reply cache without launching the CLI.
+    pub from_cache: bool,
+    /// Structured view of the same result, for scripting without parsing `reply`.
+    pub details: Option<WakeupDetails>,
+    /// Full CLI capture; only set when the call asked for `verbose`.
+    pub debug: Option<WakeupDebug>,
+}
+
+impl WakeupResponse {
+    /// True when the CLI never ran (deduplicated call or reply cache hit).
+    pub fn cli_skipped(&self) -> bool {
+        self.details.as_ref().is_some_and(|details| !details.cli_executed)
+    }
+}
+
+/// Untruncated CLI run capture for debugging, with account tokens redacted.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WakeupDebug {
+    /// Program followed by its arguments.
+    pub args: Vec<String>,
+    /// `None` when the process was killed or never started.
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    /// Launch/timeout error that prevented a normal exit.
+    pub error: Option<String>,
+}
+
+impl WakeupDebug {
+    fn redacted(mut self, account: &CodexAccount) -> Self {
+        let tokens = [
+            Some(account.tokens.access_token.as_str()),
+            Some(account.tokens.id_token.as_str()),
+            account.tokens.refresh_token.as_deref(),
+        ];
+        let redact = |text: &mut String| {
+            for token in tokens.iter().flatten().filter(|token| token.len() >= 8) {
+                if text.contains(token) {
+                    *text = text.replace(token, "[redacted]");
+                }
+            }
+        };
+        self.args.iter_mut().for_each(redact);
+        redact(&mut self.stdout);
+        redact(&mut self.stderr);
+        if let Some(error) = self.error.as_mut() {
+            redact(error);
+        }
+        self
+    }
+}
+
+/// Last verbose CLI capture for an account (from a `verbose` wakeup, successful or not).
+pub fn last_wakeup_debug(account_id: &str) -> Option<WakeupDebug> {
+    LAST_CLI_DEBUG
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .expect("codex cli debug lock")
+        .get(account_id)
+        .cloned()
+}
+
+/// Machine-readable wakeup result.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WakeupDetails {
+    /// Requested window (`codex-hourly`, `codex-weekly`, ...).
+    pub model: String,
+    pub cli_model: String,
+    pub reasoning_effort: String,
+    /// False when the call was deduplicated and the CLI did not run.
+    pub cli_executed: bool,
+    pub cli_reply: String,
+    pub reply_matched: Option<bool>,
+    /// Quota windows after the wakeup; empty when the quota refresh failed.
+    pub windows: Vec<WakeupWindowDetails>,
+    /// False when the post-wakeup quota refresh failed.
+    pub quota_refreshed: bool,
+    pub duration_ms: u64,
+    pub slo_violation: bool,
+    /// Output token cap the CLI ran with; 0 means the CLI default.
+    pub max_output_tokens: u32,
+    /// Process exit code; `None` when the CLI did not run or was killed.
+    pub exit_code: Option<i32>,
+    /// Classification of the normalized reply; `None` when the CLI did not run.
+    pub reply_status: Option<ReplyStatus>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WakeupWindowDetails {
+    /// "5h" or "weekly".
+    pub window: String,
+    pub previous_percentage: Option<i32>,
+    pub remaining_percentage: i32,
+    /// Unix timestamp in seconds.
+    pub reset_at: Option<i64>,
+}
+
+/// What `WakeupResponse::reply` contains.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WakeupOutputFormat {
+    /// Human-readable sentence (previous behavior).
+    #[default]
+    Text,
+    /// `details` serialized as JSON.
+    Json,
+}
+
+/// Optional knobs for a single wakeup call; also the `options` argument of
+/// `codex_trigger_wakeup`, where every field may be omitted.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct WakeupOptions {
+    /// Pattern the trimmed CLI reply must match.
+    pub expect_reply_regex: Option<String>,
+    pub output_format: WakeupOutputFormat,
+    /// Overrides the `-m` model passed to the CLI.
+    pub cli_model: Option<String>,
+    /// Overrides `model_reasoning_effort`; one of `CLI_REASONING_LEVELS`.
+    pub reasoning_effort: Option<String>,
+    /// Overrides the duplicate-wakeup suppression window (account guard or 8s default).
+    pub duplicate_window_ms: Option<i64>,
+    /// Skip duplicate suppression; the launch is still recorded for later calls.
+    pub force: bool,
+    /// Capture full stdout/stderr/args into `WakeupResponse::debug` and `last_wakeup_debug`.
+    pub verbose: bool,
+    /// Fail the wakeup when the CLI ran but its normalized reply is empty.
+    pub require_reply: bool,
+    /// How the history record written for this call is labelled.
+    #[serde(skip)]
+    pub history: WakeupHistoryContext,
+}
+
+/// Labels for the history item every wakeup call records.
+#[derive(Debug, Clone)]
+pub struct WakeupHistoryContext {
+    /// "manual" or "auto".
+    pub trigger_type: String,
+    pub trigger_source: codex_wakeup_history::TriggerSource,
+    pub task_name: Option<String>,
+    pub replay_of: Option<String>,
+}
+
+impl Default for WakeupHistoryContext {
+    fn default() -> Self {
+        Self {
+            trigger_type: "manual".to_string(),
+            trigger_source: codex_wakeup_history::TriggerSource::Manual,
+            task_name: None,
+            replay_of: None,
+        }
+    }
+}
+
+/// Resolves the CLI model and reasoning effort for a call, falling back to the defaults.
+fn resolve_cli_overrides(options: &WakeupOptions) -> Result<(String, String), String> {
+    let cli_model = match options.cli_model.as_deref().map(str::trim) {
+        Some(model) if !model.is_empty() => model.to_string(),
+        _ => CLI_MODEL.to_string(),
+    };
+    let reasoning_effort = match options.reasoning_effort.as_deref().map(str::trim) {
+        Some(effort) if !effort.is_empty() => {
+            let effort = effort.to_ascii_lowercase();
+            if !CLI_REASONING_LEVELS.contains(&effort.as_str()) {
+                return Err(format!(
+                    "Invalid reasoning effort \"{}\"; expected one of: {}",
+                    effort,
+                    CLI_REASONING_LEVELS.join(", ")
+                ));
+            }
+            effort
+        }
+        _ => CLI_REASONING_LEVEL.to_string(),
+    };
+    Ok((cli_model, reasoning_effort))
+}
+
+/// Swaps `reply` for the JSON-encoded `details` when the caller asked for JSON.
+fn apply_output_format(mut response: WakeupResponse, format: WakeupOutputFormat) -> WakeupResponse {
+    if format == WakeupOutputFormat::Json {
+        if let Some(json) = response
+            .details
+            .as_ref()
+            .and_then(|details| serde_json::to_string(details).ok())
+        {
+            response.reply = json;
+        }
+    }
+    response
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CliModel {
+    pub id: String,
+    /// `Some(true)` when reported by the CLI, `None` when taken from the configured fallback.
+    pub available: Option<bool>,
+    /// "cli" or "config".
+    pub source: String,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -44,14 +346,135 @@ pub struct AvailableModel {
     pub recommended: Option<bool>,
 }
 
-fn format_reset_time(timestamp: Option<i64>) -> String {
-    let Some(ts) = timestamp else {
+/// Maps `codex-auto` to the window with the least remaining quota. Without a stored quota
+/// (or with an unknown window) it stays `codex-auto`, which describes both windows.
+fn resolve_auto_window<'a>(model: &'a str, quota: Option<&CodexQuota>) -> &'a str {
+    if model != MODEL_AUTO {
+        return model;
+    }
+    match quota {
+        Some(quota) if quota.unknown_windows.is_empty() => {
+            if quota.weekly_percentage < quota.hourly_percentage {
+                MODEL_WEEKLY
+            } else {
+                MODEL_HOURLY
+            }
+        }
+        _ => model,
+    }
+}
+
+const DEFAULT_RESET_TIME_FORMAT: &str = "%m-%d %H:%M";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ReplyTimezone {
+    Local,
+    Named(chrono_tz::Tz),
+    Fixed(chrono::FixedOffset),
+}
+
+/// Timezone and strftime format used for reset times in the wakeup reply.
+#[derive(Debug, Clone, PartialEq)]
+struct ReplyTimeFormat {
+    timezone: ReplyTimezone,
+    format: String,
+}
+
+impl Default for ReplyTimeFormat {
+    fn default() -> Self {
+        Self {
+            timezone: ReplyTimezone::Local,
+            format: DEFAULT_RESET_TIME_FORMAT.to_string(),
+        }
+    }
+}
+
+/// Parses `+08:00`, `-0530`, `+8`, optionally prefixed with `UTC`/`GMT`.
+fn parse_fixed_offset(value: &str) -> Option<chrono::FixedOffset> {
+    let upper = value.to_ascii_uppercase();
+    let rest = upper
+        .strip_prefix("UTC")
+        .or_else(|| upper.strip_prefix("GMT"))
+        .unwrap_or(&upper);
+    let (sign, digits) = match rest.as_bytes().first()? {
+        b'+' => (1, &rest[1..]),
+        b'-' => (-1, &rest[1..]),
+        _ => return None,
+    };
+    let (hours, minutes) = match digits.split_once(':') {
+        Some((h, m)) => (h, m),
+        None if digits.len() > 2 => digits.split_at(digits.len() - 2),
+        None => (digits, "0"),
+    };
+    if hours.is_empty() || !hours.chars().chain(minutes.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i32 = hours.parse().ok()?;
+    let minutes: i32 = minutes.parse().ok()?;
+    if hours > 14 || minutes >= 60 {
+        return None;
+    }
+    chrono::FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+impl ReplyTimeFormat {
+    /// Invalid timezones or formats fall back to Local / `%m-%d %H:%M` with a warning,
+    /// so a bad setting never fails the wakeup itself.
+    fn new(timezone: Option<&str>, format: Option<&str>) -> Self {
+        let timezone = match timezone.map(str::trim).filter(|tz| !tz.is_empty()) {
+            None => ReplyTimezone::Local,
+            Some(tz) if tz.eq_ignore_ascii_case("local") => ReplyTimezone::Local,
+            Some(tz) => {
+                if let Some(offset) = parse_fixed_offset(tz) {
+                    ReplyTimezone::Fixed(offset)
+                } else if let Ok(named) = tz.parse::<chrono_tz::Tz>() {
+                    ReplyTimezone::Named(named)
+                } else {
+                    logger::log_warn(&format!(
+                        "[CodexWakeup] Unknown reply timezone {:?}, using local time",
+                        tz
+                    ));
+                    ReplyTimezone::Local
+                }
+            }
+        };
+        let format = match format.filter(|f| !f.trim().is_empty()) {
+            None => DEFAULT_RESET_TIME_FORMAT.to_string(),
+            Some(f) => {
+                let valid = chrono::format::StrftimeItems::new(f)
+                    .all(|item| !matches!(item, chrono::format::Item::Error));
+                if valid {
+                    f.to_string()
+                } else {
+                    logger::log_warn(&format!(
+                        "[CodexWakeup] Invalid reply time format {:?}, using {}",
+                        f, DEFAULT_RESET_TIME_FORMAT
+                    ));
+                    DEFAULT_RESET_TIME_FORMAT.to_string()
+                }
+            }
+        };
+        Self { timezone, format }
+    }
+
+    fn from_settings(settings: &codex_wakeup_settings::CodexWakeupSettings) -> Self {
+        Self::new(
+            settings.reply_timezone.as_deref(),
+            settings.reply_time_format.as_deref(),
+        )
+    }
+}
+
+fn format_reset_time(timestamp: Option<i64>, time_format: &ReplyTimeFormat) -> String {
+    let Some(utc) = timestamp.and_then(|ts| chrono::DateTime::from_timestamp(ts, 0)) else {
         return "-".to_string();
     };
-    if let Some(local_dt) = Local.timestamp_opt(ts, 0).single() {
-        return local_dt.format("%m-%d %H:%M").to_string();
+    let format = time_format.format.as_str();
+    match time_format.timezone {
+        ReplyTimezone::Local => utc.with_timezone(&Local).format(format).to_string(),
+        ReplyTimezone::Named(tz) => utc.with_timezone(&tz).format(format).to_string(),
+        ReplyTimezone::Fixed(offset) => utc.with_timezone(&offset).format(format).to_string(),
     }
-    "-".to_string()
 }
 
 fn describe_window_change(
@@ -59,6 +482,7 @@ fn describe_window_change(
     old_remaining: Option<i32>,
     new_remaining: i32,
     reset_at: Option<i64>,
+    time_format: &ReplyTimeFormat,
 ) -> String {
     let remaining_text = match old_remaining {
         Some(old) => format!("{}% -> {}%", old, new_remaining),
@@ -68,7 +492,7 @@ fn describe_window_change(
         "{} remaining {}, reset {}",
         name,
         remaining_text,
-        format_reset_time(reset_at)
+        format_reset_time(reset_at, time_format)
     )
 }
 
@@ -81,15 +505,63 @@ fn trim_for_log(value: &str, max_chars: usize) -> String {
     out
 }
 
-fn build_reply(
+fn build_window_details(
     model: &str,
     old_quota: Option<&CodexQuota>,
     new_quota: Option<&CodexQuota>,
-    cli_reply: &str,
-) -> String {
+) -> Vec<WakeupWindowDetails> {
+    let Some(new_quota) = new_quota else {
+        return Vec::new();
+    };
+    let hourly = WakeupWindowDetails {
+        window: "5h".to_string(),
+        previous_percentage: old_quota.map(|q| q.hourly_percentage),
+        remaining_percentage: new_quota.hourly_percentage,
+        reset_at: new_quota.hourly_reset_time,
+    };
+    let weekly = WakeupWindowDetails {
+        window: "weekly".to_string(),
+        previous_percentage: old_quota.map(|q| q.weekly_percentage),
+        remaining_percentage: new_quota.weekly_percentage,
+        reset_at: new_quota.weekly_reset_time,
+    };
+    match model {
+        MODEL_HOURLY => vec![hourly],
+        MODEL_WEEKLY => vec![weekly],
+        _ => vec![hourly, weekly],
+    }
+}
+
+/// Inputs for [`build_reply`].
+struct ReplyContext<'a> {
+    model: &'a str,
+    cli_model: &'a str,
+    reasoning_effort: &'a str,
+    old_quota: Option<&'a CodexQuota>,
+    new_quota: Option<&'a CodexQuota>,
+    cli_reply: &'a str,
+    window_order: ReplyWindowOrder,
+    /// Controls the reply when the post-wakeup refresh failed (`new_quota` is `None`): the
+    /// pre-wakeup quota is shown with a caveat instead of no window info.
+    show_stale_quota: bool,
+    time_format: &'a ReplyTimeFormat,
+}
+
+fn build_reply(context: ReplyContext) -> String {
+    let ReplyContext {
+        model,
+        cli_model,
+        reasoning_effort,
+        old_quota,
+        new_quota,
+        cli_reply,
+        window_order,
+        show_stale_quota,
+        time_format,
+    } = context;
     let cli_model_part = format!(
         " Used CLI model {} (reasoning: {}).",
-        CLI_MODEL, CLI_REASONING_LEVEL
+        cli_model, reasoning_effort
     );
     let cli_reply_part = if cli_reply.trim().is_empty() {
         String::new()
@@ -97,11 +569,19 @@ fn build_reply(
         format!(" Reply: {}", trim_for_log(cli_reply.trim(), 140))
     };
 
-    let Some(new_quota) = new_quota else {
-        return format!(
-            "Codex wakeup request completed.{}{}",
-            cli_model_part, cli_reply_part
-        );
+    let (new_quota, old_quota, status) = match (new_quota, old_quota) {
+        (Some(new_quota), _) => (new_quota, old_quota, "completed."),
+        (None, Some(old_quota)) if show_stale_quota => (
+            old_quota,
+            None,
+            "completed (quota refresh failed; showing quota as of before wakeup).",
+        ),
+        (None, _) => {
+            return format!(
+                "Codex wakeup request completed (quota refresh failed).{}{}",
+                cli_model_part, cli_reply_part
+            );
+        }
     };
 
     let hourly = describe_window_change(
@@ -109,32 +589,52 @@ fn build_reply(
         old_quota.map(|q| q.hourly_percentage),
         new_quota.hourly_percentage,
         new_quota.hourly_reset_time,
+        time_format,
     );
     let weekly = describe_window_change(
         "Weekly",
         old_quota.map(|q| q.weekly_percentage),
         new_quota.weekly_percentage,
         new_quota.weekly_reset_time,
+        time_format,
     );
 
     match model {
         MODEL_HOURLY => format!(
-            "Codex wakeup completed. {}.{}{}",
-            hourly, cli_model_part, cli_reply_part
+            "Codex wakeup {} {}.{}{}",
+            status, hourly, cli_model_part, cli_reply_part
         ),
         MODEL_WEEKLY => format!(
-            "Codex wakeup completed. {}.{}{}",
-            weekly, cli_model_part, cli_reply_part
-        ),
-        _ => format!(
-            "Codex wakeup completed. {} | {}.{}{}",
-            hourly, weekly, cli_model_part, cli_reply_part
+            "Codex wakeup {} {}.{}{}",
+            status, weekly, cli_model_part, cli_reply_part
         ),
+        _ => {
+            let weekly_first = match window_order {
+                ReplyWindowOrder::HourlyFirst => false,
+                ReplyWindowOrder::WeeklyFirst => true,
+                ReplyWindowOrder::MostConstrainedFirst => {
+                    new_quota.weekly_percentage < new_quota.hourly_percentage
+                }
+            };
+            let (first, second) = if weekly_first {
+                (weekly, hourly)
+            } else {
+                (hourly, weekly)
+            };
+            format!(
+                "Codex wakeup {} {} | {}.{}{}",
+                status, first, second, cli_model_part, cli_reply_part
+            )
+        }
     }
 }
 
+fn temp_home_base_dir() -> PathBuf {
+    std::env::temp_dir().join("cockpit-tools-codex-wakeup")
+}
+
 fn next_temp_home_dir() -> Result<PathBuf, String> {
-    let base = std::env::temp_dir().join("cockpit-tools-codex-wakeup");
+    let base = temp_home_base_dir();
     fs::create_dir_all(&base).map_err(|e| format!("Failed to create temp wakeup base dir: {}", e))?;
 
     let nanos = std::time::SystemTime::now()
@@ -147,6 +647,76 @@ fn next_temp_home_dir() -> Result<PathBuf, String> {
     Ok(path)
 }
 
+/// Lists temp `CODEX_HOME` session dirs created by this process that still exist.
+///
+/// Every `run_codex_wakeup_cli` call removes its session dir on all paths, so once no
+/// wakeup is in flight this should return `Ok(())`; anything left over is a leak.
+pub fn assert_no_leaked_sessions_for_pid() -> Result<(), Vec<PathBuf>> {
+    let prefix = format!("session-{}-", std::process::id());
+    let Ok(entries) = fs::read_dir(temp_home_base_dir()) else {
+        return Ok(());
+    };
+    let mut leaked: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .map(|entry| entry.path())
+        .collect();
+    if leaked.is_empty() {
+        return Ok(());
+    }
+    leaked.sort();
+    Err(leaked)
+}
+
+/// Session dirs younger than this are left alone at startup even if their pid is gone.
+pub const STALE_SESSION_MAX_AGE: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Parses `session-<pid>-<nanos>` into its pid and creation time (nanos since epoch).
+fn parse_session_dir_name(name: &str) -> Option<(u32, u128)> {
+    let (pid, nanos) = name.strip_prefix("session-")?.split_once('-')?;
+    Some((pid.parse().ok()?, nanos.parse().ok()?))
+}
+
+/// Removes temp `CODEX_HOME` session dirs older than `max_age` whose owning process is
+/// no longer alive (left behind by crashes or kills). Returns how many were removed.
+pub fn cleanup_stale_wakeup_dirs(max_age: std::time::Duration) -> usize {
+    let Ok(entries) = fs::read_dir(temp_home_base_dir()) else {
+        return 0;
+    };
+    let now_nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let own_pid = std::process::id();
+    let mut alive: HashMap<u32, bool> = HashMap::new();
+    let mut removed = 0usize;
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some((pid, created_nanos)) = parse_session_dir_name(&name) else {
+            continue;
+        };
+        if pid == own_pid || now_nanos.saturating_sub(created_nanos) < max_age.as_nanos() {
+            continue;
+        }
+        if *alive
+            .entry(pid)
+            .or_insert_with(|| crate::modules::process::is_pid_running(pid))
+        {
+            continue;
+        }
+        match fs::remove_dir_all(entry.path()) {
+            Ok(()) => removed += 1,
+            Err(e) => logger::log_warn(&format!(
+                "[CodexWakeup] Failed to remove stale temp dir {}: {}",
+                entry.path().display(),
+                e
+            )),
+        }
+    }
+    removed
+}
+
 fn add_candidate(list: &mut Vec<PathBuf>, seen: &mut std::collections::HashSet<String>, path: PathBuf) {
     let key = path.to_string_lossy().to_string().to_lowercase();
     if seen.insert(key) {
@@ -154,6 +724,61 @@ fn add_candidate(list: &mut Vec<PathBuf>, seen: &mut std::collections::HashSet<S
     }
 }
 
+/// Lists version directories under `root` in descending name order; missing roots yield nothing.
+#[cfg(not(target_os = "windows"))]
+fn list_version_dirs(root: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut versions: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    versions.sort_by(|a, b| b.file_name().cmp(&a.file_name()));
+    versions
+}
+
+/// Bin directories of common node version managers (nvm, fnm, volta).
+#[cfg(not(target_os = "windows"))]
+fn node_manager_bin_dirs() -> Vec<PathBuf> {
+    let mut bin_dirs: Vec<PathBuf> = Vec::new();
+    let home = dirs::home_dir();
+
+    let nvm_dir = std::env::var_os("NVM_DIR")
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|h| h.join(".nvm")));
+    if let Some(nvm_dir) = nvm_dir {
+        for version in list_version_dirs(&nvm_dir.join("versions").join("node")) {
+            bin_dirs.push(version.join("bin"));
+        }
+    }
+
+    let mut fnm_roots: Vec<PathBuf> = Vec::new();
+    if let Some(fnm_dir) = std::env::var_os("FNM_DIR") {
+        fnm_roots.push(PathBuf::from(fnm_dir));
+    }
+    if let Some(ref h) = home {
+        fnm_roots.push(h.join(".fnm"));
+        fnm_roots.push(h.join(".local").join("share").join("fnm"));
+        fnm_roots.push(h.join("Library").join("Application Support").join("fnm"));
+    }
+    for root in fnm_roots {
+        for version in list_version_dirs(&root.join("node-versions")) {
+            bin_dirs.push(version.join("installation").join("bin"));
+        }
+    }
+
+    let volta_home = std::env::var_os("VOLTA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|h| h.join(".volta")));
+    if let Some(volta_home) = volta_home {
+        bin_dirs.push(volta_home.join("bin"));
+    }
+
+    bin_dirs
+}
+
 fn codex_cli_candidates() -> Vec<PathBuf> {
     let mut candidates: Vec<PathBuf> = Vec::new();
     let mut seen = std::collections::HashSet::new();
@@ -189,6 +814,14 @@ fn codex_cli_candidates() -> Vec<PathBuf> {
         }
     }
 
+    #[cfg(not(target_os = "windows"))]
+    {
+        // GUI launches do not inherit shell PATH tweaks from node version managers.
+        for dir in node_manager_bin_dirs() {
+            add_candidate(&mut candidates, &mut seen, dir.join("codex"));
+        }
+    }
+
     if let Some(path_var) = std::env::var_os("PATH") {
         for dir in std::env::split_paths(&path_var) {
             #[cfg(target_os = "windows")]
@@ -208,10 +841,38 @@ fn codex_cli_candidates() -> Vec<PathBuf> {
     candidates
 }
 
-fn resolve_codex_cli_path() -> Result<PathBuf, String> {
+/// Resolves the CLI binary, reusing the last hit while it still exists on disk.
+///
+/// An account-level `cli_path` wins over `CODEX_CLI_PATH` and the PATH search, and is
+/// never cached or silently skipped: a missing override is an error.
+fn resolve_codex_cli_path(account: Option<&CodexAccount>) -> Result<PathBuf, String> {
+    if let Some(custom) = account
+        .and_then(|a| a.cli_path.as_deref())
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+    {
+        let path = PathBuf::from(custom);
+        if path.is_file() {
+            return Ok(path);
+        }
+        return Err(format!(
+            "Codex CLI override for account {} not found: {}",
+            account.map(|a| a.display_label()).unwrap_or_default(),
+            path.display()
+        ));
+    }
+
+    let cache = CLI_PATH_CACHE.get_or_init(|| Mutex::new(None));
+    if let Some(cached) = cache.lock().expect("codex cli path cache lock").clone() {
+        if cached.is_file() {
+            return Ok(cached);
+        }
+    }
+
     let candidates = codex_cli_candidates();
     for candidate in &candidates {
         if candidate.is_file() {
+            *cache.lock().expect("codex cli path cache lock") = Some(candidate.clone());
             return Ok(candidate.clone());
         }
     }
@@ -248,208 +909,2116 @@ fn command_for_executable(executable: &Path) -> Command {
     Command::new(executable)
 }
 
-fn read_last_message(path: &PathBuf, stdout: &str) -> String {
+/// Resolved CLI binary and its `--version` output.
+pub fn codex_cli_version() -> Result<(PathBuf, String), String> {
+    let codex_cli = resolve_codex_cli_path(None)?;
+    let output = command_for_executable(&codex_cli)
+        .arg("--version")
+        .output()
+        .map_err(|e| format!("Failed to launch codex CLI (binary={}): {}", codex_cli.display(), e))?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || version.is_empty() {
+        return Err(format!(
+            "codex --version failed (binary={}): {}",
+            codex_cli.display(),
+            trim_for_log(String::from_utf8_lossy(&output.stderr).trim(), 200)
+        ));
+    }
+    Ok((codex_cli, version))
+}
+
+/// Preflight result for the Codex CLI.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CliInfo {
+    pub path: String,
+    /// Semver-like part of the version output (e.g. `0.46.0`), or the raw output if none.
+    pub version: String,
+    pub raw_version: String,
+}
+
+/// First whitespace-separated token that looks like `digits.digits...`.
+fn parse_cli_version(raw: &str) -> String {
+    raw.split_whitespace()
+        .map(|token| token.trim_start_matches('v'))
+        .find(|token| {
+            token.contains('.')
+                && token
+                    .split('.')
+                    .next()
+                    .is_some_and(|major| !major.is_empty() && major.chars().all(|c| c.is_ascii_digit()))
+        })
+        .unwrap_or(raw)
+        .to_string()
+}
+
+/// Checks the CLI is installed and runnable, for a status indicator before wakeups.
+pub async fn check_codex_cli() -> Result<CliInfo, String> {
+    let (path, raw_version) = tauri::async_runtime::spawn_blocking(codex_cli_version)
+        .await
+        .map_err(|e| format!("Codex CLI check task failed: {}", e))??;
+    Ok(CliInfo {
+        path: path.to_string_lossy().to_string(),
+        version: parse_cli_version(&raw_version),
+        raw_version,
+    })
+}
+
+/// Resolves and caches the CLI path, checks its version and opens a connection to the
+/// usage host, so the first wakeup skips that work and a missing CLI is reported early.
+pub async fn warmup() {
+    let started = std::time::Instant::now();
+    match tauri::async_runtime::spawn_blocking(codex_cli_version).await {
+        Ok(Ok((path, version))) => logger::log_info(&format!(
+            "[CodexWakeup] Warmup: CLI {} ({})",
+            version,
+            path.display()
+        )),
+        Ok(Err(err)) => logger::log_warn(&format!("[CodexWakeup] Warmup: {}", err)),
+        Err(join_err) => logger::log_warn(&format!(
+            "[CodexWakeup] Warmup task failed: {}",
+            join_err
+        )),
+    }
+    if let Err(err) = codex_quota::warm_connection().await {
+        logger::log_warn(&format!("[CodexWakeup] Warmup: {}", err));
+    }
+    logger::log_info(&format!(
+        "[CodexWakeup] Warmup finished in {}ms",
+        started.elapsed().as_millis()
+    ));
+}
+
+fn is_summary_line(line: &str, ignored_prefixes: &[String]) -> bool {
+    let lower = line.to_lowercase();
+    ignored_prefixes
+        .iter()
+        .map(|prefix| prefix.trim().to_lowercase())
+        .any(|prefix| !prefix.is_empty() && lower.starts_with(&prefix))
+}
+
+/// Bare counters such as `1,234` that the CLI prints on the line after a summary label.
+fn is_count_line(line: &str) -> bool {
+    line.chars().any(|c| c.is_ascii_digit())
+        && line
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == ',' || c == '.' || c == '_' || c.is_whitespace())
+}
+
+fn read_last_message(path: &PathBuf, stdout: &str, ignored_prefixes: &[String]) -> String {
     if let Ok(content) = fs::read_to_string(path) {
+        let content = strip_ansi(&content);
         let trimmed = content.trim();
         if !trimmed.is_empty() {
             return trimmed.to_string();
         }
     }
 
-    stdout
-        .lines()
-        .map(|line| line.trim())
-        .rev()
-        .find(|line| !line.is_empty() && *line != "tokens used")
-        .unwrap_or("Wakeup request sent.")
-        .to_string()
+    // Some CLI versions emit escapes even with `--color never` (spinners etc.).
+    let stdout = strip_ansi(stdout);
+
+    let mut after_summary = false;
+    let mut last_message: Option<&str> = None;
+    for line in stdout.lines().map(|line| line.trim()) {
+        if line.is_empty() {
+            continue;
+        }
+        if is_summary_line(line, ignored_prefixes) {
+            after_summary = true;
+            continue;
+        }
+        if after_summary && is_count_line(line) {
+            after_summary = false;
+            continue;
+        }
+        after_summary = false;
+        last_message = Some(line);
+    }
+
+    last_message.unwrap_or(NO_REPLY_PLACEHOLDER).to_string()
 }
 
-fn run_codex_wakeup_cli(account: &CodexAccount, prompt: &str) -> Result<String, String> {
-    let temp_home = next_temp_home_dir()?;
-    let output_file = temp_home.join("last_message.txt");
-    let codex_cli = resolve_codex_cli_path()?;
+/// Reply with ANSI escapes removed and whitespace runs collapsed to single spaces.
+fn normalize_reply(raw: &str) -> String {
+    strip_ansi(raw).split_whitespace().collect::<Vec<_>>().join(" ")
+}
 
-    let run_result = (|| -> Result<String, String> {
-        codex_account::write_auth_file_to_dir(&temp_home, account)?;
+/// How a CLI reply reads once normalized.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplyStatus {
+    /// The reply carries at least one letter or digit.
+    Ok,
+    /// Empty, whitespace/punctuation only, or the no-reply placeholder.
+    Unexpected,
+}
 
-        logger::log_info(&format!(
-            "[CodexWakeup] Using Codex CLI binary: {}",
-            codex_cli.display()
-        ));
+fn classify_reply(raw: &str) -> ReplyStatus {
+    let normalized = normalize_reply(raw);
+    if normalized == NO_REPLY_PLACEHOLDER || !normalized.chars().any(char::is_alphanumeric) {
+        ReplyStatus::Unexpected
+    } else {
+        ReplyStatus::Ok
+    }
+}
 
-        let mut command = command_for_executable(&codex_cli);
-        command
-            .arg("exec")
-            .arg("-m")
-            .arg(CLI_MODEL)
-            .arg("-c")
-            .arg(CLI_REASONING_CONFIG)
-            .arg("--skip-git-repo-check")
-            .arg("--color")
-            .arg("never")
-            .arg("--output-last-message")
-            .arg(&output_file);
-        if let Ok(cwd) = std::env::current_dir() {
-            command.arg("-C").arg(cwd);
+/// Final assistant message from the `--json` event stream. A `task_complete` event's
+/// `last_agent_message` wins; otherwise the last `agent_message` (either the
+/// `msg.message` or the `item.text` event shape) is used.
+fn parse_json_final_message(stdout: &str) -> Option<String> {
+    let mut last_message: Option<String> = None;
+    let mut final_message: Option<String> = None;
+    for line in stdout.lines().map(|line| line.trim()) {
+        if !line.starts_with('{') {
+            continue;
         }
-        command.arg(prompt);
-        command.env("CODEX_HOME", &temp_home);
-        #[cfg(target_os = "windows")]
-        {
-            if let Ok(appdata) = std::env::var("APPDATA") {
-                let npm_dir = PathBuf::from(appdata).join("npm");
-                if npm_dir.exists() {
-                    let mut path_entries = vec![npm_dir];
-                    if let Some(current_path) = std::env::var_os("PATH") {
-                        path_entries.extend(std::env::split_paths(&current_path));
-                    }
-                    if let Ok(joined) = std::env::join_paths(path_entries) {
-                        command.env("PATH", joined);
-                    }
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        let text_of = |event: Option<&serde_json::Value>, key: &str| {
+            event
+                .and_then(|event| event.get(key))
+                .and_then(|text| text.as_str())
+                .map(|text| text.trim().to_string())
+                .filter(|text| !text.is_empty())
+        };
+        let msg = value.get("msg");
+        let item = value.get("item");
+        match msg.and_then(|msg| msg.get("type")).and_then(|t| t.as_str()) {
+            Some("task_complete") => {
+                if let Some(text) = text_of(msg, "last_agent_message") {
+                    final_message = Some(text);
+                }
+            }
+            Some("agent_message") => {
+                if let Some(text) = text_of(msg, "message") {
+                    last_message = Some(text);
                 }
             }
+            _ => {}
         }
+        if item.and_then(|item| item.get("type")).and_then(|t| t.as_str()) == Some("agent_message") {
+            if let Some(text) = text_of(item, "text") {
+                last_message = Some(text);
+            }
+        }
+    }
+    final_message.or(last_message).map(|text| strip_ansi(&text))
+}
 
-        let output = command
-            .output()
-            .map_err(|e| {
-                format!(
-                    "Failed to launch codex CLI wakeup (binary={}): {}",
-                    codex_cli.display(),
-                    e
-                )
-            })?;
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+/// Token counts reported by the CLI; any field the output did not mention stays `None`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct CliTokenUsage {
+    prompt_tokens: Option<u32>,
+    completion_tokens: Option<u32>,
+    total_tokens: Option<u32>,
+}
 
-        if !output.status.success() {
-            let code = output
-                .status
-                .code()
-                .map(|value| value.to_string())
-                .unwrap_or_else(|| "unknown".to_string());
-            let details = if stderr.trim().is_empty() {
-                stdout.trim()
-            } else {
-                stderr.trim()
-            };
-            return Err(format!(
-                "Codex CLI wakeup failed (exit={}): {}",
-                code,
-                trim_for_log(details, 500)
-            ));
-        }
+/// Result of a successful CLI run.
+#[derive(Debug, Clone, Default)]
+struct CliRunOutput {
+    reply: String,
+    usage: CliTokenUsage,
+    ids: CliResponseIds,
+    debug: Option<WakeupDebug>,
+    /// `None` when the CLI did not run.
+    exit_code: Option<i32>,
+    /// Set when a non-zero exit was mapped to `warning` severity.
+    exit_warning: Option<String>,
+}
 
-        Ok(read_last_message(&output_file, &stdout))
-    })();
+/// Server-side identifiers found in the `--json` event stream, for correlating with backend logs.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct CliResponseIds {
+    trace_id: Option<String>,
+    response_id: Option<String>,
+}
 
-    if let Err(e) = fs::remove_dir_all(&temp_home) {
-        logger::log_warn(&format!(
-            "[CodexWakeup] Failed to cleanup temp CODEX_HOME {}: {}",
-            temp_home.display(),
-            e
-        ));
+fn find_json_string(value: &serde_json::Value, keys: &[&str]) -> Option<String> {
+    match value {
+        serde_json::Value::Object(object) => keys
+            .iter()
+            .find_map(|key| {
+                object
+                    .get(*key)
+                    .and_then(|v| v.as_str())
+                    .map(str::trim)
+                    .filter(|v| !v.is_empty())
+                    .map(str::to_string)
+            })
+            .or_else(|| object.values().find_map(|child| find_json_string(child, keys))),
+        serde_json::Value::Array(items) => items.iter().find_map(|item| find_json_string(item, keys)),
+        _ => None,
     }
-
-    run_result
 }
 
-fn try_reserve_wakeup(account_id: &str) -> bool {
-    let now = chrono::Utc::now().timestamp_millis();
-    let mut guard = wakeup_state().lock().expect("codex wakeup state lock");
-    if let Some(last) = guard.get(account_id) {
-        if now - *last < DUPLICATE_WAKEUP_WINDOW_MS {
+/// Extracts the last trace/response ids from the CLI's JSON event lines.
+fn parse_response_ids(stdout: &str) -> CliResponseIds {
+    let mut ids = CliResponseIds::default();
+    for line in stdout.lines().map(|line| line.trim()) {
+        if !line.starts_with('{') {
+            continue;
+        }
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        if let Some(trace_id) = find_json_string(&value, &["trace_id", "traceId", "request_id"]) {
+            ids.trace_id = Some(trace_id);
+        }
+        if let Some(response_id) = find_json_string(&value, &["response_id", "responseId"]) {
+            ids.response_id = Some(response_id);
+        }
+    }
+    ids
+}
+
+fn parse_count(text: &str) -> Option<u32> {
+    let digits: String = text
+        .chars()
+        .filter(|c| !matches!(c, ',' | '_') && !c.is_whitespace())
+        .collect();
+    digits.parse().ok()
+}
+
+fn json_count(value: &serde_json::Value, key: &str) -> Option<u32> {
+    value
+        .get(key)
+        .and_then(|v| v.as_u64())
+        .and_then(|v| u32::try_from(v).ok())
+}
+
+/// Finds the innermost object carrying `input_tokens`/`output_tokens` (the `--json`
+/// `usage` / `total_token_usage` payloads).
+fn find_json_usage(value: &serde_json::Value) -> Option<CliTokenUsage> {
+    let object = value.as_object()?;
+    for child in object.values() {
+        if let Some(usage) = find_json_usage(child) {
+            return Some(usage);
+        }
+    }
+    if !object.contains_key("input_tokens") && !object.contains_key("output_tokens") {
+        return None;
+    }
+    let prompt_tokens = json_count(value, "input_tokens");
+    let completion_tokens = json_count(value, "output_tokens");
+    let total_tokens = json_count(value, "total_tokens").or_else(|| match (prompt_tokens, completion_tokens) {
+        (Some(input), Some(output)) => input.checked_add(output),
+        _ => None,
+    });
+    Some(CliTokenUsage {
+        prompt_tokens,
+        completion_tokens,
+        total_tokens,
+    })
+}
+
+/// Extracts token usage from CLI stdout: the last `--json` usage event wins, otherwise the
+/// plain `tokens used` summary (same line after `:` or on the following line) gives the total.
+fn parse_token_usage(stdout: &str) -> CliTokenUsage {
+    let stdout = strip_ansi(stdout);
+    let mut usage = CliTokenUsage::default();
+    let mut after_summary = false;
+    for line in stdout.lines().map(|line| line.trim()) {
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('{') {
+            if let Some(found) = serde_json::from_str::<serde_json::Value>(line)
+                .ok()
+                .as_ref()
+                .and_then(find_json_usage)
+            {
+                usage = found;
+            }
+            after_summary = false;
+            continue;
+        }
+        if after_summary && is_count_line(line) {
+            if let Some(total) = parse_count(line) {
+                usage.total_tokens = Some(total);
+            }
+            after_summary = false;
+            continue;
+        }
+        let lower = line.to_ascii_lowercase();
+        after_summary = false;
+        if let Some(index) = lower.find("tokens used") {
+            let rest = line[index + "tokens used".len()..].trim_start_matches([':', ' ']);
+            match parse_count(rest) {
+                Some(total) => usage.total_tokens = Some(total),
+                None => after_summary = true,
+            }
+        }
+    }
+    usage
+}
+
+/// Per-invocation CLI tuning.
+#[derive(Debug, Clone)]
+struct CliRunParams {
+    cli_model: String,
+    reasoning_effort: String,
+    /// Output token cap passed to the CLI; 0 keeps the CLI default.
+    max_output_tokens: u32,
+    /// Kill the CLI if it has not exited within this duration.
+    timeout: Option<std::time::Duration>,
+    /// Keep a `WakeupDebug` capture of the run.
+    capture_debug: bool,
+}
+
+impl Default for CliRunParams {
+    fn default() -> Self {
+        Self {
+            cli_model: CLI_MODEL.to_string(),
+            reasoning_effort: CLI_REASONING_LEVEL.to_string(),
+            max_output_tokens: 0,
+            timeout: None,
+            capture_debug: false,
+        }
+    }
+}
+
+/// Output token cap for a wakeup on `window`: an explicit value wins, then the per-window
+/// setting, then the global default; 0 leaves the CLI default in place.
+fn effective_max_output_tokens(
+    requested: u32,
+    window: &str,
+    settings: &codex_wakeup_settings::CodexWakeupSettings,
+) -> u32 {
+    let per_window = match window {
+        MODEL_HOURLY => settings.hourly_max_output_tokens,
+        MODEL_WEEKLY => settings.weekly_max_output_tokens,
+        _ => 0,
+    };
+    [requested, per_window, settings.default_max_output_tokens]
+        .into_iter()
+        .find(|&cap| cap > 0)
+        .unwrap_or(0)
+}
+
+/// Converts the `cli_timeout_ms` setting into a CLI deadline; 0 disables it.
+fn cli_timeout(timeout_ms: u64) -> Option<std::time::Duration> {
+    (timeout_ms > 0).then(|| std::time::Duration::from_millis(timeout_ms))
+}
+
+/// A whole CLI line that starts with a login marker (optionally after `error:`), followed by
+/// nothing or punctuation. Markers in the middle of a line never match.
+fn is_login_prompt_line(line: &str) -> bool {
+    let lower = strip_ansi(line).trim().to_ascii_lowercase();
+    let lower = lower
+        .strip_prefix("error:")
+        .map(str::trim_start)
+        .unwrap_or(&lower);
+    CLI_LOGIN_PROMPT_MARKERS.iter().any(|marker| {
+        lower
+            .strip_prefix(marker)
+            .is_some_and(|rest| rest.chars().next().is_none_or(|c| !c.is_ascii_alphanumeric()))
+    })
+}
+
+/// Watches stderr for login prompts until the session transcript starts, so an echoed
+/// prompt or a reply that mentions logging in cannot abort a healthy run.
+#[derive(Debug, Default)]
+struct LoginPromptScanner {
+    transcript_started: bool,
+}
+
+impl LoginPromptScanner {
+    /// True when `line` is a login prompt seen before the transcript.
+    fn feed(&mut self, line: &str) -> bool {
+        if self.transcript_started {
             return false;
         }
+        let lower = strip_ansi(line).trim().to_ascii_lowercase();
+        // Lines may carry a `[timestamp]` prefix; `--json` events start with `{`.
+        let content = match lower.strip_prefix('[').and_then(|rest| rest.split_once(']')) {
+            Some((_, rest)) => rest.trim_start(),
+            None => lower.as_str(),
+        };
+        if content.starts_with('{') || CLI_TRANSCRIPT_START_LINES.contains(&content) {
+            self.transcript_started = true;
+            return false;
+        }
+        is_login_prompt_line(line)
+    }
+}
+
+/// True when the CLI process could not be started at all (ETXTBSY, transient EACCES, ...).
+/// A CLI that ran and exited non-zero is never a launch failure.
+fn is_launch_failure(error: &str) -> bool {
+    error.starts_with(CLI_LAUNCH_ERROR_PREFIX) && error.contains(CLI_SPAWN_ERROR_PREFIX)
+}
+
+/// Backoff before launch retry `attempt` (1-based): `base_ms * 2^(attempt - 1)`.
+fn launch_retry_delay(base_ms: u64, attempt: u32) -> std::time::Duration {
+    let factor = 1u64 << (attempt.saturating_sub(1)).min(16);
+    std::time::Duration::from_millis(base_ms.saturating_mul(factor))
+}
+
+/// True when a CLI run took longer than `slo_ms`; an SLO of 0 is disabled.
+fn latency_slo_violated(slo_ms: u64, cli_duration_ms: Option<u64>) -> bool {
+    slo_ms > 0 && cli_duration_ms.is_some_and(|ms| ms > slo_ms)
+}
+
+/// Exit code carried by a "Codex CLI wakeup failed (exit=N)" error.
+pub fn cli_exit_code(error: &str) -> Option<i32> {
+    let rest = error.strip_prefix(CLI_EXIT_ERROR_PREFIX)?;
+    rest.split(')').next()?.parse().ok()
+}
+
+/// Classifies a wakeup error so the UI can react to it (e.g. prompt re-auth).
+pub fn failure_kind(error: &str) -> Option<&'static str> {
+    if error.starts_with(CLI_AUTH_REQUIRED_ERROR) {
+        Some(FAILURE_KIND_AUTH_REQUIRED)
+    } else if error.starts_with(CLI_TIMEOUT_ERROR_PREFIX) {
+        Some(FAILURE_KIND_TIMEOUT)
+    } else if error.starts_with(CLI_EMPTY_REPLY_ERROR) {
+        Some(FAILURE_KIND_EMPTY_REPLY)
+    } else {
+        None
+    }
+}
+
+/// Called with each stdout line (trailing newline stripped) as the CLI prints it.
+type StdoutLineCallback = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Runs `command` to completion, killing it once `timeout` elapses or as soon as
+/// it prints a login prompt (it has no TTY, so it would otherwise hang).
+fn output_with_timeout(
+    command: &mut Command,
+    timeout: Option<std::time::Duration>,
+    stdin_data: Option<&str>,
+    on_stdout_line: Option<StdoutLineCallback>,
+) -> Result<std::process::Output, String> {
+    command
+        .stdin(if stdin_data.is_some() {
+            std::process::Stdio::piped()
+        } else {
+            std::process::Stdio::null()
+        })
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped());
+    let mut child = command.spawn().map_err(|e| {
+        // A missing binary will not appear on retry; keep it out of the retryable class.
+        if e.kind() == std::io::ErrorKind::NotFound {
+            e.to_string()
+        } else {
+            format!("{}{}", CLI_SPAWN_ERROR_PREFIX, e)
+        }
+    })?;
+    if let (Some(data), Some(mut stdin)) = (stdin_data, child.stdin.take()) {
+        let data = data.as_bytes().to_vec();
+        // Write on a thread so a child that does not read stdin cannot block us; dropping
+        // the handle closes the pipe so the CLI sees EOF.
+        std::thread::spawn(move || {
+            let _ = std::io::Write::write_all(&mut stdin, &data);
+        });
+    }
+    let login_prompted = Arc::new(AtomicBool::new(false));
+
+    // Drain pipes on helper threads so a chatty child cannot block on a full pipe.
+    // Only stderr is scanned for login prompts (stdout carries the reply / `--json` events),
+    // and only up to the start of the transcript.
+    fn spawn_reader<R: std::io::Read + Send + 'static>(
+        pipe: R,
+        login_prompted: Option<Arc<AtomicBool>>,
+        on_line: Option<StdoutLineCallback>,
+    ) -> std::thread::JoinHandle<Vec<u8>> {
+        std::thread::spawn(move || {
+            let mut reader = std::io::BufReader::new(pipe);
+            let mut buf = Vec::new();
+            let mut line = Vec::new();
+            let mut scanner = LoginPromptScanner::default();
+            loop {
+                line.clear();
+                match std::io::BufRead::read_until(&mut reader, b'\n', &mut line) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {
+                        let text = String::from_utf8_lossy(&line);
+                        if let Some(login_prompted) = &login_prompted {
+                            if scanner.feed(&text) {
+                                login_prompted.store(true, Ordering::SeqCst);
+                            }
+                        }
+                        if let Some(on_line) = &on_line {
+                            on_line(text.trim_end_matches(['\r', '\n']));
+                        }
+                        buf.extend_from_slice(&line);
+                    }
+                }
+            }
+            buf
+        })
+    }
+    let stdout_reader = child
+        .stdout
+        .take()
+        .map(|out| spawn_reader(out, None, on_stdout_line));
+    let stderr_reader = child
+        .stderr
+        .take()
+        .map(|err| spawn_reader(err, Some(login_prompted.clone()), None));
+
+    let started = std::time::Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|e| e.to_string())? {
+            break status;
+        }
+        if login_prompted.load(Ordering::SeqCst) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(CLI_AUTH_REQUIRED_ERROR.to_string());
+        }
+        if let Some(timeout) = timeout {
+            if started.elapsed() >= timeout {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("{}{}ms", CLI_TIMEOUT_ERROR_PREFIX, timeout.as_millis()));
+            }
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    };
+
+    let stdout = stdout_reader
+        .and_then(|handle| handle.join().ok())
+        .unwrap_or_default();
+    let stderr = stderr_reader
+        .and_then(|handle| handle.join().ok())
+        .unwrap_or_default();
+    // The CLI may also print the prompt and exit on its own before we poll.
+    if !status.success() && login_prompted.load(Ordering::SeqCst) {
+        return Err(CLI_AUTH_REQUIRED_ERROR.to_string());
+    }
+
+    Ok(std::process::Output {
+        status,
+        stdout,
+        stderr,
+    })
+}
+
+/// Adds `-c model_max_output_tokens=<n>`; 0 means "CLI default" and adds nothing.
+fn push_max_output_tokens_arg(command: &mut Command, max_output_tokens: u32) {
+    if max_output_tokens > 0 {
+        command
+            .arg("-c")
+            .arg(format!("model_max_output_tokens={}", max_output_tokens));
+    }
+}
+
+/// Appends the prompt as the final positional argument, after a `--` separator so a
+/// prompt starting with `-` is never parsed as an option. Long prompts are replaced by
+/// `-` (read from stdin); returns true in that case.
+fn push_prompt_arg(command: &mut Command, prompt: &str) -> bool {
+    command.arg("--");
+    if prompt.len() > PROMPT_STDIN_THRESHOLD_BYTES {
+        command.arg("-");
+        true
+    } else {
+        command.arg(prompt);
+        false
+    }
+}
+
+/// One line of live CLI output, emitted as `codex-wakeup://progress` while a wakeup runs.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WakeupProgressPayload {
+    pub account_id: String,
+    pub line: String,
+    /// Unix timestamp in milliseconds.
+    pub timestamp: i64,
+}
+
+/// Forwards CLI stdout lines to the frontend; a no-op before the app handle exists.
+fn progress_emitter(account_id: &str) -> StdoutLineCallback {
+    let account_id = account_id.to_string();
+    Arc::new(move |line: &str| {
+        use tauri::Emitter;
+
+        let Some(app) = crate::get_app_handle() else {
+            return;
+        };
+        let _ = app.emit(
+            "codex-wakeup://progress",
+            WakeupProgressPayload {
+                account_id: account_id.clone(),
+                line: strip_ansi(line),
+                timestamp: chrono::Utc::now().timestamp_millis(),
+            },
+        );
+    })
+}
+
+/// Builds the `codex exec` invocation for a wakeup run in `temp_home`. Returns the
+/// command and whether the prompt must be written to stdin.
+fn build_wakeup_command(
+    codex_cli: &Path,
+    temp_home: &Path,
+    output_file: &Path,
+    prompt: &str,
+    params: &CliRunParams,
+    json_events: bool,
+) -> (Command, bool) {
+    let mut command = command_for_executable(codex_cli);
+    command
+        .arg("exec")
+        .arg("-m")
+        .arg(&params.cli_model)
+        .arg("-c")
+        .arg(format!("model_reasoning_effort=\"{}\"", params.reasoning_effort));
+    push_max_output_tokens_arg(&mut command, params.max_output_tokens);
+    command
+        .arg("--skip-git-repo-check")
+        .arg("--color")
+        .arg(codex_wakeup_settings::get_settings().cli_color_mode.as_arg())
+        .arg("--output-last-message")
+        .arg(output_file);
+    if json_events {
+        command.arg("--json");
+    }
+    if let Ok(cwd) = std::env::current_dir() {
+        command.arg("-C").arg(cwd);
+    }
+    let prompt_via_stdin = push_prompt_arg(&mut command, prompt);
+    if codex_wakeup_settings::get_settings().cli_env_isolation
+        == codex_wakeup_settings::CliEnvIsolation::Isolated
+    {
+        command.env_clear();
+        for key in ISOLATED_ENV_PASSTHROUGH {
+            if let Some(value) = std::env::var_os(key) {
+                command.env(key, value);
+            }
+        }
+    }
+    command.env("CODEX_HOME", temp_home);
+    #[cfg(target_os = "windows")]
+    {
+        if let Ok(appdata) = std::env::var("APPDATA") {
+            let npm_dir = PathBuf::from(appdata).join("npm");
+            if npm_dir.exists() {
+                let mut path_entries = vec![npm_dir];
+                if let Some(current_path) = std::env::var_os("PATH") {
+                    path_entries.extend(std::env::split_paths(&current_path));
+                }
+                if let Ok(joined) = std::env::join_paths(path_entries) {
+                    command.env("PATH", joined);
+                }
+            }
+        }
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        // npm-installed codex is a `#!/usr/bin/env node` script, so the node that
+        // ships next to it must be reachable from PATH.
+        if let Some(bin_dir) = codex_cli.parent() {
+            let mut path_entries = vec![bin_dir.to_path_buf()];
+            if let Some(current_path) = std::env::var_os("PATH") {
+                path_entries.extend(std::env::split_paths(&current_path));
+            }
+            if let Ok(joined) = std::env::join_paths(path_entries) {
+                command.env("PATH", joined);
+            }
+        }
+    }
+
+    (command, prompt_via_stdin)
+}
+
+/// Runs the CLI, retrying launch failures per the `cli_launch_retries` settings. Each
+/// attempt gets its own temp CODEX_HOME, removed before the next one starts.
+fn run_codex_wakeup_cli(
+    account: &CodexAccount,
+    prompt: &str,
+    params: &CliRunParams,
+) -> Result<CliRunOutput, String> {
+    let settings = codex_wakeup_settings::get_settings();
+    let mut attempt = 0u32;
+    loop {
+        match run_codex_wakeup_cli_once(account, prompt, params) {
+            Err(e) if is_launch_failure(&e) && attempt < settings.cli_launch_retries => {
+                attempt += 1;
+                let delay = launch_retry_delay(settings.cli_launch_retry_base_delay_ms, attempt);
+                logger::log_warn(&format!(
+                    "[CodexWakeup] CLI launch failed, retrying in {}ms ({}/{}): email={}, error={}",
+                    delay.as_millis(),
+                    attempt,
+                    settings.cli_launch_retries,
+                    account.display_label(),
+                    e
+                ));
+                std::thread::sleep(delay);
+            }
+            result => return result,
+        }
+    }
+}
+
+fn run_codex_wakeup_cli_once(
+    account: &CodexAccount,
+    prompt: &str,
+    params: &CliRunParams,
+) -> Result<CliRunOutput, String> {
+    let temp_home = next_temp_home_dir()?;
+    let output_file = temp_home.join("last_message.txt");
+    let codex_cli = resolve_codex_cli_path(Some(account))?;
+    let json_events = codex_wakeup_settings::get_settings().cli_json_events;
+    let mut debug: Option<WakeupDebug> = None;
+
+    let mut run_result = (|| -> Result<CliRunOutput, String> {
+        codex_account::write_auth_file_to_dir(&temp_home, account)?;
+
+        logger::log_info(&format!(
+            "[CodexWakeup] Using Codex CLI binary: {}",
+            codex_cli.display()
+        ));
+
+        let (mut command, prompt_via_stdin) = build_wakeup_command(
+            &codex_cli,
+            &temp_home,
+            &output_file,
+            prompt,
+            params,
+            json_events,
+        );
+        if prompt_via_stdin {
+            logger::log_info(&format!(
+                "[CodexWakeup] Prompt is {} bytes; passing it via stdin",
+                prompt.len()
+            ));
+        }
+
+        let stdin_data = if prompt_via_stdin { Some(prompt) } else { None };
+        let args: Vec<String> = std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect();
+        let output = match output_with_timeout(
+            &mut command,
+            params.timeout,
+            stdin_data,
+            Some(progress_emitter(&account.id)),
+        ) {
+            Ok(output) => output,
+            Err(e) => {
+                let e = if failure_kind(&e).is_some() {
+                    e
+                } else {
+                    format!(
+                        "{} (binary={}): {}",
+                        CLI_LAUNCH_ERROR_PREFIX,
+                        codex_cli.display(),
+                        e
+                    )
+                };
+                if params.capture_debug {
+                    debug = Some(WakeupDebug {
+                        args,
+                        exit_code: None,
+                        stdout: String::new(),
+                        stderr: String::new(),
+                        error: Some(e.clone()),
+                    });
+                }
+                return Err(e);
+            }
+        };
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        if params.capture_debug {
+            debug = Some(WakeupDebug {
+                args,
+                exit_code: output.status.code(),
+                stdout: stdout.clone(),
+                stderr: stderr.clone(),
+                error: None,
+            });
+        }
+
+        let exit_code = output.status.code();
+        let details = if stderr.trim().is_empty() {
+            stdout.trim()
+        } else {
+            stderr.trim()
+        };
+        let severity = codex_wakeup_settings::CliExitSeverity::for_exit_code(
+            exit_code,
+            &codex_wakeup_settings::get_settings().cli_exit_code_severities,
+        );
+        let exit_warning = match severity {
+            codex_wakeup_settings::CliExitSeverity::Failure => {
+                let code = exit_code
+                    .map(|value| value.to_string())
+                    .unwrap_or_else(|| "unknown".to_string());
+                return Err(format!(
+                    "{}{}): {}",
+                    CLI_EXIT_ERROR_PREFIX,
+                    code,
+                    trim_for_log(details, 500)
+                ));
+            }
+            codex_wakeup_settings::CliExitSeverity::Warning => {
+                let code = exit_code.unwrap_or_default();
+                logger::log_warn(&format!(
+                    "[CodexWakeup] CLI exited with code {} (mapped to warning): {}",
+                    code,
+                    trim_for_log(details, 200)
+                ));
+                Some(format!("Codex CLI exited with code {}", code))
+            }
+            codex_wakeup_settings::CliExitSeverity::Success => None,
+        };
+
+        let ignored_prefixes = codex_wakeup_settings::get_settings().ignored_stdout_prefixes;
+        let ids = parse_response_ids(&stdout);
+        if ids.trace_id.is_none() || ids.response_id.is_none() {
+            logger::log_debug(&format!(
+                "[CodexWakeup] CLI output missing ids: trace_id={:?}, response_id={:?}",
+                ids.trace_id, ids.response_id
+            ));
+        }
+        let json_reply = if json_events {
+            let parsed = parse_json_final_message(&stdout);
+            if parsed.is_none() {
+                logger::log_warn(
+                    "[CodexWakeup] No assistant message in CLI --json output; falling back to last message",
+                );
+            }
+            parsed
+        } else {
+            None
+        };
+        Ok(CliRunOutput {
+            reply: json_reply
+                .unwrap_or_else(|| read_last_message(&output_file, &stdout, &ignored_prefixes)),
+            usage: parse_token_usage(&stdout),
+            ids,
+            debug: None,
+            exit_code,
+            exit_warning,
+        })
+    })();
+
+    if let Some(debug) = debug.map(|debug| debug.redacted(account)) {
+        LAST_CLI_DEBUG
+            .get_or_init(|| Mutex::new(HashMap::new()))
+            .lock()
+            .expect("codex cli debug lock")
+            .insert(account.id.clone(), debug.clone());
+        if let Ok(output) = run_result.as_mut() {
+            output.debug = Some(debug);
+        }
+    }
+
+    if let Err(e) = fs::remove_dir_all(&temp_home) {
+        logger::log_warn(&format!(
+            "[CodexWakeup] Failed to cleanup temp CODEX_HOME {}: {}",
+            temp_home.display(),
+            e
+        ));
+    }
+
+    run_result
+}
+
+/// Guard thresholds for one account after applying its overrides over the defaults.
+#[derive(Debug, Clone)]
+struct EffectiveGuards {
+    duplicate_window_ms: i64,
+    daily_cap: Option<u32>,
+    cooldown_ms: Option<i64>,
+    budget_floor_percent: Option<i32>,
+}
+
+fn effective_guards(account: &CodexAccount) -> EffectiveGuards {
+    let overrides = account.guards.clone().unwrap_or_default();
+    EffectiveGuards {
+        duplicate_window_ms: overrides
+            .duplicate_window_ms
+            .unwrap_or(DUPLICATE_WAKEUP_WINDOW_MS)
+            .max(0),
+        daily_cap: overrides.daily_cap,
+        cooldown_ms: overrides.cooldown_ms.filter(|ms| *ms > 0),
+        budget_floor_percent: overrides.budget_floor_percent,
+    }
+}
+
+/// Checks the stored tokens are usable before any temp dir or CLI work.
+fn validate_account_tokens(account: &CodexAccount) -> Result<(), String> {
+    if account.tokens.access_token.trim().is_empty() {
+        return Err(format!(
+            "Account {} has no access token; re-import or re-login this account",
+            account.display_label()
+        ));
+    }
+    if account
+        .tokens
+        .refresh_token
+        .as_deref()
+        .map(|token| token.trim().is_empty())
+        .unwrap_or(true)
+    {
+        return Err(format!(
+            "Account {} has no refresh token; re-login this account so the CLI can renew its session",
+            account.display_label()
+        ));
+    }
+    if codex_account::decode_jwt_payload_value(&account.tokens.access_token).is_none() {
+        return Err(format!(
+            "Account {} has a malformed access token; re-import or re-login this account",
+            account.display_label()
+        ));
+    }
+    Ok(())
+}
+
+fn check_daily_cap(account: &CodexAccount, guards: &EffectiveGuards) -> Result<(), String> {
+    if let Some(cap) = guards.daily_cap {
+        let today = Local::now().date_naive();
+        let counts = DAILY_WAKEUP_COUNTS.get_or_init(|| Mutex::new(HashMap::new()));
+        let used = counts
+            .lock()
+            .expect("codex daily wakeup count lock")
+            .get(&account.id)
+            .filter(|(day, _)| *day == today)
+            .map(|(_, count)| *count)
+            .unwrap_or(0);
+        if used >= cap {
+            return Err(format!(
+                "Daily wakeup cap reached for {} ({}/{})",
+                account.display_label(), used, cap
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Rejects wakeups while the account's `paused_until` (Unix seconds) is in the future.
+fn check_paused(account: &CodexAccount, now_secs: i64) -> Result<(), String> {
+    match account.paused_until {
+        Some(until) if until > now_secs => Err(format!(
+            "Codex account {} is paused until {}",
+            account.display_label(),
+            Local
+                .timestamp_opt(until, 0)
+                .single()
+                .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_else(|| until.to_string())
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Fails fast with actionable errors for accounts that cannot possibly wake up:
+/// paused accounts, missing or malformed tokens, or the daily cap already reached.
+pub fn validate_account_for_wakeup(account: &CodexAccount) -> Result<(), String> {
+    check_paused(account, chrono::Utc::now().timestamp())?;
+    validate_account_tokens(account)?;
+    check_daily_cap(account, &effective_guards(account))
+}
+
+/// Hard guards (cooldown, budget floor) that reject a wakeup outright. Checked per call
+/// since they depend on the target window; the daily cap is enforced up front by
+/// [`validate_account_for_wakeup`].
+fn check_account_guards(
+    account: &CodexAccount,
+    model: &str,
+    guards: &EffectiveGuards,
+) -> Result<(), String> {
+    if let Some(cooldown_ms) = guards.cooldown_ms {
+        let now = chrono::Utc::now().timestamp_millis();
+        let last_success = LAST_WAKEUP_SUCCESS_AT
+            .get_or_init(|| Mutex::new(HashMap::new()))
+            .lock()
+            .expect("codex wakeup success lock")
+            .get(&account.id)
+            .copied();
+        if let Some(last) = last_success {
+            if now - last < cooldown_ms {
+                return Err(format!(
+                    "Wakeup cooldown active for {} ({}ms remaining)",
+                    account.display_label(),
+                    cooldown_ms - (now - last)
+                ));
+            }
+        }
+    }
+
+    if let (Some(floor), Some(quota)) = (guards.budget_floor_percent, account.quota.as_ref()) {
+        let remaining = match model {
+            MODEL_HOURLY => quota.hourly_percentage,
+            MODEL_WEEKLY => quota.weekly_percentage,
+            _ => quota.hourly_percentage.min(quota.weekly_percentage),
+        };
+        if remaining < floor {
+            return Err(format!(
+                "Remaining quota {}% is below the budget floor {}% for {}",
+                remaining, floor, account.display_label()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn record_wakeup_launch(account_id: &str) {
+    let today = Local::now().date_naive();
+    let counts = DAILY_WAKEUP_COUNTS.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut guard = counts.lock().expect("codex daily wakeup count lock");
+    let entry = guard.entry(account_id.to_string()).or_insert((today, 0));
+    if entry.0 != today {
+        *entry = (today, 0);
+    }
+    entry.1 += 1;
+}
+
+fn record_wakeup_success(account_id: &str) {
+    LAST_WAKEUP_SUCCESS_AT
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .expect("codex wakeup success lock")
+        .insert(account_id.to_string(), chrono::Utc::now().timestamp_millis());
+}
+
+/// Key for duplicate-wakeup suppression: the account alone, or the account plus window
+/// when `dedup_per_window` is enabled.
+fn wakeup_dedup_key(account_id: &str, model: &str, per_window: bool) -> String {
+    if per_window {
+        format!("{}|{}", account_id, model)
+    } else {
+        account_id.to_string()
+    }
+}
+
+/// Reservations older than this many duplicate windows are dropped on each insert.
+const RESERVATION_PRUNE_FACTOR: i64 = 10;
+
+/// Drops reservations that can no longer suppress anything: older than
+/// `RESERVATION_PRUNE_FACTOR` times the larger of their own and the current window.
+fn prune_reservations(reservations: &mut HashMap<String, (i64, i64)>, now: i64, window_ms: i64) {
+    reservations.retain(|_, (launched_at, entry_window_ms)| {
+        let horizon = (*entry_window_ms)
+            .max(window_ms)
+            .max(DUPLICATE_WAKEUP_WINDOW_MS)
+            .saturating_mul(RESERVATION_PRUNE_FACTOR);
+        now - *launched_at < horizon
+    });
+}
+
+fn try_reserve_wakeup(key: &str, window_ms: i64) -> bool {
+    let now = chrono::Utc::now().timestamp_millis();
+    let mut guard = wakeup_state().lock().expect("codex wakeup state lock");
+    if let Some((last, _)) = guard.get(key) {
+        if now - *last < window_ms {
+            return false;
+        }
+    }
+    prune_reservations(&mut guard, now, window_ms);
+    guard.insert(key.to_string(), (now, window_ms));
+    true
+}
+
+fn release_wakeup_reservation(key: &str) {
+    let mut guard = wakeup_state().lock().expect("codex wakeup state lock");
+    guard.remove(key);
+}
+
+/// Drops every duplicate-wakeup reservation; returns how many were removed.
+pub fn clear_wakeup_reservations() -> usize {
+    let mut guard = wakeup_state().lock().expect("codex wakeup state lock");
+    let removed = guard.len();
+    guard.clear();
+    logger::log_info(&format!("[CodexWakeup] Cleared {} wakeup reservations", removed));
+    removed
+}
+
+/// Drops the reservations of one account (account-wide and per-window keys); returns
+/// how many were removed.
+pub fn clear_wakeup_reservation(account_id: &str) -> usize {
+    let per_window_prefix = format!("{}|", account_id);
+    let mut guard = wakeup_state().lock().expect("codex wakeup state lock");
+    let before = guard.len();
+    guard.retain(|key, _| key != account_id && !key.starts_with(&per_window_prefix));
+    let removed = before - guard.len();
+    logger::log_info(&format!(
+        "[CodexWakeup] Cleared {} wakeup reservations for account {}",
+        removed, account_id
+    ));
+    removed
+}
+
+/// Process-wide gate: waits (up to the configured cap) until `min_interval_ms`
+/// has passed since the previous CLI launch of any account.
+async fn acquire_global_launch_slot() -> Result<(), String> {
+    let settings = codex_wakeup_settings::get_settings();
+    let min_interval_ms = settings.global_min_interval_ms as i64;
+    if min_interval_ms <= 0 {
+        return Ok(());
+    }
+
+    let deadline = chrono::Utc::now().timestamp_millis() + settings.global_rate_max_wait_ms as i64;
+    loop {
+        let now = chrono::Utc::now().timestamp_millis();
+        let wait_ms = {
+            let mut last = global_launch_state().lock().expect("codex global launch lock");
+            let wait_ms = *last + min_interval_ms - now;
+            if wait_ms <= 0 {
+                *last = now;
+                return Ok(());
+            }
+            wait_ms
+        };
+        if now + wait_ms > deadline {
+            return Err(format!(
+                "Codex wakeup global rate limit active (next slot in {}ms)",
+                wait_ms
+            ));
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(wait_ms as u64)).await;
+    }
+}
+
+/// Serializes wakeups of the same account while bounding how many accounts run at once.
+struct WakeupLimiter {
+    account_locks: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+    /// (limit, semaphore); replaced when the configured limit changes.
+    semaphore: Mutex<(usize, Arc<tokio::sync::Semaphore>)>,
+}
+
+/// Held for the duration of one wakeup; dropping it frees both the account and a global slot.
+struct WakeupSlot {
+    _account: tokio::sync::OwnedMutexGuard<()>,
+    _permit: tokio::sync::OwnedSemaphorePermit,
+}
+
+impl WakeupLimiter {
+    fn new(limit: usize) -> Self {
+        let limit = limit.max(1);
+        Self {
+            account_locks: Mutex::new(HashMap::new()),
+            semaphore: Mutex::new((limit, Arc::new(tokio::sync::Semaphore::new(limit)))),
+        }
+    }
+
+    fn semaphore_for(&self, limit: usize) -> Arc<tokio::sync::Semaphore> {
+        let limit = limit.max(1);
+        let mut guard = self.semaphore.lock().expect("codex wakeup semaphore lock");
+        if guard.0 != limit {
+            // In-flight permits drain from the old semaphore; new wakeups use the new limit.
+            *guard = (limit, Arc::new(tokio::sync::Semaphore::new(limit)));
+        }
+        guard.1.clone()
+    }
+
+    async fn acquire(&self, account_id: &str, limit: usize) -> Result<WakeupSlot, String> {
+        let account_lock = self
+            .account_locks
+            .lock()
+            .expect("codex wakeup account locks")
+            .entry(account_id.to_string())
+            .or_default()
+            .clone();
+        // Take the account lock first so queued same-account calls do not hold global slots.
+        let account_guard = account_lock.lock_owned().await;
+        let permit = self
+            .semaphore_for(limit)
+            .acquire_owned()
+            .await
+            .map_err(|_| "Codex wakeup concurrency limiter closed".to_string())?;
+        Ok(WakeupSlot {
+            _account: account_guard,
+            _permit: permit,
+        })
+    }
+}
+
+async fn acquire_wakeup_slot(account_id: &str) -> Result<WakeupSlot, String> {
+    let limit = codex_wakeup_settings::get_settings().max_concurrent_wakeups;
+    WAKEUP_LIMITER
+        .get_or_init(|| WakeupLimiter::new(limit))
+        .acquire(account_id, limit)
+        .await
+}
+
+/// Drops NUL and other control characters (keeping newlines and tabs); they cannot be
+/// passed on a command line and only confuse the model.
+fn sanitize_prompt(prompt: &str) -> String {
+    prompt
+        .chars()
+        .filter(|c| !c.is_control() || matches!(c, '\n' | '\r' | '\t'))
+        .collect()
+}
+
+/// Resolves `@file` prompts, sanitizes the text, falls back to the default prompt when
+/// nothing is left, and enforces `max_prompt_chars`. Runs before any CLI work.
+fn prepare_prompt(prompt: &str) -> Result<String, String> {
+    let sanitized = sanitize_prompt(&resolve_prompt(prompt)?);
+    let final_prompt = match sanitized.trim() {
+        "" => DEFAULT_WAKEUP_PROMPT.to_string(),
+        trimmed => trimmed.to_string(),
+    };
+    check_prompt_length(&final_prompt)?;
+    Ok(final_prompt)
+}
+
+/// Rejects prompts over `max_prompt_chars` (0 disables the limit).
+fn check_prompt_length(prompt: &str) -> Result<(), String> {
+    let max_chars = codex_wakeup_settings::get_settings().max_prompt_chars;
+    let chars = prompt.chars().count();
+    if max_chars > 0 && chars > max_chars {
+        return Err(format!(
+            "Prompt is {} characters, over the configured limit of {} (max_prompt_chars)",
+            chars, max_chars
+        ));
+    }
+    Ok(())
+}
+
+/// Resolves `@/path/to/prompt.txt` to the file contents; other prompts are returned as-is.
+fn resolve_prompt(prompt: &str) -> Result<String, String> {
+    let trimmed = prompt.trim();
+    let Some(reference) = trimmed.strip_prefix('@') else {
+        return Ok(trimmed.to_string());
+    };
+
+    let path = match reference.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()
+            .ok_or("Cannot resolve home directory for prompt file")?
+            .join(rest),
+        None => PathBuf::from(reference),
+    };
+    let metadata = fs::metadata(&path)
+        .map_err(|e| format!("Prompt file not readable: {} ({})", path.display(), e))?;
+    if !metadata.is_file() {
+        return Err(format!("Prompt file is not a regular file: {}", path.display()));
+    }
+    if metadata.len() > MAX_PROMPT_FILE_BYTES {
+        return Err(format!(
+            "Prompt file too large: {} ({} bytes, max {})",
+            path.display(),
+            metadata.len(),
+            MAX_PROMPT_FILE_BYTES
+        ));
+    }
+    let bytes = fs::read(&path)
+        .map_err(|e| format!("Failed to read prompt file {}: {}", path.display(), e))?;
+    let content = String::from_utf8(bytes)
+        .map_err(|_| format!("Prompt file is not valid UTF-8: {}", path.display()))?;
+    Ok(content.trim().to_string())
+}
+
+fn compile_expect_reply_regex(pattern: Option<&str>) -> Result<Option<regex::Regex>, String> {
+    let Some(pattern) = pattern.filter(|p| !p.trim().is_empty()) else {
+        return Ok(None);
+    };
+    regex::Regex::new(pattern)
+        .map(Some)
+        .map_err(|e| format!("Invalid expect_reply_regex {:?}: {}", pattern, e))
+}
+
+/// What a wakeup would run, as reported by [`dry_run_wakeup`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WakeupDryRun {
+    /// Window after resolving `codex-auto` against the stored quota.
+    pub model: String,
+    pub binary: String,
+    /// Program followed by its arguments.
+    pub args: Vec<String>,
+    /// Temp `CODEX_HOME` used for the check; already removed when this is returned.
+    pub codex_home: String,
+    pub auth_file_written: bool,
+    pub prompt_via_stdin: bool,
+    pub max_output_tokens: u32,
+}
+
+/// Resolves everything a wakeup would run (binary, arguments, temp `CODEX_HOME`) and
+/// writes the auth file to verify it, without launching the CLI, touching the network,
+/// reserving a duplicate slot or recording history. The temp dir is removed before returning.
+pub fn dry_run_wakeup(
+    account_id: &str,
+    model: &str,
+    prompt: &str,
+    max_output_tokens: u32,
+    options: &WakeupOptions,
+) -> Result<WakeupDryRun, String> {
+    let (cli_model, reasoning_effort) = resolve_cli_overrides(options)?;
+    let account = codex_account::load_account(account_id)
+        .ok_or_else(|| format!("Codex account not found: {}", account_id))?;
+    let model = resolve_auto_window(model, account.quota.as_ref());
+    let params = CliRunParams {
+        cli_model,
+        reasoning_effort,
+        max_output_tokens: effective_max_output_tokens(
+            max_output_tokens,
+            model,
+            &codex_wakeup_settings::get_settings(),
+        ),
+        ..CliRunParams::default()
+    };
+    let final_prompt = prepare_prompt(prompt)?;
+    let codex_cli = resolve_codex_cli_path(Some(&account))?;
+
+    let temp_home = next_temp_home_dir()?;
+    let result = codex_account::write_auth_file_to_dir(&temp_home, &account).map(|()| {
+        let output_file = temp_home.join("last_message.txt");
+        let (command, prompt_via_stdin) = build_wakeup_command(
+            &codex_cli,
+            &temp_home,
+            &output_file,
+            &final_prompt,
+            &params,
+            codex_wakeup_settings::get_settings().cli_json_events,
+        );
+        WakeupDryRun {
+            model: model.to_string(),
+            binary: codex_cli.to_string_lossy().to_string(),
+            args: std::iter::once(command.get_program())
+                .chain(command.get_args())
+                .map(|arg| arg.to_string_lossy().to_string())
+                .collect(),
+            codex_home: temp_home.to_string_lossy().to_string(),
+            auth_file_written: temp_home.join("auth.json").is_file(),
+            prompt_via_stdin,
+            max_output_tokens: params.max_output_tokens,
+        }
+    });
+    if let Err(e) = fs::remove_dir_all(&temp_home) {
+        logger::log_warn(&format!(
+            "[CodexWakeup] Failed to cleanup temp CODEX_HOME {}: {}",
+            temp_home.display(),
+            e
+        ));
+    }
+    result
+}
+
+/// `max_output_tokens` caps the CLI reply; 0 means "CLI default".
+pub async fn trigger_wakeup(
+    account_id: &str,
+    model: &str,
+    prompt: &str,
+    max_output_tokens: u32,
+) -> Result<WakeupResponse, String> {
+    trigger_wakeup_with_options(
+        account_id,
+        model,
+        prompt,
+        max_output_tokens,
+        &WakeupOptions::default(),
+    )
+    .await
+}
+
+pub async fn trigger_wakeup_with_options(
+    account_id: &str,
+    model: &str,
+    prompt: &str,
+    max_output_tokens: u32,
+    options: &WakeupOptions,
+) -> Result<WakeupResponse, String> {
+    trigger_wakeup_recorded(account_id, model, prompt, max_output_tokens, options)
+        .await
+        .0
+}
+
+/// Runs a wakeup and persists its history item (success, failure or skipped duplicate),
+/// labelled from `options.history`. The item is also returned for callers that report it.
+pub async fn trigger_wakeup_recorded(
+    account_id: &str,
+    model: &str,
+    prompt: &str,
+    max_output_tokens: u32,
+    options: &WakeupOptions,
+) -> (
+    Result<WakeupResponse, String>,
+    codex_wakeup_history::WakeupHistoryItem,
+) {
+    let started = std::time::Instant::now();
+    let result = run_wakeup(account_id, model, prompt, max_output_tokens, options).await;
+    let account_label = codex_account::load_account(account_id)
+        .map(|account| account.display_label().to_string())
+        .unwrap_or_else(|| account_id.to_string());
+    let context = &options.history;
+    let item = codex_wakeup_history::WakeupHistoryItem {
+        trigger_type: context.trigger_type.clone(),
+        task_name: context.task_name.clone(),
+        replay_of: context.replay_of.clone(),
+        ..history_item_for_result(
+            &result,
+            &account_label,
+            model,
+            prompt,
+            context.trigger_source.clone(),
+            options.expect_reply_regex.clone(),
+            started.elapsed().as_millis() as u64,
+        )
+    };
+    // Cache hits and skipped duplicates never reached the CLI, so they are not wakeups.
+    if !result.as_ref().is_ok_and(WakeupResponse::cli_skipped) {
+        codex_metrics::record_wakeup(&item.account_email, item.success);
+    }
+    if let Err(e) = codex_wakeup_history::add_history_items(vec![item.clone()]) {
+        logger::log_error(&format!("Failed to persist Codex wakeup history: {}", e));
+    }
+    (result, item)
+}
+
+async fn run_wakeup(
+    account_id: &str,
+    model: &str,
+    prompt: &str,
+    max_output_tokens: u32,
+    options: &WakeupOptions,
+) -> Result<WakeupResponse, String> {
+    let expect_reply_regex = compile_expect_reply_regex(options.expect_reply_regex.as_deref())?;
+    let (cli_model, reasoning_effort) = resolve_cli_overrides(options)?;
+
+    let account = codex_account::load_account(account_id)
+        .ok_or_else(|| format!("Codex account not found: {}", account_id))?;
+
+    let old_quota = account.quota.clone();
+    let started = std::time::Instant::now();
+    let model = resolve_auto_window(model, old_quota.as_ref());
+    let max_output_tokens = effective_max_output_tokens(
+        max_output_tokens,
+        model,
+        &codex_wakeup_settings::get_settings(),
+    );
+
+    logger::log_info(&format!(
+        "[CodexWakeup] Starting wakeup: email={}, window={}, cli_model={}, reasoning={}, max_output_tokens={}",
+        account.display_label(), model, cli_model, reasoning_effort, max_output_tokens
+    ));
+
+    let final_prompt = prepare_prompt(prompt)?;
+
+    let _slot = acquire_wakeup_slot(account_id).await?;
+    if let Err(err) = validate_account_for_wakeup(&account) {
+        logger::log_warn(&format!(
+            "[CodexWakeup] Account failed pre-wakeup validation: email={}, reason={}",
+            account.display_label(), err
+        ));
+        return Err(err);
+    }
+
+    let guards = effective_guards(&account);
+    if let Err(err) = check_account_guards(&account, model, &guards) {
+        logger::log_warn(&format!(
+            "[CodexWakeup] Wakeup blocked by account guard: email={}, window={}, reason={}",
+            account.display_label(), model, err
+        ));
+        return Err(err);
+    }
+
+    // Served only after the guards above, so a cache hit never bypasses pause, caps or cooldown.
+    let reply_cache_ttl_ms = codex_wakeup_settings::get_settings().reply_cache_ttl_ms as i64;
+    let cache_key: ReplyCacheKey = (
+        account_id.to_string(),
+        final_prompt.clone(),
+        format!("{}|{}|{}", model, cli_model, reasoning_effort),
+    );
+    if reply_cache_ttl_ms > 0 {
+        if let Some(cached) = cached_reply(&cache_key, reply_cache_ttl_ms) {
+            logger::log_info(&format!(
+                "[CodexWakeup] Returning cached reply: email={}, window={}",
+                account.display_label(), model
+            ));
+            return Ok(apply_output_format(cached, options.output_format));
+        }
+    }
+
+    let dedup_key = wakeup_dedup_key(
+        account_id,
+        model,
+        codex_wakeup_settings::get_settings().dedup_per_window,
+    );
+    // A zero window always reserves, so `force` still records the launch timestamp.
+    let duplicate_window_ms = if options.force {
+        0
+    } else {
+        options
+            .duplicate_window_ms
+            .unwrap_or(guards.duplicate_window_ms)
+            .max(0)
+    };
+    let cli_executed = try_reserve_wakeup(&dedup_key, duplicate_window_ms);
+    let mut cli_duration_ms = None;
+    let CliRunOutput {
+        reply: cli_reply,
+        usage,
+        ids,
+        debug,
+        exit_code,
+        exit_warning,
+    } = if cli_executed {
+        if let Err(err) = acquire_global_launch_slot().await {
+            release_wakeup_reservation(&dedup_key);
+            logger::log_warn(&format!(
+                "[CodexWakeup] Wakeup gated: email={}, window={}, reason={}",
+                account.display_label(), model, err
+            ));
+            return Err(err);
+        }
+        record_wakeup_launch(account_id);
+        let account_for_cli = account.clone();
+        let prompt_for_cli = final_prompt.clone();
+        let params = CliRunParams {
+            cli_model: cli_model.clone(),
+            reasoning_effort: reasoning_effort.clone(),
+            max_output_tokens,
+            timeout: cli_timeout(codex_wakeup_settings::get_settings().cli_timeout_ms),
+            capture_debug: options.verbose,
+            ..CliRunParams::default()
+        };
+        let cli_started = std::time::Instant::now();
+        match tauri::async_runtime::spawn_blocking(move || {
+            run_codex_wakeup_cli(&account_for_cli, &prompt_for_cli, &params)
+        })
+        .await
+        {
+            Ok(Ok(output)) => {
+                cli_duration_ms = Some(cli_started.elapsed().as_millis() as u64);
+                record_wakeup_success(account_id);
+                output
+            }
+            Ok(Err(err)) => {
+                release_wakeup_reservation(&dedup_key);
+                return Err(err);
+            }
+            Err(join_err) => {
+                release_wakeup_reservation(&dedup_key);
+                return Err(format!(
+                    "Codex wakeup background task failed: {}",
+                    join_err
+                ));
+            }
+        }
+    } else {
+        logger::log_info(&format!(
+            "[CodexWakeup] Skipping duplicate wakeup call: email={}, window={}",
+            account.display_label(), model
+        ));
+        CliRunOutput {
+            reply: "Skipped duplicate wakeup request (recently executed for this account)."
+                .to_string(),
+            usage: CliTokenUsage::default(),
+            ids: CliResponseIds::default(),
+            debug: None,
+            exit_code: None,
+            exit_warning: None,
+        }
+    };
+
+    let reply_status = cli_executed.then(|| classify_reply(&cli_reply));
+    if options.require_reply && reply_status == Some(ReplyStatus::Unexpected) {
+        logger::log_warn(&format!(
+            "[CodexWakeup] Empty reply rejected (require_reply): email={}, window={}",
+            account.display_label(), model
+        ));
+        return Err(format!(
+            "{} (raw: {:?})",
+            CLI_EMPTY_REPLY_ERROR,
+            trim_for_log(cli_reply.trim(), 80)
+        ));
+    }
+
+    let refresh_delay_ms = codex_wakeup_settings::get_settings().post_wakeup_refresh_delay_ms;
+    if cli_executed && refresh_delay_ms > 0 {
+        tokio::time::sleep(std::time::Duration::from_millis(refresh_delay_ms)).await;
+    }
+    let new_quota = match codex_quota::refresh_account_quota(account_id, 0).await {
+        Ok(quota) => Some(quota),
+        Err(err) => {
+            logger::log_warn(&format!(
+                "[CodexWakeup] Quota refresh failed after wakeup: email={}, error={}",
+                account.display_label(), err
+            ));
+            None
+        }
+    };
+    let duration_ms = started.elapsed().as_millis() as u64;
+    let settings = codex_wakeup_settings::get_settings();
+    let mut reply = build_reply(ReplyContext {
+        model,
+        cli_model: &cli_model,
+        reasoning_effort: &reasoning_effort,
+        old_quota: old_quota.as_ref(),
+        new_quota: new_quota.as_ref(),
+        cli_reply: &cli_reply,
+        window_order: settings.reply_window_order,
+        show_stale_quota: settings.show_stale_quota_in_reply,
+        time_format: &ReplyTimeFormat::from_settings(&settings),
+    });
+    let latency_slo_ms = codex_wakeup_settings::get_settings().latency_slo_ms;
+    let slo_violation = latency_slo_violated(latency_slo_ms, cli_duration_ms);
+    if slo_violation {
+        let cli_ms = cli_duration_ms.unwrap_or_default();
+        logger::log_warn(&format!(
+            "[CodexWakeup] Wakeup exceeded latency SLO: email={}, window={}, cli={}ms, slo={}ms",
+            account.display_label(), model, cli_ms, latency_slo_ms
+        ));
+        reply.push_str(&format!(
+            "\nLatency SLO exceeded: CLI took {}ms (target {}ms)",
+            cli_ms, latency_slo_ms
+        ));
+    }
+    let reply_length = cli_reply.chars().count();
+    let reply_matched = match (&expect_reply_regex, cli_executed) {
+        (Some(re), true) => Some(re.is_match(cli_reply.trim())),
+        _ => None,
+    };
+    let pattern_warning = if reply_matched == Some(false) {
+        let pattern = expect_reply_regex.as_ref().map(|re| re.as_str()).unwrap_or_default();
+        logger::log_warn(&format!(
+            "[CodexWakeup] Reply did not match expected pattern: email={}, pattern={}",
+            account.display_label(), pattern
+        ));
+        Some(format!("Reply did not match expected pattern: {}", pattern))
+    } else {
+        None
+    };
+    let warnings: Vec<String> = [exit_warning, pattern_warning].into_iter().flatten().collect();
+    let warning = (!warnings.is_empty()).then(|| warnings.join("; "));
+
+    logger::log_info(&format!(
+        "[CodexWakeup] Wakeup completed: email={}, window={}, duration={}ms",
+        account.display_label(), model, duration_ms
+    ));
+
+    let details = WakeupDetails {
+        model: model.to_string(),
+        cli_model,
+        reasoning_effort,
+        cli_executed,
+        cli_reply: cli_reply.trim().to_string(),
+        reply_matched,
+        windows: build_window_details(model, old_quota.as_ref(), new_quota.as_ref()),
+        quota_refreshed: new_quota.is_some(),
+        duration_ms,
+        slo_violation,
+        max_output_tokens,
+        exit_code,
+        reply_status,
+    };
+    let response = WakeupResponse {
+        reply,
+        prompt_tokens: usage.prompt_tokens,
+        completion_tokens: usage.completion_tokens,
+        total_tokens: usage.total_tokens,
+        trace_id: ids.trace_id,
+        response_id: ids.response_id,
+        duration_ms,
+        cli_duration_ms,
+        slo_violation,
+        reply_length,
+        reply_matched,
+        warning,
+        from_cache: false,
+        details: Some(details),
+        debug,
+    };
+    if reply_cache_ttl_ms > 0 && cli_executed {
+        store_cached_reply(cache_key, &response);
+    }
+    Ok(apply_output_format(response, options.output_format))
+}
+
+/// History message for a reply served from the cache without launching the CLI.
+const CACHED_REPLY_HISTORY_MESSAGE: &str = "Served from the reply cache (not sent to the CLI)";
+
+/// History message for a duplicate call that never reached the CLI.
+const SKIPPED_DUPLICATE_HISTORY_MESSAGE: &str =
+    "Skipped: duplicate wakeup within the suppression window (not sent to the CLI)";
+
+/// Builds the history record for a wakeup outcome.
+fn history_item_for_result(
+    result: &Result<WakeupResponse, String>,
+    account_email: &str,
+    model: &str,
+    prompt: &str,
+    trigger_source: codex_wakeup_history::TriggerSource,
+    reply_pattern: Option<String>,
+    duration_ms: u64,
+) -> codex_wakeup_history::WakeupHistoryItem {
+    let slo_violation = result.as_ref().is_ok_and(|resp| resp.slo_violation);
+    let max_output_tokens = result
+        .as_ref()
+        .ok()
+        .and_then(|resp| resp.details.as_ref())
+        .map(|details| details.max_output_tokens)
+        .filter(|&cap| cap > 0);
+    let exit_code = match result {
+        Ok(resp) => resp.details.as_ref().and_then(|details| details.exit_code),
+        Err(err) => cli_exit_code(err),
+    };
+    let (success, message, reply_length, reply_matched, failure_kind) = match result {
+        Ok(resp) if resp.from_cache => (
+            true,
+            Some(CACHED_REPLY_HISTORY_MESSAGE.to_string()),
+            None,
+            None,
+            None,
+        ),
+        Ok(resp) if resp.details.as_ref().is_some_and(|details| !details.cli_executed) => (
+            true,
+            Some(SKIPPED_DUPLICATE_HISTORY_MESSAGE.to_string()),
+            None,
+            None,
+            None,
+        ),
+        Ok(resp) => (
+            resp.reply_matched.unwrap_or(true),
+            Some(match &resp.warning {
+                Some(warning) => format!("{} ({})", resp.reply, warning),
+                None => resp.reply.clone(),
+            }),
+            Some(resp.reply_length),
+            resp.reply_matched,
+            None,
+        ),
+        Err(err) => (
+            false,
+            Some(err.clone()),
+            None,
+            None,
+            failure_kind(err).map(str::to_string),
+        ),
+    };
+    let now = chrono::Utc::now().timestamp_millis();
+    codex_wakeup_history::WakeupHistoryItem {
+        id: format!("{}-{}-{}", now, trigger_source, uuid::Uuid::new_v4().simple()),
+        timestamp: now,
+        trigger_type: "manual".to_string(),
+        trigger_source,
+        task_name: None,
+        account_email: account_email.to_string(),
+        model_id: model.to_string(),
+        prompt: Some(prompt.to_string()),
+        success,
+        message,
+        duration: Some(duration_ms),
+        reply_length,
+        reply_pattern,
+        reply_matched,
+        failure_kind,
+        replay_of: None,
+        slo_violation,
+        max_output_tokens,
+        exit_code,
+    }
+}
+
+/// Outcome for one account in a [`wake_all_low`] run.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchWakeResult {
+    pub account_id: String,
+    pub email: String,
+    /// Remaining percentage of the targeted window before waking.
+    pub remaining_percentage: i32,
+    pub success: bool,
+    pub message: String,
+    /// Set when the account was selected but not woken (e.g. over its daily cap).
+    pub skipped_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WakeAllLowReport {
+    pub threshold: i32,
+    pub model: String,
+    pub refresh_failures: usize,
+    pub woken: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub results: Vec<BatchWakeResult>,
+}
+
+fn window_remaining(quota: &CodexQuota, model: &str) -> i32 {
+    match model {
+        MODEL_HOURLY => quota.hourly_percentage,
+        MODEL_WEEKLY => quota.weekly_percentage,
+        _ => quota.hourly_percentage.min(quota.weekly_percentage),
     }
-    guard.insert(account_id.to_string(), now);
-    true
 }
 
-fn release_wakeup_reservation(account_id: &str) {
-    let mut guard = wakeup_state().lock().expect("codex wakeup state lock");
-    guard.remove(account_id);
+/// Stored reset time (Unix seconds) of the window a wakeup on `model` targets; `codex-auto`
+/// and unknown ids use whichever window resets first.
+fn window_reset_time(quota: &CodexQuota, model: &str) -> Option<i64> {
+    match model {
+        MODEL_HOURLY => quota.hourly_reset_time,
+        MODEL_WEEKLY => quota.weekly_reset_time,
+        _ => match (quota.hourly_reset_time, quota.weekly_reset_time) {
+            (Some(hourly), Some(weekly)) => Some(hourly.min(weekly)),
+            (hourly, weekly) => hourly.or(weekly),
+        },
+    }
 }
 
-pub async fn trigger_wakeup(
-    account_id: &str,
-    model: &str,
-    prompt: &str,
-    _max_output_tokens: u32,
-) -> Result<WakeupResponse, String> {
-    let account = codex_account::load_account(account_id)
-        .ok_or_else(|| format!("Codex account not found: {}", account_id))?;
+/// `reset + offset_secs`, but never earlier than `now` (a past target means "as soon as
+/// possible": the window already rolled over and is sitting idle).
+fn suggest_from_reset(reset_at: i64, offset_secs: i64, now: i64) -> i64 {
+    reset_at.saturating_add(offset_secs).max(now)
+}
 
-    let old_quota = account.quota.clone();
-    let started = std::time::Instant::now();
+/// Suggested Unix timestamp (seconds) for the next keep-alive on `window`.
+///
+/// Heuristic: a wakeup spent inside the current window does nothing for the next one,
+/// so the best moment is right after the stored reset, when a fresh window can be opened.
+/// `reset_wakeup_offset_secs` (default +120s) shifts the target: a lag absorbs backend
+/// clock skew so the call does not land in the old window, a negative lead targets the
+/// expiring window instead. If that moment has already passed, the suggestion is now.
+/// Returns `None` when the account or its stored reset time for the window is missing;
+/// refresh the quota first to get one.
+pub fn suggest_wakeup_time(account_id: &str, window: &str) -> Option<i64> {
+    let account = codex_account::load_account(account_id)?;
+    let reset_at = window_reset_time(account.quota.as_ref()?, window)?;
+    Some(suggest_from_reset(
+        reset_at,
+        codex_wakeup_settings::get_settings().reset_wakeup_offset_secs,
+        chrono::Utc::now().timestamp(),
+    ))
+}
 
+/// Maintenance sweep: refreshes every quota, then wakes each account whose `model` window
+/// is below `threshold` percent. Accounts failing pre-wakeup validation (bad tokens, daily
+/// cap) are reported as skipped. Concurrency is bounded by the shared wakeup limiter, and
+/// every attempt is recorded in history with `trigger_source = batch`.
+pub async fn wake_all_low(threshold: i32, model: &str) -> Result<WakeAllLowReport, String> {
+    let refresh = codex_quota::refresh_all_quotas_report().await;
+    let candidates: Vec<(CodexAccount, i32)> = codex_account::list_accounts()
+        .into_iter()
+        .filter_map(|account| {
+            let remaining = window_remaining(account.quota.as_ref()?, model);
+            (remaining < threshold).then_some((account, remaining))
+        })
+        .collect();
     logger::log_info(&format!(
-        "[CodexWakeup] Starting wakeup: email={}, window={}",
-        account.email, model
+        "[CodexWakeup] Batch wake of low accounts: window={}, threshold={}%, selected={}",
+        model,
+        threshold,
+        candidates.len()
     ));
 
-    let final_prompt = if prompt.trim().is_empty() {
-        DEFAULT_WAKEUP_PROMPT.to_string()
-    } else {
-        prompt.trim().to_string()
-    };
-
-    let cli_reply = if try_reserve_wakeup(account_id) {
-        let account_for_cli = account.clone();
-        let prompt_for_cli = final_prompt.clone();
-        match tauri::async_runtime::spawn_blocking(move || {
-            run_codex_wakeup_cli(&account_for_cli, &prompt_for_cli)
-        })
-        .await
-        {
-            Ok(Ok(reply)) => reply,
-            Ok(Err(err)) => {
-                release_wakeup_reservation(account_id);
-                return Err(err);
-            }
-            Err(join_err) => {
-                release_wakeup_reservation(account_id);
-                return Err(format!(
-                    "Codex wakeup background task failed: {}",
-                    join_err
-                ));
+    let prompt = DEFAULT_WAKEUP_PROMPT.to_string();
+    let tasks = candidates.into_iter().map(|(account, remaining)| {
+        let prompt = prompt.clone();
+        async move {
+            if let Err(reason) = validate_account_for_wakeup(&account) {
+                return (account, remaining, None, Some(reason));
             }
+            let options = WakeupOptions {
+                history: WakeupHistoryContext {
+                    trigger_source: codex_wakeup_history::TriggerSource::Batch,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let (result, _) = trigger_wakeup_recorded(&account.id, model, &prompt, 0, &options).await;
+            (account, remaining, Some(result), None)
         }
-    } else {
-        logger::log_info(&format!(
-            "[CodexWakeup] Skipping duplicate wakeup call: email={}, window={}",
-            account.email, model
-        ));
-        "Skipped duplicate wakeup request (recently executed for this account).".to_string()
-    };
+    });
+    let outcomes = futures::future::join_all(tasks).await;
 
-    let new_quota = match codex_quota::refresh_account_quota(account_id).await {
-        Ok(quota) => Some(quota),
-        Err(err) => {
-            logger::log_warn(&format!(
-                "[CodexWakeup] Quota refresh failed after wakeup: email={}, error={}",
-                account.email, err
-            ));
-            None
+    let mut results = Vec::new();
+    for (account, remaining, outcome, skipped_reason) in outcomes {
+        let (success, message) = match outcome {
+            Some(Ok(resp)) => (resp.reply_matched.unwrap_or(true), resp.reply),
+            Some(Err(err)) => (false, err),
+            None => (false, skipped_reason.clone().unwrap_or_default()),
+        };
+        results.push(BatchWakeResult {
+            account_id: account.id,
+            email: account.display_label().to_string(),
+            remaining_percentage: remaining,
+            success,
+            message,
+            skipped_reason,
+        });
+    }
+
+    let skipped = results.iter().filter(|r| r.skipped_reason.is_some()).count();
+    let woken = results.iter().filter(|r| r.success).count();
+    Ok(WakeAllLowReport {
+        threshold,
+        model: model.to_string(),
+        refresh_failures: refresh.failure_count,
+        woken,
+        failed: results.len() - woken - skipped,
+        skipped,
+        results,
+    })
+}
+
+/// Wakes `account_ids` with at most `concurrency` wakeups of this batch in flight (still
+/// bounded by the shared wakeup limiter). Each wakeup keeps its duplicate reservation and is
+/// recorded in history with `trigger_source = batch`; one failure never stops the rest.
+/// Results follow the order of `account_ids`.
+pub async fn trigger_wakeup_batch(
+    account_ids: &[String],
+    model: &str,
+    prompt: &str,
+    concurrency: usize,
+) -> Vec<(String, Result<WakeupResponse, String>)> {
+    let semaphore = Arc::new(tokio::sync::Semaphore::new(concurrency.max(1)));
+    logger::log_info(&format!(
+        "[CodexWakeup] Batch wakeup: accounts={}, window={}, concurrency={}",
+        account_ids.len(),
+        model,
+        concurrency.max(1)
+    ));
+    let tasks = account_ids.iter().map(|account_id| {
+        let semaphore = semaphore.clone();
+        async move {
+            let _permit = match semaphore.acquire_owned().await {
+                Ok(permit) => permit,
+                Err(e) => return (account_id.clone(), Err(format!("Batch wakeup aborted: {}", e))),
+            };
+            let options = WakeupOptions {
+                history: WakeupHistoryContext {
+                    trigger_source: codex_wakeup_history::TriggerSource::Batch,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let (result, _) = trigger_wakeup_recorded(account_id, model, prompt, 0, &options).await;
+            (account_id.clone(), result)
+        }
+    });
+    futures::future::join_all(tasks).await
+}
+
+/// Serializable form of one [`trigger_wakeup_batch`] result.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchWakeupOutcome {
+    pub account_id: String,
+    pub response: Option<WakeupResponse>,
+    pub error: Option<String>,
+}
+
+impl From<(String, Result<WakeupResponse, String>)> for BatchWakeupOutcome {
+    fn from((account_id, result): (String, Result<WakeupResponse, String>)) -> Self {
+        let (response, error) = match result {
+            Ok(response) => (Some(response), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            account_id,
+            response,
+            error,
         }
+    }
+}
+
+/// Re-runs a past wakeup with its stored account, window, prompt and reply pattern,
+/// recording a fresh history item linked to the original via `replay_of`.
+pub async fn replay_history_item(id: &str) -> Result<WakeupResponse, String> {
+    let original = codex_wakeup_history::load_history()?
+        .into_iter()
+        .find(|item| item.id == id)
+        .ok_or_else(|| format!("Codex wakeup history item not found: {}", id))?;
+    let account = codex_account::list_accounts()
+        .into_iter()
+        .find(|account| account.matches_label(&original.account_email))
+        .ok_or_else(|| {
+            format!(
+                "Cannot replay wakeup: account {} no longer exists",
+                original.account_email
+            )
+        })?;
+    let prompt = original
+        .prompt
+        .clone()
+        .filter(|prompt| !prompt.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_WAKEUP_PROMPT.to_string());
+    let options = WakeupOptions {
+        expect_reply_regex: original.reply_pattern.clone(),
+        history: WakeupHistoryContext {
+            trigger_source: codex_wakeup_history::TriggerSource::Replay,
+            task_name: original.task_name.clone(),
+            replay_of: Some(original.id.clone()),
+            ..Default::default()
+        },
+        ..Default::default()
     };
-    let duration_ms = started.elapsed().as_millis() as u64;
-    let reply = build_reply(model, old_quota.as_ref(), new_quota.as_ref(), &cli_reply);
 
     logger::log_info(&format!(
-        "[CodexWakeup] Wakeup completed: email={}, window={}, duration={}ms",
-        account.email, model, duration_ms
+        "[CodexWakeup] Replaying history item: id={}, email={}, window={}",
+        original.id, account.display_label(), original.model_id
+    ));
+    trigger_wakeup_with_options(&account.id, &original.model_id, &prompt, 0, &options).await
+}
+
+/// Fast interactive check: minimal reasoning, short timeout, no dedup, quota refresh or history.
+pub async fn quick_test(
+    account_id: &str,
+    prompt: &str,
+    model: &str,
+) -> Result<WakeupResponse, String> {
+    let account = codex_account::load_account(account_id)
+        .ok_or_else(|| format!("Codex account not found: {}", account_id))?;
+    validate_account_tokens(&account)?;
+    let final_prompt = prepare_prompt(prompt)?;
+
+    logger::log_info(&format!(
+        "[CodexWakeup] Quick test: email={}, window={}",
+        account.display_label(), model
     ));
+    let _slot = acquire_wakeup_slot(account_id).await?;
+    let started = std::time::Instant::now();
+    let params = CliRunParams {
+        cli_model: CLI_MODEL.to_string(),
+        reasoning_effort: QUICK_TEST_REASONING_LEVEL.to_string(),
+        max_output_tokens: 0,
+        timeout: Some(std::time::Duration::from_secs(QUICK_TEST_TIMEOUT_SECS)),
+        capture_debug: false,
+    };
+    let CliRunOutput {
+        reply: cli_reply,
+        usage,
+        ids,
+        exit_code,
+        ..
+    } = tauri::async_runtime::spawn_blocking(move || {
+        run_codex_wakeup_cli(&account, &final_prompt, &params)
+    })
+    .await
+    .map_err(|e| format!("Codex quick test background task failed: {}", e))??;
 
+    let duration_ms = started.elapsed().as_millis() as u64;
+    let details = WakeupDetails {
+        model: model.to_string(),
+        cli_model: CLI_MODEL.to_string(),
+        reasoning_effort: QUICK_TEST_REASONING_LEVEL.to_string(),
+        cli_executed: true,
+        cli_reply: cli_reply.trim().to_string(),
+        reply_matched: None,
+        windows: Vec::new(),
+        quota_refreshed: false,
+        duration_ms,
+        slo_violation: false,
+        max_output_tokens: 0,
+        exit_code,
+        reply_status: Some(classify_reply(&cli_reply)),
+    };
     Ok(WakeupResponse {
-        reply,
-        prompt_tokens: None,
-        completion_tokens: None,
-        total_tokens: None,
-        trace_id: None,
-        response_id: None,
+        reply_length: cli_reply.chars().count(),
+        reply: cli_reply,
+        prompt_tokens: usage.prompt_tokens,
+        completion_tokens: usage.completion_tokens,
+        total_tokens: usage.total_tokens,
+        trace_id: ids.trace_id,
+        response_id: ids.response_id,
         duration_ms,
+        cli_duration_ms: Some(duration_ms),
+        slo_violation: false,
+        reply_matched: None,
+        warning: None,
+        from_cache: false,
+        details: Some(details),
+        debug: None,
     })
 }
 
@@ -467,5 +3036,725 @@ pub async fn fetch_available_models() -> Result<Vec<AvailableModel>, String> {
             model_constant: Some("weekly".to_string()),
             recommended: Some(true),
         },
+        AvailableModel {
+            id: MODEL_AUTO.to_string(),
+            display_name: "Lowest Window".to_string(),
+            model_constant: Some("auto".to_string()),
+            recommended: Some(true),
+        },
     ])
 }
+
+fn parse_cli_model_list(stdout: &str) -> Vec<String> {
+    if let Ok(value) = serde_json::from_str::<serde_json::Value>(stdout.trim()) {
+        let entries = value
+            .get("models")
+            .or_else(|| value.get("data"))
+            .unwrap_or(&value)
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        return entries
+            .iter()
+            .filter_map(|entry| {
+                entry
+                    .as_str()
+                    .or_else(|| entry.get("id").and_then(|v| v.as_str()))
+                    .or_else(|| entry.get("slug").and_then(|v| v.as_str()))
+                    .map(|id| id.to_string())
+            })
+            .collect();
+    }
+
+    stdout
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .map(|token| token.trim_matches(|c: char| c == '-' || c == '*' || c == ','))
+        .filter(|token| {
+            !token.is_empty()
+                && token.chars().any(|c| c.is_ascii_digit())
+                && token
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.' || c == '_')
+        })
+        .map(|token| token.to_string())
+        .collect()
+}
+
+fn query_cli_models() -> Result<Vec<String>, String> {
+    let codex_cli = resolve_codex_cli_path(None)?;
+    let output = command_for_executable(&codex_cli)
+        .arg("models")
+        .output()
+        .map_err(|e| format!("Failed to launch codex CLI: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "codex models exited with {}",
+            output
+                .status
+                .code()
+                .map(|code| code.to_string())
+                .unwrap_or_else(|| "unknown".to_string())
+        ));
+    }
+    let models = parse_cli_model_list(&String::from_utf8_lossy(&output.stdout));
+    if models.is_empty() {
+        return Err("codex models returned no model ids".to_string());
+    }
+    Ok(models)
+}
+
+/// Lists models the CLI reports, falling back to the configured list; cached until `refresh`.
+pub async fn list_cli_models(refresh: bool) -> Result<Vec<CliModel>, String> {
+    let cache = CLI_MODELS_CACHE.get_or_init(|| Mutex::new(None));
+    if !refresh {
+        if let Some(models) = cache.lock().expect("codex cli models lock").clone() {
+            return Ok(models);
+        }
+    }
+
+    let queried = tauri::async_runtime::spawn_blocking(query_cli_models)
+        .await
+        .map_err(|e| format!("Codex model query task failed: {}", e))?;
+    let models = match queried {
+        Ok(ids) => ids
+            .into_iter()
+            .map(|id| CliModel {
+                id,
+                available: Some(true),
+                source: "cli".to_string(),
+            })
+            .collect::<Vec<_>>(),
+        Err(err) => {
+            logger::log_info(&format!(
+                "[CodexWakeup] CLI model listing unavailable, using configured list: {}",
+                err
+            ));
+            codex_wakeup_settings::get_settings()
+                .fallback_cli_models
+                .into_iter()
+                .map(|id| CliModel {
+                    id,
+                    available: None,
+                    source: "config".to_string(),
+                })
+                .collect()
+        }
+    };
+
+    *cache.lock().expect("codex cli models lock") = Some(models.clone());
+    Ok(models)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_prefixes() -> Vec<String> {
+        vec!["tokens used".to_string()]
+    }
+
+    fn missing_file() -> PathBuf {
+        std::env::temp_dir().join("cockpit-tools-missing-last-message.txt")
+    }
+
+    #[test]
+    fn test_cached_reply_is_not_recorded_as_cli_run() {
+        let key: ReplyCacheKey = (
+            format!("codex_test_{}", uuid::Uuid::new_v4().simple()),
+            "hi".to_string(),
+            "codex-hourly|gpt|low".to_string(),
+        );
+        let response = WakeupResponse {
+            reply: "OK".to_string(),
+            prompt_tokens: None,
+            completion_tokens: None,
+            total_tokens: None,
+            trace_id: None,
+            response_id: None,
+            reply_length: 2,
+            duration_ms: 10,
+            cli_duration_ms: Some(10),
+            slo_violation: false,
+            reply_matched: None,
+            warning: None,
+            from_cache: false,
+            details: Some(WakeupDetails {
+                model: MODEL_HOURLY.to_string(),
+                cli_model: "gpt".to_string(),
+                reasoning_effort: "low".to_string(),
+                cli_executed: true,
+                cli_reply: "OK".to_string(),
+                reply_matched: None,
+                windows: Vec::new(),
+                quota_refreshed: false,
+                duration_ms: 10,
+                slo_violation: false,
+                max_output_tokens: 0,
+                exit_code: Some(0),
+                reply_status: None,
+            }),
+            debug: None,
+        };
+        store_cached_reply(key.clone(), &response);
+
+        let cached = cached_reply(&key, 60_000).expect("cached reply");
+        assert!(cached.from_cache);
+        assert!(!cached.details.as_ref().unwrap().cli_executed);
+
+        let item = history_item_for_result(
+            &Ok(cached),
+            "a@example.com",
+            MODEL_HOURLY,
+            "hi",
+            codex_wakeup_history::TriggerSource::Manual,
+            None,
+            1,
+        );
+        assert_eq!(item.message.as_deref(), Some(CACHED_REPLY_HISTORY_MESSAGE));
+        assert_eq!(item.reply_length, None);
+    }
+
+    #[test]
+    fn test_read_last_message_skips_token_summary_variants() {
+        let variants = [
+            "codex\nOK\ntokens used\n1,234\n",
+            "codex\nOK\ntokens used: 1234\n",
+            "codex\nOK\nTokens used 123\n",
+            "codex\nOK\n\nTOKENS USED\n  42  \n\n",
+        ];
+        for stdout in variants {
+            assert_eq!(
+                read_last_message(&missing_file(), stdout, &default_prefixes()),
+                "OK",
+                "stdout variant: {:?}",
+                stdout
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_last_message_keeps_numeric_reply_without_summary() {
+        let stdout = "codex\n42\n";
+        assert_eq!(read_last_message(&missing_file(), stdout, &default_prefixes()), "42");
+    }
+
+    #[test]
+    fn test_read_last_message_uses_custom_prefixes() {
+        let stdout = "OK\nUsage summary: 10 in / 2 out\n";
+        let prefixes = vec!["usage summary".to_string()];
+        assert_eq!(read_last_message(&missing_file(), stdout, &prefixes), "OK");
+    }
+
+    #[test]
+    fn test_read_last_message_falls_back_when_only_summary() {
+        let stdout = "tokens used\n99\n";
+        assert_eq!(
+            read_last_message(&missing_file(), stdout, &default_prefixes()),
+            "Wakeup request sent."
+        );
+    }
+
+    #[test]
+    fn test_login_prompt_detection_and_failure_kind() {
+        assert!(is_login_prompt_line("Not logged in. Run `codex login` to continue."));
+        assert!(is_login_prompt_line("Error: 401 Unauthorized"));
+        assert!(!is_login_prompt_line("OK"));
+        assert!(!is_login_prompt_line("Remind me to run codex login tomorrow"));
+        assert!(!is_login_prompt_line("Please loginless mode"));
+        assert_eq!(
+            failure_kind(CLI_AUTH_REQUIRED_ERROR),
+            Some(FAILURE_KIND_AUTH_REQUIRED)
+        );
+        assert_eq!(
+            failure_kind(&format!("{}20000ms", CLI_TIMEOUT_ERROR_PREFIX)),
+            Some(FAILURE_KIND_TIMEOUT)
+        );
+        assert_eq!(failure_kind("Codex CLI wakeup failed (exit=1): boom"), None);
+    }
+
+    #[test]
+    fn test_login_prompt_scanner_stops_at_transcript() {
+        let mut scanner = LoginPromptScanner::default();
+        assert!(!scanner.feed("OpenAI Codex v0.40.0 (research preview)"));
+        assert!(scanner.feed("Not logged in. Please run `codex login`."));
+
+        let mut scanner = LoginPromptScanner::default();
+        assert!(!scanner.feed("[2025-01-01T00:00:00] User instructions:"));
+        assert!(!scanner.feed("Please login and reply OK"));
+        assert!(!scanner.feed("401 Unauthorized"));
+
+        let mut scanner = LoginPromptScanner::default();
+        assert!(!scanner.feed(r#"{"type":"thread.started"}"#));
+        assert!(!scanner.feed("not logged in"));
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn test_output_with_timeout_ignores_login_markers_in_prompt_and_reply() {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(
+            "printf 'user\\nplease login as admin\\ncodex\\nnot logged in here\\n' >&2; \
+             printf 'Please login: OK\\n401 Unauthorized\\n'",
+        );
+        let output = output_with_timeout(
+            &mut command,
+            Some(std::time::Duration::from_secs(10)),
+            None,
+            None,
+        )
+        .expect("healthy run must not be aborted");
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout).contains("401 Unauthorized"));
+    }
+
+    #[test]
+    fn test_cli_exit_code_and_severity_mapping() {
+        use codex_wakeup_settings::CliExitSeverity;
+
+        assert_eq!(cli_exit_code("Codex CLI wakeup failed (exit=3): boom"), Some(3));
+        assert_eq!(cli_exit_code("Codex CLI wakeup failed (exit=unknown): boom"), None);
+        assert_eq!(cli_exit_code(CLI_AUTH_REQUIRED_ERROR), None);
+
+        let mut mapping = std::collections::BTreeMap::new();
+        assert_eq!(CliExitSeverity::for_exit_code(Some(0), &mapping), CliExitSeverity::Success);
+        assert_eq!(CliExitSeverity::for_exit_code(Some(2), &mapping), CliExitSeverity::Failure);
+        assert_eq!(CliExitSeverity::for_exit_code(None, &mapping), CliExitSeverity::Failure);
+        mapping.insert(2, CliExitSeverity::Warning);
+        assert_eq!(CliExitSeverity::for_exit_code(Some(2), &mapping), CliExitSeverity::Warning);
+    }
+
+    #[test]
+    fn test_validate_account_tokens_reports_specific_problems() {
+        use crate::models::codex::CodexTokens;
+
+        // {"sub":"x"} as an unsigned JWT payload.
+        let valid_access = "e30.eyJzdWIiOiJ4In0.sig";
+        let account = |access: &str, refresh: Option<&str>| {
+            CodexAccount::new(
+                "id".to_string(),
+                "user@example.com".to_string(),
+                CodexTokens {
+                    id_token: String::new(),
+                    access_token: access.to_string(),
+                    refresh_token: refresh.map(str::to_string),
+                },
+            )
+        };
+
+        assert!(validate_account_tokens(&account(valid_access, Some("rt"))).is_ok());
+        assert!(validate_account_tokens(&account("", Some("rt")))
+            .unwrap_err()
+            .contains("no access token"));
+        assert!(validate_account_tokens(&account(valid_access, None))
+            .unwrap_err()
+            .contains("no refresh token"));
+        assert!(validate_account_tokens(&account("not-a-jwt", Some("rt")))
+            .unwrap_err()
+            .contains("malformed access token"));
+    }
+
+    #[tokio::test]
+    async fn test_limiter_serializes_same_account_and_parallelizes_others() {
+        use std::sync::atomic::AtomicUsize;
+
+        let limiter = Arc::new(WakeupLimiter::new(2));
+        let active: Arc<Mutex<HashMap<String, usize>>> = Arc::new(Mutex::new(HashMap::new()));
+        let max_same_account = Arc::new(AtomicUsize::new(0));
+        let max_total = Arc::new(AtomicUsize::new(0));
+
+        let mut handles = Vec::new();
+        for account in ["a", "a", "a", "b", "b"] {
+            let limiter = limiter.clone();
+            let active = active.clone();
+            let max_same_account = max_same_account.clone();
+            let max_total = max_total.clone();
+            handles.push(tokio::spawn(async move {
+                let _slot = limiter.acquire(account, 2).await.unwrap();
+                {
+                    let mut guard = active.lock().unwrap();
+                    *guard.entry(account.to_string()).or_default() += 1;
+                    max_same_account.fetch_max(guard[account], Ordering::SeqCst);
+                    max_total.fetch_max(guard.values().sum::<usize>(), Ordering::SeqCst);
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                *active.lock().unwrap().get_mut(account).unwrap() -= 1;
+            }));
+        }
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        assert_eq!(max_same_account.load(Ordering::SeqCst), 1);
+        assert_eq!(max_total.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_read_last_message_strips_ansi_colors() {
+        let stdout = "\x1b[1m\x1b[32mcodex\x1b[0m\nOK\n\x1b[2mtokens used\x1b[0m\n1,234\n";
+        assert_eq!(
+            read_last_message(&missing_file(), stdout, &default_prefixes()),
+            "OK"
+        );
+    }
+
+    #[test]
+    fn test_leaked_session_dirs_are_reported() {
+        let session = next_temp_home_dir().unwrap();
+        let leaked = assert_no_leaked_sessions_for_pid().unwrap_err();
+        assert!(leaked.contains(&session));
+
+        fs::remove_dir_all(&session).unwrap();
+        let still_leaked = assert_no_leaked_sessions_for_pid().err().unwrap_or_default();
+        assert!(!still_leaked.contains(&session));
+    }
+
+    #[test]
+    fn test_parse_session_dir_name() {
+        assert_eq!(parse_session_dir_name("session-42-1700000000000000000"), Some((42, 1_700_000_000_000_000_000)));
+        assert_eq!(parse_session_dir_name("session-x-1"), None);
+        assert_eq!(parse_session_dir_name("other-42-1"), None);
+        assert_eq!(parse_session_dir_name("session-42"), None);
+    }
+
+    #[test]
+    fn test_dash_leading_prompt_follows_separator() {
+        let mut command = Command::new("codex");
+        command.arg("exec");
+        assert!(!push_prompt_arg(&mut command, "-n explain this"));
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args, ["exec", "--", "-n explain this"]);
+
+        let mut command = Command::new("codex");
+        let long_prompt = "x".repeat(PROMPT_STDIN_THRESHOLD_BYTES + 1);
+        assert!(push_prompt_arg(&mut command, &long_prompt));
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args, ["--", "-"]);
+    }
+
+    #[test]
+    fn test_resolve_cli_overrides_validates_reasoning_effort() {
+        let defaults = resolve_cli_overrides(&WakeupOptions::default()).unwrap();
+        assert_eq!(defaults, (CLI_MODEL.to_string(), CLI_REASONING_LEVEL.to_string()));
+
+        let options = WakeupOptions {
+            cli_model: Some("gpt-5-codex".to_string()),
+            reasoning_effort: Some("High".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            resolve_cli_overrides(&options).unwrap(),
+            ("gpt-5-codex".to_string(), "high".to_string())
+        );
+
+        let invalid = WakeupOptions {
+            reasoning_effort: Some("extreme".to_string()),
+            ..Default::default()
+        };
+        assert!(resolve_cli_overrides(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_wakeup_options_deserialize_camel_case_with_defaults() {
+        let options: WakeupOptions = serde_json::from_str(
+            r#"{"cliModel": "gpt-5-codex", "duplicateWindowMs": 500, "requireReply": true, "outputFormat": "json"}"#,
+        )
+        .unwrap();
+        assert_eq!(options.cli_model.as_deref(), Some("gpt-5-codex"));
+        assert_eq!(options.duplicate_window_ms, Some(500));
+        assert!(options.require_reply && !options.force && !options.verbose);
+        assert_eq!(options.output_format, WakeupOutputFormat::Json);
+        assert_eq!(
+            options.history.trigger_source,
+            codex_wakeup_history::TriggerSource::Manual
+        );
+    }
+
+    #[test]
+    fn test_wakeup_dedup_key_per_window() {
+        assert_eq!(wakeup_dedup_key("acc", MODEL_HOURLY, false), "acc");
+        assert_ne!(
+            wakeup_dedup_key("acc", MODEL_HOURLY, true),
+            wakeup_dedup_key("acc", MODEL_WEEKLY, true)
+        );
+    }
+
+    #[test]
+    fn test_check_paused_blocks_until_deadline() {
+        use crate::models::codex::CodexTokens;
+
+        let mut account = CodexAccount::new(
+            "id".to_string(),
+            "user@example.com".to_string(),
+            CodexTokens {
+                id_token: String::new(),
+                access_token: String::new(),
+                refresh_token: None,
+            },
+        );
+        assert!(check_paused(&account, 1_000).is_ok());
+        account.paused_until = Some(2_000);
+        assert!(check_paused(&account, 1_000).unwrap_err().contains("paused until"));
+        assert!(check_paused(&account, 2_000).is_ok());
+    }
+
+    #[test]
+    fn test_effective_max_output_tokens_falls_back_per_window_then_global() {
+        let mut settings = codex_wakeup_settings::CodexWakeupSettings {
+            weekly_max_output_tokens: 1,
+            ..Default::default()
+        };
+        assert_eq!(effective_max_output_tokens(0, MODEL_WEEKLY, &settings), 1);
+        assert_eq!(effective_max_output_tokens(64, MODEL_WEEKLY, &settings), 64);
+        assert_eq!(effective_max_output_tokens(0, MODEL_HOURLY, &settings), 0);
+        settings.default_max_output_tokens = 16;
+        assert_eq!(effective_max_output_tokens(0, MODEL_HOURLY, &settings), 16);
+        settings.hourly_max_output_tokens = 8;
+        assert_eq!(effective_max_output_tokens(0, MODEL_HOURLY, &settings), 8);
+    }
+
+    #[test]
+    fn test_push_max_output_tokens_arg() {
+        let mut command = Command::new("codex");
+        push_max_output_tokens_arg(&mut command, 0);
+        assert_eq!(command.get_args().count(), 0);
+
+        push_max_output_tokens_arg(&mut command, 256);
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args, ["-c", "model_max_output_tokens=256"]);
+    }
+
+    #[test]
+    fn test_cli_timeout_zero_disables() {
+        assert_eq!(cli_timeout(0), None);
+        assert_eq!(cli_timeout(1_500), Some(std::time::Duration::from_millis(1_500)));
+    }
+
+    #[test]
+    fn test_latency_slo_violated() {
+        assert!(latency_slo_violated(1_000, Some(1_001)));
+        assert!(!latency_slo_violated(1_000, Some(1_000)));
+        assert!(!latency_slo_violated(0, Some(60_000)));
+        assert!(!latency_slo_violated(1_000, None));
+    }
+
+    #[test]
+    fn test_only_spawn_errors_are_launch_failures() {
+        let spawn = format!(
+            "{} (binary=codex): {}Text file busy (os error 26)",
+            CLI_LAUNCH_ERROR_PREFIX, CLI_SPAWN_ERROR_PREFIX
+        );
+        assert!(is_launch_failure(&spawn));
+        assert!(!is_launch_failure("Codex CLI wakeup failed (exit=1): spawn failed: nope"));
+        assert!(!is_launch_failure(&format!("{} (binary=codex): No such file", CLI_LAUNCH_ERROR_PREFIX)));
+        assert_eq!(launch_retry_delay(250, 1).as_millis(), 250);
+        assert_eq!(launch_retry_delay(250, 3).as_millis(), 1_000);
+    }
+
+    #[test]
+    fn test_parse_token_usage_from_text_summary() {
+        let next_line = parse_token_usage("codex\nOK\ntokens used\n1,234\n");
+        assert_eq!(next_line.total_tokens, Some(1_234));
+        assert_eq!(next_line.prompt_tokens, None);
+
+        let same_line = parse_token_usage("[2025-01-01T00:00:00] tokens used: 5678\nOK\n");
+        assert_eq!(same_line.total_tokens, Some(5_678));
+
+        let colored = parse_token_usage("\u{1b}[2mtokens used\u{1b}[0m\n\u{1b}[1m42\u{1b}[0m\n");
+        assert_eq!(colored.total_tokens, Some(42));
+    }
+
+    #[test]
+    fn test_parse_token_usage_from_json_events() {
+        let stdout = concat!(
+            "{\"type\":\"thread.started\",\"thread_id\":\"t\"}\n",
+            "{\"type\":\"item.completed\",\"item\":{\"type\":\"agent_message\",\"text\":\"OK\"}}\n",
+            "{\"type\":\"turn.completed\",\"usage\":{\"input_tokens\":120,\"cached_input_tokens\":0,\"output_tokens\":8}}\n",
+        );
+        let usage = parse_token_usage(stdout);
+        assert_eq!(usage.prompt_tokens, Some(120));
+        assert_eq!(usage.completion_tokens, Some(8));
+        assert_eq!(usage.total_tokens, Some(128));
+    }
+
+    #[test]
+    fn test_parse_token_usage_missing_stays_none() {
+        assert_eq!(parse_token_usage("OK\n"), CliTokenUsage::default());
+        assert_eq!(
+            parse_token_usage("tokens used\nnot a number\n{broken json\n"),
+            CliTokenUsage::default()
+        );
+    }
+
+    #[test]
+    fn test_parse_response_ids_from_json_events() {
+        let stdout = concat!(
+            "OK\n",
+            "{\"type\":\"response.created\",\"response\":{\"id\":\"x\",\"response_id\":\"resp_123\"}}\n",
+            "{\"type\":\"turn.completed\",\"trace_id\":\"trace-abc\"}\n",
+        );
+        let ids = parse_response_ids(stdout);
+        assert_eq!(ids.response_id.as_deref(), Some("resp_123"));
+        assert_eq!(ids.trace_id.as_deref(), Some("trace-abc"));
+        assert_eq!(parse_response_ids("tokens used\n12\n"), CliResponseIds::default());
+    }
+
+    #[test]
+    fn test_suggest_wakeup_time_from_reset() {
+        let quota = CodexQuota {
+            hourly_percentage: 40,
+            hourly_reset_time: Some(5_000),
+            weekly_percentage: 80,
+            weekly_reset_time: None,
+            raw_data: None,
+            partial: false,
+            unknown_windows: Vec::new(),
+            code_review_percentage: None,
+            code_review_reset_time: None,
+            plan_type: None,
+            hourly_reset_after_seconds: None,
+            hourly_window_seconds: None,
+            weekly_reset_after_seconds: None,
+            weekly_window_seconds: None,
+            allowed: None,
+            limit_reached: None,
+        };
+        assert_eq!(window_reset_time(&quota, MODEL_HOURLY), Some(5_000));
+        assert_eq!(window_reset_time(&quota, MODEL_WEEKLY), None);
+        assert_eq!(window_reset_time(&quota, MODEL_AUTO), Some(5_000));
+
+        assert_eq!(suggest_from_reset(5_000, 120, 1_000), 5_120);
+        assert_eq!(suggest_from_reset(5_000, -300, 1_000), 4_700);
+        assert_eq!(suggest_from_reset(5_000, 120, 9_000), 9_000);
+    }
+
+    #[test]
+    fn test_prune_reservations_keeps_entries_within_their_window() {
+        let mut reservations = HashMap::new();
+        reservations.insert("old".to_string(), (0, 8_000));
+        reservations.insert("long-window".to_string(), (0, 60_000));
+        reservations.insert("recent".to_string(), (90_000, 8_000));
+        prune_reservations(&mut reservations, 100_000, 0);
+        let mut keys: Vec<_> = reservations.keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, ["long-window", "recent"]);
+    }
+
+    #[test]
+    fn test_prepare_prompt_strips_control_characters() {
+        assert_eq!(sanitize_prompt("a\0b\x07c\nd\te"), "abc\nd\te");
+        assert_eq!(prepare_prompt("  \0\x1b  ").unwrap(), DEFAULT_WAKEUP_PROMPT);
+        assert_eq!(prepare_prompt(" hi\0 ").unwrap(), "hi");
+    }
+
+    #[test]
+    fn test_classify_reply_treats_whitespace_and_punctuation_as_empty() {
+        for reply in ["", "   ", "   .  ", "\n\t...!?\n", "\x1b[1m \x1b[0m", NO_REPLY_PLACEHOLDER] {
+            assert_eq!(classify_reply(reply), ReplyStatus::Unexpected, "reply: {:?}", reply);
+        }
+        for reply in ["OK", "  ok. ", "42", "好的"] {
+            assert_eq!(classify_reply(reply), ReplyStatus::Ok, "reply: {:?}", reply);
+        }
+        assert_eq!(normalize_reply("  a \n\n  b  "), "a b");
+    }
+
+    #[test]
+    fn test_parse_json_final_message_prefers_task_complete() {
+        let stdout = concat!(
+            "{\"id\":\"0\",\"msg\":{\"type\":\"agent_message\",\"message\":\"First paragraph.\\n\\nSecond.\"}}\n",
+            "{\"id\":\"0\",\"msg\":{\"type\":\"token_count\",\"input_tokens\":5}}\n",
+        );
+        assert_eq!(
+            parse_json_final_message(stdout).as_deref(),
+            Some("First paragraph.\n\nSecond.")
+        );
+
+        let with_complete = format!(
+            "{}{}",
+            stdout, "{\"msg\":{\"type\":\"task_complete\",\"last_agent_message\":\"Final\"}}\n"
+        );
+        assert_eq!(parse_json_final_message(&with_complete).as_deref(), Some("Final"));
+
+        let item_shape = "{\"type\":\"item.completed\",\"item\":{\"type\":\"agent_message\",\"text\":\"OK\"}}\n";
+        assert_eq!(parse_json_final_message(item_shape).as_deref(), Some("OK"));
+        assert_eq!(parse_json_final_message("codex\nOK\ntokens used\n12\n"), None);
+    }
+
+    #[test]
+    fn test_parse_cli_version() {
+        assert_eq!(parse_cli_version("codex-cli 0.46.0"), "0.46.0");
+        assert_eq!(parse_cli_version("codex v1.2.3-beta"), "1.2.3-beta");
+        assert_eq!(parse_cli_version("dev build"), "dev build");
+    }
+
+    #[test]
+    fn test_resolve_auto_window_picks_lowest_remaining() {
+        let mut quota = CodexQuota {
+            hourly_percentage: 70,
+            hourly_reset_time: None,
+            weekly_percentage: 20,
+            weekly_reset_time: None,
+            raw_data: None,
+            partial: false,
+            unknown_windows: Vec::new(),
+            code_review_percentage: None,
+            code_review_reset_time: None,
+            plan_type: None,
+            hourly_reset_after_seconds: None,
+            hourly_window_seconds: None,
+            weekly_reset_after_seconds: None,
+            weekly_window_seconds: None,
+            allowed: None,
+            limit_reached: None,
+        };
+        assert_eq!(resolve_auto_window(MODEL_AUTO, Some(&quota)), MODEL_WEEKLY);
+        quota.hourly_percentage = 10;
+        assert_eq!(resolve_auto_window(MODEL_AUTO, Some(&quota)), MODEL_HOURLY);
+        assert_eq!(resolve_auto_window(MODEL_AUTO, None), MODEL_AUTO);
+        assert_eq!(resolve_auto_window(MODEL_WEEKLY, Some(&quota)), MODEL_WEEKLY);
+    }
+
+    #[test]
+    fn test_resolve_cli_path_reports_missing_account_override() {
+        use crate::models::codex::CodexTokens;
+
+        let mut account = CodexAccount::new(
+            "id".to_string(),
+            "user@example.com".to_string(),
+            CodexTokens {
+                id_token: String::new(),
+                access_token: String::new(),
+                refresh_token: None,
+            },
+        );
+        let missing = std::env::temp_dir().join("cockpit-tools-missing-codex-nightly");
+        account.cli_path = Some(missing.to_string_lossy().to_string());
+        let err = resolve_codex_cli_path(Some(&account)).unwrap_err();
+        assert!(err.contains(&missing.display().to_string()));
+        assert!(err.contains("user@example.com"));
+    }
+
+    #[test]
+    fn test_format_reset_time_honours_timezone_and_format() {
+        // 2024-01-15 12:30:00 UTC
+        let ts = Some(1_705_321_800);
+        let utc = ReplyTimeFormat::new(Some("UTC"), Some("%Y-%m-%d %H:%M"));
+        assert_eq!(format_reset_time(ts, &utc), "2024-01-15 12:30");
+        let tokyo = ReplyTimeFormat::new(Some("Asia/Tokyo"), None);
+        assert_eq!(format_reset_time(ts, &tokyo), "01-15 21:30");
+        let india = ReplyTimeFormat::new(Some("+05:30"), None);
+        assert_eq!(format_reset_time(ts, &india), "01-15 18:00");
+        let west = ReplyTimeFormat::new(Some("UTC-8"), None);
+        assert_eq!(format_reset_time(ts, &west), "01-15 04:30");
+        assert_eq!(format_reset_time(None, &utc), "-");
+    }
+
+    #[test]
+    fn test_reply_time_format_falls_back_on_invalid_input() {
+        let invalid = ReplyTimeFormat::new(Some("Mars/Olympus"), Some("%Q %"));
+        assert_eq!(invalid, ReplyTimeFormat::default());
+        assert_eq!(ReplyTimeFormat::new(Some("local"), Some("  ")), ReplyTimeFormat::default());
+        assert_eq!(parse_fixed_offset("+0530"), chrono::FixedOffset::east_opt(19_800));
+        assert_eq!(parse_fixed_offset("+25"), None);
+    }
+}
@@ -10,13 +10,77 @@ const MAX_HISTORY_ITEMS: usize = 100;
 
 static HISTORY_LOCK: std::sync::LazyLock<Mutex<()>> = std::sync::LazyLock::new(|| Mutex::new(()));
 
+/// Where a wakeup came from. Serialized as the legacy lowercase strings; free-form
+/// values from older files ("Manual", "ui", ...) are normalized when loaded and the
+/// canonical form is written back on the next save.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum TriggerSource {
+    Manual,
+    /// Time-based schedule ("scheduled").
+    Scheduler,
+    Crontab,
+    QuotaReset,
+    Batch,
+    Replay,
+    Api,
+    CsvImport,
+    /// Unrecognized value kept verbatim.
+    Other(String),
+}
+
+impl TriggerSource {
+    pub fn as_str(&self) -> &str {
+        match self {
+            TriggerSource::Manual => "manual",
+            TriggerSource::Scheduler => "scheduled",
+            TriggerSource::Crontab => "crontab",
+            TriggerSource::QuotaReset => "quota_reset",
+            TriggerSource::Batch => "batch",
+            TriggerSource::Replay => "replay",
+            TriggerSource::Api => "api",
+            TriggerSource::CsvImport => "csv_import",
+            TriggerSource::Other(value) => value,
+        }
+    }
+}
+
+impl From<String> for TriggerSource {
+    fn from(value: String) -> Self {
+        let normalized = value.trim().to_ascii_lowercase().replace(['-', ' '], "_");
+        match normalized.as_str() {
+            "manual" | "ui" | "user" => TriggerSource::Manual,
+            "scheduled" | "schedule" | "scheduler" => TriggerSource::Scheduler,
+            "crontab" | "cron" => TriggerSource::Crontab,
+            "quota_reset" | "quotareset" | "reset" => TriggerSource::QuotaReset,
+            "batch" => TriggerSource::Batch,
+            "replay" => TriggerSource::Replay,
+            "api" => TriggerSource::Api,
+            "csv_import" | "csv" => TriggerSource::CsvImport,
+            _ => TriggerSource::Other(value),
+        }
+    }
+}
+
+impl From<TriggerSource> for String {
+    fn from(value: TriggerSource) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+impl std::fmt::Display for TriggerSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct WakeupHistoryItem {
     pub id: String,
     pub timestamp: i64,
     pub trigger_type: String,
-    pub trigger_source: String,
+    pub trigger_source: TriggerSource,
     pub task_name: Option<String>,
     pub account_email: String,
     pub model_id: String,
@@ -24,6 +88,40 @@ pub struct WakeupHistoryItem {
     pub success: bool,
     pub message: Option<String>,
     pub duration: Option<u64>,
+    /// Character count of the raw CLI reply, kept even when `message` is truncated.
+    #[serde(default)]
+    pub reply_length: Option<usize>,
+    /// Pattern the reply was validated against, with its outcome.
+    #[serde(default)]
+    pub reply_pattern: Option<String>,
+    #[serde(default)]
+    pub reply_matched: Option<bool>,
+    /// Machine-readable failure category (e.g. `auth_required`, `timeout`).
+    #[serde(default)]
+    pub failure_kind: Option<String>,
+    /// Id of the history item this run replayed, if any.
+    #[serde(default)]
+    pub replay_of: Option<String>,
+    /// The CLI round-trip exceeded `latency_slo_ms` (the run may still have succeeded).
+    #[serde(default)]
+    pub slo_violation: bool,
+    /// Output token cap the CLI ran with; `None` when the CLI default applied or it did not run.
+    #[serde(default)]
+    pub max_output_tokens: Option<u32>,
+    /// CLI process exit code, recorded for successes and failures alike.
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+}
+
+/// Caps `message` at `max_chars`, appending a marker with the number of dropped chars.
+fn truncate_message(message: &str, max_chars: usize) -> String {
+    let total = message.chars().count();
+    if max_chars == 0 || total <= max_chars {
+        return message.to_string();
+    }
+    let mut out = message.chars().take(max_chars).collect::<String>();
+    out.push_str(&format!(" [truncated {} chars]", total - max_chars));
+    out
 }
 
 fn history_path() -> Result<PathBuf, String> {
@@ -45,10 +143,66 @@ pub fn load_history() -> Result<Vec<WakeupHistoryItem>, String> {
 
     let items: Vec<WakeupHistoryItem> = serde_json::from_str(&content)
         .map_err(|e| format!("Failed to parse Codex wakeup history: {}", e))?;
+    let (items, collapsed) = dedup_by_id(items);
+    if collapsed > 0 {
+        modules::logger::log_warn(&format!(
+            "[CodexWakeup] History contains duplicate ids: collapsed={}",
+            collapsed
+        ));
+    }
     Ok(items)
 }
 
+/// Keeps the newest item per id (order preserved) and returns how many were dropped.
+fn dedup_by_id(items: Vec<WakeupHistoryItem>) -> (Vec<WakeupHistoryItem>, usize) {
+    let mut newest: std::collections::HashMap<String, i64> = std::collections::HashMap::new();
+    for item in &items {
+        let entry = newest.entry(item.id.clone()).or_insert(item.timestamp);
+        if item.timestamp > *entry {
+            *entry = item.timestamp;
+        }
+    }
+
+    let total = items.len();
+    let mut kept_ids = std::collections::HashSet::new();
+    let deduped: Vec<WakeupHistoryItem> = items
+        .into_iter()
+        .filter(|item| newest.get(&item.id) == Some(&item.timestamp) && kept_ids.insert(item.id.clone()))
+        .collect();
+    let collapsed = total - deduped.len();
+    (deduped, collapsed)
+}
+
+/// Rewrites the history file without duplicate ids; returns how many items were collapsed.
+pub fn repair_history() -> Result<usize, String> {
+    let _lock = HISTORY_LOCK
+        .lock()
+        .map_err(|_| "Failed to acquire Codex wakeup history lock")?;
+    let path = history_path()?;
+    if !path.exists() {
+        return Ok(0);
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read Codex wakeup history: {}", e))?;
+    if content.trim().is_empty() {
+        return Ok(0);
+    }
+    let items: Vec<WakeupHistoryItem> = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse Codex wakeup history: {}", e))?;
+    let (items, collapsed) = dedup_by_id(items);
+    if collapsed > 0 {
+        save_history(&items)?;
+        modules::logger::log_info(&format!(
+            "[CodexWakeup] History repaired: collapsed={}, total={}",
+            collapsed,
+            items.len()
+        ));
+    }
+    Ok(collapsed)
+}
+
 fn save_history(items: &[WakeupHistoryItem]) -> Result<(), String> {
+    modules::account::ensure_data_dir_writable()?;
     let path = history_path()?;
     let data_dir = modules::account::get_data_dir()?;
     let temp_path = data_dir.join(format!("{}.tmp", HISTORY_FILE));
@@ -60,8 +214,13 @@ fn save_history(items: &[WakeupHistoryItem]) -> Result<(), String> {
 }
 
 pub fn add_history_items(new_items: Vec<WakeupHistoryItem>) -> Result<(), String> {
+    merge_history_items(new_items).map(|_| ())
+}
+
+/// Merges items into the stored history and returns how many new items survived the cap.
+pub fn merge_history_items(new_items: Vec<WakeupHistoryItem>) -> Result<usize, String> {
     if new_items.is_empty() {
-        return Ok(());
+        return Ok(0);
     }
 
     let _lock = HISTORY_LOCK
@@ -71,27 +230,166 @@ pub fn add_history_items(new_items: Vec<WakeupHistoryItem>) -> Result<(), String
     let existing_ids: std::collections::HashSet<String> =
         existing.iter().map(|item| item.id.clone()).collect();
 
+    let max_message_chars = modules::codex_wakeup_settings::get_settings().max_stored_message_chars;
     let filtered_new: Vec<WakeupHistoryItem> = new_items
         .into_iter()
         .filter(|item| !existing_ids.contains(&item.id))
+        .map(|mut item| {
+            item.message = item
+                .message
+                .map(|message| truncate_message(&message, max_message_chars));
+            item
+        })
         .collect();
     if filtered_new.is_empty() {
-        return Ok(());
+        return Ok(0);
     }
 
     let added_count = filtered_new.len();
+    let new_ids: std::collections::HashSet<String> =
+        filtered_new.iter().map(|item| item.id.clone()).collect();
     let mut merged = filtered_new;
     merged.append(&mut existing);
     merged.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
     merged.truncate(MAX_HISTORY_ITEMS);
+    let kept_count = merged.iter().filter(|item| new_ids.contains(&item.id)).count();
 
     save_history(&merged)?;
     modules::logger::log_info(&format!(
-        "[CodexWakeup] History updated: added={}, total={}",
+        "[CodexWakeup] History updated: added={}, kept={}, total={}",
         added_count,
+        kept_count,
         merged.len()
     ));
-    Ok(())
+    Ok(kept_count)
+}
+
+fn success_rate_of(
+    items: &[WakeupHistoryItem],
+    account_email: Option<&str>,
+    since_ms: i64,
+) -> Option<f64> {
+    let (total, succeeded) = items
+        .iter()
+        .filter(|item| item.timestamp >= since_ms)
+        .filter(|item| {
+            account_email
+                .map(|email| item.account_email.eq_ignore_ascii_case(email))
+                .unwrap_or(true)
+        })
+        .fold((0usize, 0usize), |(total, succeeded), item| {
+            (total + 1, succeeded + usize::from(item.success))
+        });
+    if total == 0 {
+        return None;
+    }
+    Some(succeeded as f64 / total as f64)
+}
+
+/// Fraction of successful wakeups within the last `window`, optionally for one account.
+/// Returns `None` when no wakeups fall inside the window.
+pub fn success_rate(account_email: Option<&str>, window: std::time::Duration) -> Option<f64> {
+    let items = match load_history() {
+        Ok(items) => items,
+        Err(e) => {
+            modules::logger::log_warn(&format!(
+                "[CodexWakeup] Failed to load history for success rate: {}",
+                e
+            ));
+            return None;
+        }
+    };
+    let window_ms = i64::try_from(window.as_millis()).unwrap_or(i64::MAX);
+    let since_ms = chrono::Utc::now().timestamp_millis().saturating_sub(window_ms);
+    success_rate_of(&items, account_email, since_ms)
+}
+
+/// Timezone used to assign history items to calendar days.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum HistoryTimezone {
+    /// The system's local timezone (DST-aware).
+    #[default]
+    Local,
+    Utc,
+    /// Fixed offset east of UTC, in minutes.
+    Offset { minutes: i32 },
+}
+
+impl HistoryTimezone {
+    fn date_of(self, timestamp_ms: i64) -> Option<chrono::NaiveDate> {
+        let utc = chrono::DateTime::from_timestamp_millis(timestamp_ms)?;
+        Some(match self {
+            HistoryTimezone::Local => utc.with_timezone(&chrono::Local).date_naive(),
+            HistoryTimezone::Utc => utc.date_naive(),
+            HistoryTimezone::Offset { minutes } => {
+                let offset = chrono::FixedOffset::east_opt(minutes.checked_mul(60)?)?;
+                utc.with_timezone(&offset).date_naive()
+            }
+        })
+    }
+}
+
+/// Wakeup counts for one calendar day.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DayBucket {
+    /// `YYYY-MM-DD` in the requested timezone.
+    pub date: String,
+    pub total: usize,
+    pub success: usize,
+    pub failure: usize,
+}
+
+fn bucket_by_day(
+    items: &[WakeupHistoryItem],
+    tz: HistoryTimezone,
+    days: usize,
+    now_ms: i64,
+) -> Vec<DayBucket> {
+    let Some(today) = tz.date_of(now_ms) else {
+        return Vec::new();
+    };
+    let dates: Vec<chrono::NaiveDate> = (0..days)
+        .rev()
+        .filter_map(|back| today.checked_sub_days(chrono::Days::new(back as u64)))
+        .collect();
+    let mut buckets: Vec<DayBucket> = dates
+        .iter()
+        .map(|date| DayBucket {
+            date: date.format("%Y-%m-%d").to_string(),
+            total: 0,
+            success: 0,
+            failure: 0,
+        })
+        .collect();
+    for item in items {
+        let Some(date) = tz.date_of(item.timestamp) else {
+            continue;
+        };
+        if let Ok(index) = dates.binary_search(&date) {
+            let bucket = &mut buckets[index];
+            bucket.total += 1;
+            if item.success {
+                bucket.success += 1;
+            } else {
+                bucket.failure += 1;
+            }
+        }
+    }
+    buckets
+}
+
+/// Per-day wakeup counts for the last `days` days (oldest first, today last), including
+/// days without any wakeups. Days are calendar dates in `tz`.
+pub fn history_by_day(tz: HistoryTimezone, days: usize) -> Result<Vec<DayBucket>, String> {
+    let items = load_history()?;
+    Ok(bucket_by_day(
+        &items,
+        tz,
+        days,
+        chrono::Utc::now().timestamp_millis(),
+    ))
 }
 
 pub fn clear_history() -> Result<(), String> {
@@ -102,3 +400,122 @@ pub fn clear_history() -> Result<(), String> {
     modules::logger::log_info("[CodexWakeup] History cleared");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(timestamp: i64, email: &str, success: bool) -> WakeupHistoryItem {
+        WakeupHistoryItem {
+            id: format!("{}-{}", timestamp, email),
+            timestamp,
+            trigger_type: "auto".to_string(),
+            trigger_source: TriggerSource::Scheduler,
+            task_name: None,
+            account_email: email.to_string(),
+            model_id: "codex-hourly".to_string(),
+            prompt: None,
+            success,
+            message: None,
+            duration: None,
+            reply_length: None,
+            reply_pattern: None,
+            reply_matched: None,
+            failure_kind: None,
+            replay_of: None,
+            slo_violation: false,
+            max_output_tokens: None,
+            exit_code: None,
+        }
+    }
+
+    #[test]
+    fn test_success_rate_filters_by_window_and_account() {
+        let items = vec![
+            item(1_000, "a@example.com", false),
+            item(5_000, "a@example.com", true),
+            item(6_000, "A@example.com", false),
+            item(7_000, "b@example.com", true),
+        ];
+        assert_eq!(success_rate_of(&items, None, 5_000), Some(2.0 / 3.0));
+        assert_eq!(success_rate_of(&items, Some("a@example.com"), 5_000), Some(0.5));
+        assert_eq!(success_rate_of(&items, Some("c@example.com"), 0), None);
+        assert_eq!(success_rate_of(&items, None, 10_000), None);
+    }
+
+    #[test]
+    fn test_dedup_by_id_keeps_newest_in_original_order() {
+        let mut stale = item(1_000, "a@example.com", false);
+        stale.id = "dup".to_string();
+        let mut newest = item(3_000, "a@example.com", true);
+        newest.id = "dup".to_string();
+        let items = vec![
+            stale,
+            item(2_000, "b@example.com", true),
+            newest,
+            item(4_000, "c@example.com", false),
+        ];
+
+        let (deduped, collapsed) = dedup_by_id(items);
+        assert_eq!(collapsed, 1);
+        assert_eq!(
+            deduped
+                .iter()
+                .map(|i| (i.id.as_str(), i.timestamp))
+                .collect::<Vec<_>>(),
+            [
+                ("2000-b@example.com", 2_000),
+                ("dup", 3_000),
+                ("4000-c@example.com", 4_000),
+            ]
+        );
+        assert!(deduped[1].success);
+
+        let (_, collapsed) = dedup_by_id(deduped);
+        assert_eq!(collapsed, 0);
+    }
+
+    #[test]
+    fn test_trigger_source_normalizes_legacy_strings() {
+        let parsed: Vec<TriggerSource> =
+            serde_json::from_str(r#"["Manual", "ui", "scheduled", "quota-reset", "Crontab", "custom"]"#)
+                .unwrap();
+        assert_eq!(
+            parsed,
+            vec![
+                TriggerSource::Manual,
+                TriggerSource::Manual,
+                TriggerSource::Scheduler,
+                TriggerSource::QuotaReset,
+                TriggerSource::Crontab,
+                TriggerSource::Other("custom".to_string()),
+            ]
+        );
+        assert_eq!(
+            serde_json::to_string(&parsed).unwrap(),
+            r#"["manual","manual","scheduled","quota_reset","crontab","custom"]"#
+        );
+    }
+
+    #[test]
+    fn test_bucket_by_day_respects_timezone_boundaries() {
+        // 2024-03-10T23:30:00Z, and noon UTC on the 11th.
+        let late_utc = 1_710_113_400_000;
+        let now = 1_710_158_400_000;
+        let items = vec![item(late_utc, "a@example.com", true), item(now, "a@example.com", false)];
+
+        let utc = bucket_by_day(&items, HistoryTimezone::Utc, 3, now);
+        assert_eq!(
+            utc.iter().map(|b| b.date.as_str()).collect::<Vec<_>>(),
+            ["2024-03-09", "2024-03-10", "2024-03-11"]
+        );
+        assert_eq!((utc[1].total, utc[1].success), (1, 1));
+        assert_eq!((utc[2].total, utc[2].failure), (1, 1));
+        assert_eq!(utc[0].total, 0);
+
+        // At UTC+1 the late wakeup falls after midnight, on the 11th.
+        let plus_one = bucket_by_day(&items, HistoryTimezone::Offset { minutes: 60 }, 2, now);
+        assert_eq!(plus_one[0].total, 0);
+        assert_eq!((plus_one[1].date.as_str(), plus_one[1].total), ("2024-03-11", 2));
+    }
+}
@@ -0,0 +1,389 @@
+//! CSV import/export for Codex wakeup history.
+//!
+//! Column names are matched case-insensitively and ignore `_`/spaces, so
+//! `account_email`, `accountEmail` and `Account Email` are equivalent.
+
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::modules::codex_wakeup_history::{self, TriggerSource, WakeupHistoryItem};
+use crate::modules::logger;
+
+const EXPORT_COLUMNS: [&str; 12] = [
+    "id",
+    "timestamp",
+    "timestamp_iso",
+    "trigger_type",
+    "trigger_source",
+    "task_name",
+    "account_email",
+    "model_id",
+    "prompt",
+    "success",
+    "message",
+    "duration",
+];
+const REQUIRED_IMPORT_COLUMNS: [&str; 6] = [
+    "timestamp",
+    "account_email",
+    "model_id",
+    "success",
+    "message",
+    "duration",
+];
+const MAX_REPORTED_ERRORS: usize = 50;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryCsvImportReport {
+    pub imported: usize,
+    pub skipped: usize,
+    pub errors: Vec<String>,
+}
+
+fn normalize_column(name: &str) -> String {
+    name.trim()
+        .trim_start_matches('\u{feff}')
+        .chars()
+        .filter(|c| *c != '_' && !c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+fn escape_field(value: &str) -> String {
+    if value.contains(',') || value.contains('"') || value.contains('\n') || value.contains('\r') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Splits CSV content into records (RFC 4180 quoting), tagging each with its starting line.
+fn parse_records(content: &str) -> Result<Vec<(usize, Vec<String>)>, String> {
+    let mut records = Vec::new();
+    let mut record: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+            }
+            continue;
+        }
+        match c {
+            '"' => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' => {
+                record.push(std::mem::take(&mut field));
+                if !(record.len() == 1 && record[0].trim().is_empty()) {
+                    records.push((record_line, std::mem::take(&mut record)));
+                }
+                record.clear();
+                line += 1;
+                record_line = line;
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(format!("Unterminated quoted field starting on line {}", record_line));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((record_line, record));
+    }
+    Ok(records)
+}
+
+fn parse_success(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "1" | "yes" | "y" | "success" | "ok" => Some(true),
+        "false" | "0" | "no" | "n" | "failed" | "failure" | "error" => Some(false),
+        _ => None,
+    }
+}
+
+/// Accepts epoch milliseconds, epoch seconds, or an RFC 3339 timestamp.
+fn parse_timestamp(value: &str) -> Option<i64> {
+    let value = value.trim();
+    if let Ok(number) = value.parse::<i64>() {
+        // Anything below ~2001-09 in milliseconds is treated as seconds.
+        return Some(if number.abs() < 1_000_000_000_000 { number * 1000 } else { number });
+    }
+    chrono::DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|dt| dt.timestamp_millis())
+}
+
+fn optional_field(value: Option<&String>) -> Option<String> {
+    value
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .map(|v| v.to_string())
+}
+
+fn dedup_key(item: &WakeupHistoryItem) -> (i64, String, String) {
+    (
+        item.timestamp,
+        item.account_email.trim().to_lowercase(),
+        item.model_id.clone(),
+    )
+}
+
+fn format_timestamp_iso(timestamp_ms: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(timestamp_ms)
+        .map(|dt| dt.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .unwrap_or_default()
+}
+
+fn history_to_csv(items: &[WakeupHistoryItem]) -> String {
+    let mut out = EXPORT_COLUMNS.join(",");
+    out.push_str("\r\n");
+    for item in items {
+        let row = [
+            item.id.clone(),
+            item.timestamp.to_string(),
+            format_timestamp_iso(item.timestamp),
+            item.trigger_type.clone(),
+            item.trigger_source.to_string(),
+            item.task_name.clone().unwrap_or_default(),
+            item.account_email.clone(),
+            item.model_id.clone(),
+            item.prompt.clone().unwrap_or_default(),
+            item.success.to_string(),
+            item.message.clone().unwrap_or_default(),
+            item.duration.map(|d| d.to_string()).unwrap_or_default(),
+        ];
+        out.push_str(
+            &row.iter()
+                .map(|value| escape_field(value))
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// Parses CSV content into history items, collecting per-row errors instead of failing.
+fn parse_history_csv(content: &str) -> Result<(Vec<WakeupHistoryItem>, Vec<String>), String> {
+    let mut records = parse_records(content)?.into_iter();
+    let Some((_, header)) = records.next() else {
+        return Err("CSV file is empty".to_string());
+    };
+
+    let columns: Vec<String> = header.iter().map(|name| normalize_column(name)).collect();
+    let missing: Vec<&str> = REQUIRED_IMPORT_COLUMNS
+        .iter()
+        .filter(|name| !columns.contains(&normalize_column(name)))
+        .copied()
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("CSV header is missing columns: {}", missing.join(", ")));
+    }
+    let index_of = |name: &str| columns.iter().position(|c| *c == normalize_column(name));
+
+    let mut items = Vec::new();
+    let mut errors = Vec::new();
+    for (line, row) in records {
+        let get = |name: &str| index_of(name).and_then(|idx| row.get(idx));
+        let row_result = (|| -> Result<WakeupHistoryItem, String> {
+            if row.len() != columns.len() {
+                return Err(format!("expected {} fields, found {}", columns.len(), row.len()));
+            }
+            let timestamp_raw = get("timestamp").map(|v| v.as_str()).unwrap_or_default();
+            let timestamp = parse_timestamp(timestamp_raw)
+                .ok_or_else(|| format!("invalid timestamp {:?}", timestamp_raw))?;
+            let account_email = optional_field(get("account_email"))
+                .ok_or_else(|| "account_email is empty".to_string())?;
+            let model_id =
+                optional_field(get("model_id")).ok_or_else(|| "model_id is empty".to_string())?;
+            let success_raw = get("success").map(|v| v.as_str()).unwrap_or_default();
+            let success = parse_success(success_raw)
+                .ok_or_else(|| format!("invalid success value {:?}", success_raw))?;
+            let duration = match optional_field(get("duration")) {
+                Some(value) => Some(
+                    value
+                        .parse::<u64>()
+                        .map_err(|_| format!("invalid duration {:?}", value))?,
+                ),
+                None => None,
+            };
+
+            Ok(WakeupHistoryItem {
+                id: format!("csv-{}-{}", timestamp, line),
+                timestamp,
+                trigger_type: optional_field(get("trigger_type"))
+                    .unwrap_or_else(|| "manual".to_string()),
+                trigger_source: optional_field(get("trigger_source"))
+                    .map(TriggerSource::from)
+                    .unwrap_or(TriggerSource::CsvImport),
+                task_name: optional_field(get("task_name")),
+                account_email,
+                model_id,
+                prompt: optional_field(get("prompt")),
+                success,
+                message: get("message").filter(|v| !v.is_empty()).cloned(),
+                duration,
+                reply_length: None,
+                reply_pattern: None,
+                reply_matched: None,
+                failure_kind: None,
+                replay_of: None,
+                slo_violation: false,
+                max_output_tokens: None,
+                exit_code: None,
+            })
+        })();
+        match row_result {
+            Ok(item) => items.push(item),
+            Err(err) => errors.push(format!("line {}: {}", line, err)),
+        }
+    }
+    Ok((items, errors))
+}
+
+pub fn export_history_csv(path: &Path) -> Result<usize, String> {
+    let items = codex_wakeup_history::load_history()?;
+    fs::write(path, history_to_csv(&items))
+        .map_err(|e| format!("Failed to write history CSV: {}", e))?;
+    logger::log_info(&format!(
+        "[CodexWakeup] History exported to CSV: path={}, rows={}",
+        path.display(),
+        items.len()
+    ));
+    Ok(items.len())
+}
+
+pub fn import_history_csv(path: &Path) -> Result<HistoryCsvImportReport, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read history CSV: {}", e))?;
+    let (parsed, mut errors) = parse_history_csv(&content)?;
+    let parsed_count = parsed.len();
+
+    let mut seen: HashSet<(i64, String, String)> = codex_wakeup_history::load_history()?
+        .iter()
+        .map(dedup_key)
+        .collect();
+    let mut fresh = Vec::new();
+    let mut duplicates = 0;
+    for item in parsed {
+        if seen.insert(dedup_key(&item)) {
+            fresh.push(item);
+        } else {
+            duplicates += 1;
+        }
+    }
+
+    let imported = codex_wakeup_history::merge_history_items(fresh)?;
+    let skipped = errors.len() + (parsed_count - imported);
+    logger::log_info(&format!(
+        "[CodexWakeup] History CSV imported: imported={}, skipped={}, duplicates={}, errors={}",
+        imported,
+        skipped,
+        duplicates,
+        errors.len()
+    ));
+    errors.truncate(MAX_REPORTED_ERRORS);
+    Ok(HistoryCsvImportReport {
+        imported,
+        skipped,
+        errors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_item() -> WakeupHistoryItem {
+        WakeupHistoryItem {
+            id: "1700000000000-0".to_string(),
+            timestamp: 1_700_000_000_000,
+            trigger_type: "auto".to_string(),
+            trigger_source: TriggerSource::Scheduler,
+            task_name: Some("Morning, \"daily\"".to_string()),
+            account_email: "user@example.com".to_string(),
+            model_id: "codex-hourly".to_string(),
+            prompt: Some("Reply with exactly: OK".to_string()),
+            success: true,
+            message: Some("line one, with comma\nline \"two\"\r\nline three".to_string()),
+            duration: Some(1234),
+            reply_length: None,
+            reply_pattern: None,
+            reply_matched: None,
+            failure_kind: None,
+            replay_of: None,
+            slo_violation: false,
+            max_output_tokens: None,
+            exit_code: None,
+        }
+    }
+
+    #[test]
+    fn test_export_writes_epoch_and_iso_timestamps() {
+        let csv = history_to_csv(&[sample_item()]);
+        let records = parse_records(&csv).unwrap();
+        assert_eq!(records[0].1, EXPORT_COLUMNS.to_vec());
+        assert_eq!(records[1].1[1], "1700000000000");
+        assert_eq!(records[1].1[2], "2023-11-14T22:13:20.000Z");
+    }
+
+    #[test]
+    fn test_csv_round_trip_with_embedded_delimiters() {
+        let original = sample_item();
+        let csv = history_to_csv(&[original.clone()]);
+        let (items, errors) = parse_history_csv(&csv).unwrap();
+
+        assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+        assert_eq!(items.len(), 1);
+        let item = &items[0];
+        assert_eq!(item.timestamp, original.timestamp);
+        assert_eq!(item.trigger_type, original.trigger_type);
+        assert_eq!(item.trigger_source, original.trigger_source);
+        assert_eq!(item.task_name, original.task_name);
+        assert_eq!(item.account_email, original.account_email);
+        assert_eq!(item.model_id, original.model_id);
+        assert_eq!(item.prompt, original.prompt);
+        assert_eq!(item.success, original.success);
+        assert_eq!(item.message, original.message);
+        assert_eq!(item.duration, original.duration);
+    }
+
+    #[test]
+    fn test_import_reports_malformed_rows() {
+        let csv = "timestamp,account_email,model_id,success,message,duration\n\
+                   1700000000000,a@example.com,codex-hourly,true,ok,10\n\
+                   not-a-time,b@example.com,codex-hourly,true,ok,10\n\
+                   1700000000000,c@example.com,codex-weekly,maybe,ok,10\n";
+        let (items, errors) = parse_history_csv(csv).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("line 3:"));
+    }
+
+    #[test]
+    fn test_import_rejects_missing_header_columns() {
+        let err = parse_history_csv("timestamp,account_email\n1,a@example.com\n").unwrap_err();
+        assert!(err.contains("model_id"));
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Mutex, OnceLock};
 use std::time::Duration;
 
@@ -8,13 +9,17 @@ use tauri::{AppHandle, Emitter};
 use tokio::time::sleep;
 
 use crate::modules;
+use crate::modules::codex_wakeup_history::TriggerSource;
 
 const DEFAULT_PROMPT: &str = "hi";
 const RESET_TRIGGER_COOLDOWN_MS: i64 = 10 * 60 * 1000;
 const RESET_SAFETY_MARGIN_MS: i64 = 2 * 60 * 1000;
 const RESET_QUOTA_REFRESH_INTERVAL_MS: i64 = 2 * 60 * 1000;
+/// Spacing between tasks released after the master switch is turned back on.
+const RESUME_STAGGER_MS: i64 = 60 * 1000;
+const RESUME_JITTER_MS: i64 = 15 * 1000;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct WakeupTaskInput {
     pub id: String,
@@ -25,7 +30,7 @@ pub struct WakeupTaskInput {
     pub schedule: ScheduleConfig,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ScheduleConfig {
     pub repeat_mode: String,
@@ -45,6 +50,7 @@ pub struct ScheduleConfig {
     pub time_window_start: Option<String>,
     pub time_window_end: Option<String>,
     pub fallback_times: Option<Vec<String>>,
+    pub expect_reply_regex: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -56,6 +62,8 @@ struct WakeupTask {
     created_at: i64,
     last_run_at: Option<i64>,
     schedule: ScheduleConfigNormalized,
+    /// Bumped whenever the entry is replaced; a snapshot with an older revision must not fire.
+    revision: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -77,6 +85,7 @@ struct ScheduleConfigNormalized {
     time_window_start: Option<String>,
     time_window_end: Option<String>,
     fallback_times: Vec<String>,
+    expect_reply_regex: Option<String>,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -97,10 +106,57 @@ struct SchedulerState {
     last_executed_at: HashMap<String, i64>,
     /// In quota-reset mode, tracks last proactive quota refresh time.
     last_reset_quota_refresh_at: HashMap<String, i64>,
+    /// After a resume, tasks stay held until their staggered release time.
+    resume_not_before: HashMap<String, i64>,
+    /// Consecutive failed wakeups per account id; reset on the next success.
+    consecutive_failures: HashMap<String, u32>,
+    /// Task definitions as last synced from the frontend.
+    synced_inputs: HashMap<String, WakeupTaskInput>,
+    /// Definitions replaced by `update_schedule_entry`; a later sync still carrying one of
+    /// these is a stale frontend snapshot and must not undo the backend edit.
+    superseded_inputs: HashMap<String, WakeupTaskInput>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchedulerNextFire {
+    pub task_id: String,
+    pub task_name: String,
+    pub at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchedulerStatus {
+    /// Persisted master switch.
+    pub enabled: bool,
+    /// Enablement last synced from the frontend.
+    pub sync_enabled: bool,
+    pub task_count: usize,
+    pub running_task_count: usize,
+    /// Earliest upcoming time-based fire; quota-reset tasks have no fixed time.
+    pub next_fire: Option<SchedulerNextFire>,
 }
 
 static STATE: OnceLock<Mutex<SchedulerState>> = OnceLock::new();
 static STARTED: OnceLock<Mutex<bool>> = OnceLock::new();
+static TASK_REVISION: AtomicU64 = AtomicU64::new(0);
+
+fn next_revision() -> u64 {
+    TASK_REVISION.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+fn task_from_input(task: WakeupTaskInput) -> WakeupTask {
+    WakeupTask {
+        id: task.id,
+        name: task.name,
+        enabled: task.enabled,
+        created_at: task.created_at,
+        last_run_at: task.last_run_at,
+        schedule: normalize_schedule(task.schedule),
+        revision: next_revision(),
+    }
+}
 
 fn state() -> &'static Mutex<SchedulerState> {
     STATE.get_or_init(|| Mutex::new(SchedulerState::default()))
@@ -150,30 +206,109 @@ fn normalize_schedule(raw: ScheduleConfig) -> ScheduleConfigNormalized {
         time_window_start: raw.time_window_start,
         time_window_end: raw.time_window_end,
         fallback_times,
+        expect_reply_regex: raw
+            .expect_reply_regex
+            .filter(|pattern| !pattern.trim().is_empty()),
     }
 }
 
+/// Same task definition, ignoring `last_run_at` (which the frontend updates after each run).
+fn same_definition(a: &WakeupTaskInput, b: &WakeupTaskInput) -> bool {
+    a.id == b.id
+        && a.name == b.name
+        && a.enabled == b.enabled
+        && a.created_at == b.created_at
+        && a.schedule == b.schedule
+}
+
 pub fn sync_state(enabled: bool, tasks: Vec<WakeupTaskInput>) {
     let mut guard = state().lock().expect("codex wakeup state lock");
     guard.enabled = enabled;
     let task_count = tasks.len();
-    guard.tasks = tasks
-        .into_iter()
-        .map(|task| WakeupTask {
-            id: task.id,
-            name: task.name,
-            enabled: task.enabled,
-            created_at: task.created_at,
-            last_run_at: task.last_run_at,
-            schedule: normalize_schedule(task.schedule),
-        })
-        .collect();
+    let previous = std::mem::take(&mut guard.tasks);
+    let mut next_tasks = Vec::with_capacity(task_count);
+    let mut synced_inputs = HashMap::new();
+    for input in tasks {
+        let stale = guard
+            .superseded_inputs
+            .get(&input.id)
+            .is_some_and(|superseded| same_definition(superseded, &input));
+        let kept = stale
+            .then(|| previous.iter().find(|task| task.id == input.id).cloned())
+            .flatten();
+        match kept {
+            Some(task) => {
+                modules::logger::log_info(&format!(
+                    "[CodexWakeup] Ignoring stale synced definition for updated task: task={}",
+                    task.name
+                ));
+                next_tasks.push(task);
+            }
+            None => {
+                guard.superseded_inputs.remove(&input.id);
+                next_tasks.push(task_from_input(input.clone()));
+            }
+        }
+        synced_inputs.insert(input.id.clone(), input);
+    }
+    guard
+        .superseded_inputs
+        .retain(|id, _| synced_inputs.contains_key(id));
+    guard.tasks = next_tasks;
+    guard.synced_inputs = synced_inputs;
     modules::logger::log_info(&format!(
         "[CodexWakeup] Scheduler state synced: enabled={}, tasks={}",
         enabled, task_count
     ));
 }
 
+/// Replaces one task in place and restarts its schedule from now, all under the state lock.
+/// Any in-flight snapshot of the old entry is rejected when it tries to start (revision
+/// check in `run_task_with_models`), and a later `sync_state` still carrying the old
+/// definition keeps this edit. Returns the entry's next fire time in ms, if timed.
+pub fn update_schedule_entry(id: &str, new_entry: WakeupTaskInput) -> Result<Option<i64>, String> {
+    let mut task = task_from_input(WakeupTaskInput {
+        id: id.to_string(),
+        ..new_entry
+    });
+    let now = Local::now();
+    let next_fire = if !task.enabled || task.schedule.wake_on_reset {
+        None
+    } else {
+        next_fire_time(&task, now).map(|at| at.timestamp_millis())
+    };
+
+    let mut guard = state().lock().expect("codex wakeup state lock");
+    let slot = guard
+        .tasks
+        .iter_mut()
+        .find(|existing| existing.id == id)
+        .ok_or_else(|| format!("Wakeup task not found: {}", id))?;
+    task.last_run_at = slot.last_run_at;
+    let name = task.name.clone();
+    *slot = task;
+    if let Some(replaced) = guard.synced_inputs.get(id).cloned() {
+        guard.superseded_inputs.insert(id.to_string(), replaced);
+    }
+    guard.last_executed_at.insert(id.to_string(), now.timestamp_millis());
+    guard.resume_not_before.remove(id);
+    drop(guard);
+
+    modules::logger::log_info(&format!(
+        "[CodexWakeup] Task updated: task={}, next_fire={:?}",
+        name, next_fire
+    ));
+    Ok(next_fire)
+}
+
+fn next_fire_time(task: &WakeupTask, after: DateTime<Local>) -> Option<DateTime<Local>> {
+    if let Some(expr) = &task.schedule.crontab {
+        next_crontab_time(expr, after)
+    } else {
+        next_run_time(&task.schedule, after)
+    }
+}
+
 pub fn ensure_started(app: AppHandle) {
     let mut started = started_flag().lock().expect("codex wakeup started lock");
     if *started {
@@ -189,6 +324,71 @@ pub fn ensure_started(app: AppHandle) {
     });
 }
 
+/// Persists the master switch; on resume, due tasks are released one by one with jitter.
+pub fn set_scheduler_enabled(enabled: bool) -> Result<(), String> {
+    let mut was_enabled = false;
+    modules::codex_wakeup_settings::update_settings(|settings| {
+        was_enabled = settings.scheduler_enabled;
+        settings.scheduler_enabled = enabled;
+    })?;
+
+    if enabled && !was_enabled {
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut guard = state().lock().expect("codex wakeup state lock");
+        let task_ids: Vec<String> = guard.tasks.iter().map(|task| task.id.clone()).collect();
+        guard.resume_not_before.clear();
+        for (index, task_id) in task_ids.into_iter().enumerate() {
+            let jitter = rand::random::<u64>() % (RESUME_JITTER_MS as u64);
+            let not_before = now + index as i64 * RESUME_STAGGER_MS + jitter as i64;
+            guard.resume_not_before.insert(task_id, not_before);
+        }
+    }
+
+    modules::logger::log_info(&format!(
+        "[CodexWakeup] Scheduler master switch set: enabled={}",
+        enabled
+    ));
+    Ok(())
+}
+
+/// Current streak of failed scheduled wakeups for `account_id` (0 after a success).
+pub fn consecutive_failures(account_id: &str) -> u32 {
+    state()
+        .lock()
+        .map(|guard| guard.consecutive_failures.get(account_id).copied().unwrap_or(0))
+        .unwrap_or(0)
+}
+
+pub fn scheduler_status() -> SchedulerStatus {
+    let snapshot = {
+        let guard = state().lock().expect("codex wakeup state lock");
+        guard.clone()
+    };
+    let now = Local::now();
+
+    let next_fire = snapshot
+        .tasks
+        .iter()
+        .filter(|task| task.enabled && !task.schedule.wake_on_reset)
+        .filter_map(|task| {
+            let at = next_fire_time(task, now)?;
+            Some(SchedulerNextFire {
+                task_id: task.id.clone(),
+                task_name: task.name.clone(),
+                at: at.timestamp_millis(),
+            })
+        })
+        .min_by_key(|fire| fire.at);
+
+    SchedulerStatus {
+        enabled: modules::codex_wakeup_settings::get_settings().scheduler_enabled,
+        sync_enabled: snapshot.enabled,
+        task_count: snapshot.tasks.len(),
+        running_task_count: snapshot.running_tasks.len(),
+        next_fire,
+    }
+}
+
 fn parse_time_to_minutes(value: &str) -> Option<i32> {
     let parts: Vec<&str> = value.trim().split(':').collect();
     if parts.len() != 2 {
@@ -445,7 +645,7 @@ async fn run_scheduler_once(app: &AppHandle) {
         guard.clone()
     };
 
-    if !snapshot.enabled {
+    if !snapshot.enabled || !modules::codex_wakeup_settings::get_settings().scheduler_enabled {
         return;
     }
 
@@ -458,6 +658,11 @@ async fn run_scheduler_once(app: &AppHandle) {
         if snapshot.running_tasks.contains(&task.id) {
             continue;
         }
+        if let Some(not_before) = snapshot.resume_not_before.get(&task.id) {
+            if now.timestamp_millis() < *not_before {
+                continue;
+            }
+        }
 
         if task.schedule.wake_on_reset {
             handle_quota_reset_task(app, task, now).await;
@@ -471,21 +676,17 @@ async fn run_scheduler_once(app: &AppHandle) {
             .and_then(|ts| Local.timestamp_millis_opt(ts).single())
             .unwrap_or_else(|| now - chrono::Duration::minutes(1));
 
-        let next_run = if let Some(expr) = &task.schedule.crontab {
-            next_crontab_time(expr, after)
-        } else {
-            next_run_time(&task.schedule, after)
-        };
+        let next_run = next_fire_time(task, after);
 
         // Trigger only once schedule time has been reached (no early trigger).
         if let Some(next_run) = next_run {
             if next_run <= now {
                 let trigger_source = if task.schedule.crontab.is_some() {
-                    "crontab"
+                    TriggerSource::Crontab
                 } else {
-                    "scheduled"
+                    TriggerSource::Scheduler
                 };
-                run_task(app, task, trigger_source).await;
+                run_task(app, task, &trigger_source).await;
             }
         }
     }
@@ -504,7 +705,7 @@ async fn handle_quota_reset_task(app: &AppHandle, task: &WakeupTask, now: DateTi
             }
         }
         if should_run_fallback {
-            run_task(app, task, "scheduled").await;
+            run_task(app, task, &TriggerSource::Scheduler).await;
         }
         return;
     }
@@ -515,7 +716,7 @@ async fn handle_quota_reset_task(app: &AppHandle, task: &WakeupTask, now: DateTi
         .schedule
         .selected_accounts
         .iter()
-        .filter_map(|email| accounts.iter().find(|acc| acc.email.eq_ignore_ascii_case(email)))
+        .filter_map(|email| accounts.iter().find(|acc| acc.matches_label(email)))
         .collect();
 
     if selected_accounts.is_empty() {
@@ -524,7 +725,7 @@ async fn handle_quota_reset_task(app: &AppHandle, task: &WakeupTask, now: DateTi
 
     if should_refresh_reset_quota(&task.id) {
         for account in &selected_accounts {
-            let _ = modules::codex_quota::refresh_account_quota(&account.id).await;
+            let _ = modules::codex_quota::refresh_account_quota(&account.id, 0).await;
         }
     }
 
@@ -536,7 +737,7 @@ async fn handle_quota_reset_task(app: &AppHandle, task: &WakeupTask, now: DateTi
         .filter_map(|email| {
             fresh_accounts
                 .iter()
-                .find(|acc| acc.email.eq_ignore_ascii_case(email))
+                .find(|acc| acc.matches_label(email))
         })
         .collect();
     if selected_accounts.is_empty() {
@@ -565,15 +766,20 @@ async fn handle_quota_reset_task(app: &AppHandle, task: &WakeupTask, now: DateTi
     };
 
     if !models_to_trigger.is_empty() {
-        run_task_with_models(app, task, "quota_reset", models_to_trigger.into_iter().collect()).await;
+        run_task_with_models(app, task, &TriggerSource::QuotaReset, models_to_trigger.into_iter().collect()).await;
     }
 }
 
-async fn run_task(app: &AppHandle, task: &WakeupTask, trigger_source: &str) {
+async fn run_task(app: &AppHandle, task: &WakeupTask, trigger_source: &TriggerSource) {
     run_task_with_models(app, task, trigger_source, task.schedule.selected_models.clone()).await;
 }
 
-async fn run_task_with_models(app: &AppHandle, task: &WakeupTask, trigger_source: &str, models: Vec<String>) {
+async fn run_task_with_models(
+    app: &AppHandle,
+    task: &WakeupTask,
+    trigger_source: &TriggerSource,
+    models: Vec<String>,
+) {
     if models.is_empty() {
         modules::logger::log_warn(&format!(
             "[CodexWakeup] Skipping task (no window selected): task={}, trigger={}",
@@ -588,7 +794,7 @@ async fn run_task_with_models(app: &AppHandle, task: &WakeupTask, trigger_source
         .schedule
         .selected_accounts
         .iter()
-        .filter_map(|email| accounts.iter().find(|acc| acc.email.eq_ignore_ascii_case(email)))
+        .filter_map(|email| accounts.iter().find(|acc| acc.matches_label(email)))
         .collect();
 
     if selected_accounts.is_empty() {
@@ -601,6 +807,18 @@ async fn run_task_with_models(app: &AppHandle, task: &WakeupTask, trigger_source
 
     {
         let mut guard = state().lock().expect("codex wakeup state lock");
+        let is_current = guard
+            .tasks
+            .iter()
+            .any(|current| current.id == task.id && current.revision == task.revision && current.enabled);
+        if !is_current {
+            drop(guard);
+            modules::logger::log_info(&format!(
+                "[CodexWakeup] Skipping stale task snapshot (entry was updated): task={}, trigger={}",
+                task.name, trigger_source
+            ));
+            return;
+        }
         guard.running_tasks.insert(task.id.clone());
     }
 
@@ -611,6 +829,16 @@ async fn run_task_with_models(app: &AppHandle, task: &WakeupTask, trigger_source
         .and_then(|p| if p.trim().is_empty() { None } else { Some(p.trim().to_string()) })
         .unwrap_or_else(|| DEFAULT_PROMPT.to_string());
     let max_tokens = normalize_max_tokens(task.schedule.max_output_tokens);
+    let wakeup_options = modules::codex_wakeup::WakeupOptions {
+        expect_reply_regex: task.schedule.expect_reply_regex.clone(),
+        history: modules::codex_wakeup::WakeupHistoryContext {
+            trigger_type: "auto".to_string(),
+            trigger_source: trigger_source.clone(),
+            task_name: Some(task.name.clone()),
+            replay_of: None,
+        },
+        ..Default::default()
+    };
     modules::logger::log_info(&format!(
         "[CodexWakeup] Task execution started: task={}, trigger={}, accounts={}, windows={}",
         task.name,
@@ -622,27 +850,19 @@ async fn run_task_with_models(app: &AppHandle, task: &WakeupTask, trigger_source
     let mut history: Vec<modules::codex_wakeup_history::WakeupHistoryItem> = Vec::new();
     for account in &selected_accounts {
         for model in &models {
-            let started = chrono::Utc::now();
-            let result =
-                modules::codex_wakeup::trigger_wakeup(&account.id, model, &prompt, max_tokens).await;
-            let duration = chrono::Utc::now().signed_duration_since(started).num_milliseconds().max(0) as u64;
-            let (success, message) = match result {
-                Ok(resp) => (true, Some(resp.reply)),
-                Err(err) => (false, Some(err.to_string())),
-            };
-            history.push(modules::codex_wakeup_history::WakeupHistoryItem {
-                id: format!("{}-{}", chrono::Utc::now().timestamp_millis(), history.len()),
-                timestamp: chrono::Utc::now().timestamp_millis(),
-                trigger_type: "auto".to_string(),
-                trigger_source: trigger_source.to_string(),
-                task_name: Some(task.name.clone()),
-                account_email: account.email.clone(),
-                model_id: model.clone(),
-                prompt: Some(prompt.clone()),
-                success,
-                message,
-                duration: Some(duration),
-            });
+            let (result, item) = modules::codex_wakeup::trigger_wakeup_recorded(
+                &account.id,
+                model,
+                &prompt,
+                max_tokens,
+                &wakeup_options,
+            )
+            .await;
+            // Skipped runs never reached the CLI and say nothing about the account's health.
+            if !result.as_ref().is_ok_and(|resp| resp.cli_skipped()) {
+                track_consecutive_failures(app, account, item.success, item.message.as_deref());
+            }
+            history.push(item);
         }
     }
 
@@ -660,11 +880,6 @@ async fn run_task_with_models(app: &AppHandle, task: &WakeupTask, trigger_source
         guard.last_executed_at.insert(task.id.clone(), executed_at);
     }
 
-    // Persist execution history.
-    if let Err(e) = modules::codex_wakeup_history::add_history_items(history.clone()) {
-        modules::logger::log_error(&format!("Failed to persist Codex wakeup history: {}", e));
-    }
-
     let history_count = history.len();
     let payload = WakeupTaskResultPayload {
         task_id: task.id.clone(),
@@ -688,4 +903,49 @@ struct WakeupTaskResultPayload {
     records: Vec<modules::codex_wakeup_history::WakeupHistoryItem>,
 }
 
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountFailingPayload {
+    account_id: String,
+    account_email: String,
+    consecutive_failures: u32,
+    last_error: Option<String>,
+}
+
+/// Counts consecutive failures per account and emits `codex-wakeup://account-failing`
+/// once when the configured threshold is crossed; a success resets the streak.
+fn track_consecutive_failures(
+    app: &AppHandle,
+    account: &crate::models::codex::CodexAccount,
+    success: bool,
+    message: Option<&str>,
+) {
+    let threshold = modules::codex_wakeup_settings::get_settings().failure_alert_threshold;
+    let streak = {
+        let mut guard = state().lock().expect("codex wakeup state lock");
+        if success {
+            guard.consecutive_failures.remove(&account.id);
+            return;
+        }
+        let count = guard.consecutive_failures.entry(account.id.clone()).or_insert(0);
+        *count += 1;
+        *count
+    };
+    if threshold == 0 || streak != threshold {
+        return;
+    }
+
+    modules::logger::log_warn(&format!(
+        "[CodexWakeup] Account failing repeatedly ({} consecutive failures): email={}",
+        streak, account.display_label()
+    ));
+    let payload = AccountFailingPayload {
+        account_id: account.id.clone(),
+        account_email: account.display_label().to_string(),
+        consecutive_failures: streak,
+        last_error: message.map(str::to_string),
+    };
+    let _ = app.emit("codex-wakeup://account-failing", payload);
+}
+
 // (no local helpers)
@@ -0,0 +1,453 @@
+//! Codex wakeup runtime settings (also covers the Codex HTTP client).
+//!
+//! File path: <data_dir>/codex_wakeup_settings.json
+//!
+//! Every field carries a serde default so older files keep loading as new
+//! options are introduced.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock, RwLock};
+
+use crate::modules;
+
+const SETTINGS_FILE: &str = "codex_wakeup_settings.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodexWakeupSettings {
+    /// Max characters of a history `message` kept on disk (0 disables truncation).
+    #[serde(default = "default_max_stored_message_chars")]
+    pub max_stored_message_chars: usize,
+    /// Case-insensitive prefixes of CLI stdout summary lines that are never taken as the reply.
+    #[serde(default = "default_ignored_stdout_prefixes")]
+    pub ignored_stdout_prefixes: Vec<String>,
+    /// Minimum gap between any two CLI launches across all accounts (0 disables the gate).
+    #[serde(default)]
+    pub global_min_interval_ms: u64,
+    /// How long a gated wakeup may wait for its slot before failing (0 fails immediately).
+    #[serde(default = "default_global_rate_max_wait_ms")]
+    pub global_rate_max_wait_ms: u64,
+    /// Environment the Codex CLI child process starts from.
+    #[serde(default)]
+    pub cli_env_isolation: CliEnvIsolation,
+    /// TTL of the opt-in reply cache keyed by (account, prompt, window); 0 disables it.
+    #[serde(default)]
+    pub reply_cache_ttl_ms: u64,
+    /// Model ids reported by `list_cli_models` when the CLI cannot enumerate them.
+    #[serde(default = "default_fallback_cli_models")]
+    pub fallback_cli_models: Vec<String>,
+    /// Master switch above per-task enablement; `false` pauses every scheduled wakeup.
+    #[serde(default = "default_scheduler_enabled")]
+    pub scheduler_enabled: bool,
+    /// Order of the 5h/weekly windows in a combined wakeup reply.
+    #[serde(default)]
+    pub reply_window_order: ReplyWindowOrder,
+    /// When the post-wakeup quota refresh fails, show the pre-wakeup quota (flagged as stale).
+    #[serde(default)]
+    pub show_stale_quota_in_reply: bool,
+    /// Timezone for reset times in wakeup replies: IANA name (`Asia/Shanghai`), fixed offset
+    /// (`+08:00`) or `None` for local time. Unknown values fall back to local time.
+    #[serde(default)]
+    pub reply_timezone: Option<String>,
+    /// strftime format for reset times in wakeup replies; `None` uses `%m-%d %H:%M`.
+    #[serde(default)]
+    pub reply_time_format: Option<String>,
+    /// `User-Agent` for Codex quota/auth requests; `None` uses `cockpit-tools/<version>`.
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    /// Connect timeout for Codex quota/auth requests.
+    #[serde(default = "default_http_connect_timeout_secs")]
+    pub http_connect_timeout_secs: u64,
+    /// Whole-request timeout for Codex quota/auth requests.
+    #[serde(default = "default_http_request_timeout_secs")]
+    pub http_request_timeout_secs: u64,
+    /// Accounts refreshed at once by a bulk quota refresh.
+    #[serde(default = "default_quota_refresh_concurrency")]
+    pub quota_refresh_concurrency: usize,
+    /// Attempts per quota request when the usage endpoint answers 429 or 5xx.
+    #[serde(default = "default_quota_retry_attempts")]
+    pub quota_retry_attempts: u32,
+    /// Quota snapshots kept per account in the quota history.
+    #[serde(default = "default_quota_history_max_samples")]
+    pub quota_history_max_samples: usize,
+    /// Remaining percentage at or below which a window raises a warning alert (0 disables).
+    #[serde(default = "default_quota_alert_warn_percent")]
+    pub quota_alert_warn_percent: i32,
+    /// Remaining percentage at or below which a window raises a critical alert (0 disables).
+    #[serde(default = "default_quota_alert_critical_percent")]
+    pub quota_alert_critical_percent: i32,
+    /// Override for the Codex usage endpoint (absolute https URL); `CODEX_USAGE_URL` wins over it.
+    #[serde(default)]
+    pub usage_url: Option<String>,
+    /// Wakeups allowed to run at once across different accounts (same account always serializes).
+    #[serde(default = "default_max_concurrent_wakeups")]
+    pub max_concurrent_wakeups: usize,
+    /// How a quota response missing one of the 5h/weekly windows is recorded.
+    #[serde(default)]
+    pub partial_quota_mode: PartialQuotaMode,
+    /// Consecutive scheduled failures per account before an "account failing" event (0 disables).
+    #[serde(default = "default_failure_alert_threshold")]
+    pub failure_alert_threshold: u32,
+    /// Value passed to the CLI's `--color`; replies are parsed with ANSI codes stripped.
+    #[serde(default)]
+    pub cli_color_mode: CliColorMode,
+    /// Attempts for a quota-path token refresh; only network/5xx/429 failures are retried.
+    #[serde(default = "default_token_refresh_attempts")]
+    pub token_refresh_attempts: u32,
+    /// Longest wakeup prompt accepted, in characters (0 disables the check). Prompts over
+    /// 4 KiB are sent to the CLI via stdin rather than the command line.
+    #[serde(default = "default_max_prompt_chars")]
+    pub max_prompt_chars: usize,
+    /// Periodically refresh quota without wakeups (independent of `scheduler_enabled`).
+    #[serde(default)]
+    pub quota_poll_enabled: bool,
+    /// Seconds between quota polls (minimum 60).
+    #[serde(default = "default_quota_poll_interval_secs")]
+    pub quota_poll_interval_secs: u64,
+    /// Account emails to poll; empty polls every account.
+    #[serde(default)]
+    pub quota_poll_accounts: Vec<String>,
+    /// Dedup duplicate wakeups per (account, window) instead of per account. Off by default:
+    /// account-wide suppression also stops a 5h and a weekly wakeup from both hitting the CLI
+    /// within the duplicate window, which is what users relying on one CLI call per burst want.
+    #[serde(default)]
+    pub dedup_per_window: bool,
+    /// Kill a wakeup CLI run that has not exited after this many ms (0 disables the timeout).
+    #[serde(default = "default_cli_timeout_ms")]
+    pub cli_timeout_ms: u64,
+    /// Resolve the CLI and pre-connect to the usage host at app launch.
+    #[serde(default)]
+    pub warmup_on_launch: bool,
+    /// Wait this long after the CLI call before refreshing quota (0 refreshes immediately).
+    /// The backend can lag behind the spend; 1000-2000ms usually gives truer before -> after
+    /// deltas in the wakeup reply.
+    #[serde(default)]
+    pub post_wakeup_refresh_delay_ms: u64,
+    /// Extra attempts when the CLI process fails to spawn (never for a CLI that ran and failed).
+    #[serde(default = "default_cli_launch_retries")]
+    pub cli_launch_retries: u32,
+    /// Delay before the first launch retry; doubles on each further attempt.
+    #[serde(default = "default_cli_launch_retry_base_delay_ms")]
+    pub cli_launch_retry_base_delay_ms: u64,
+    /// CLI round-trip above which a successful wakeup is flagged as slow (0 disables).
+    /// Only flags the result; it never turns a success into a failure.
+    #[serde(default)]
+    pub latency_slo_ms: u64,
+    /// Output token cap for wakeups that do not pass one (0 keeps the CLI default).
+    #[serde(default)]
+    pub default_max_output_tokens: u32,
+    /// Per-window caps for 5h / weekly wakeups; 0 falls back to `default_max_output_tokens`.
+    #[serde(default)]
+    pub hourly_max_output_tokens: u32,
+    #[serde(default)]
+    pub weekly_max_output_tokens: u32,
+    /// How specific CLI exit codes are treated. Unmapped codes keep the default:
+    /// 0 is success, anything else is a failure.
+    #[serde(default)]
+    pub cli_exit_code_severities: BTreeMap<i32, CliExitSeverity>,
+    /// Run the CLI with `--json` and take the reply from its event stream, falling back to
+    /// `last_message.txt` / stdout scraping when no assistant message can be parsed.
+    #[serde(default)]
+    pub cli_json_events: bool,
+    /// Offset from a window's reset used by `suggest_wakeup_time`, in seconds. Positive
+    /// values lag after the reset (open the fresh window once the backend has rolled over);
+    /// negative values lead it (land in the expiring window, only useful for providers that
+    /// expire idle sessions).
+    #[serde(default = "default_reset_wakeup_offset_secs")]
+    pub reset_wakeup_offset_secs: i64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CliExitSeverity {
+    Success,
+    /// Keep the reply but attach a warning to the response.
+    Warning,
+    Failure,
+}
+
+impl CliExitSeverity {
+    /// Severity for `code` (`None` when the process was killed by a signal).
+    pub fn for_exit_code(code: Option<i32>, mapping: &BTreeMap<i32, CliExitSeverity>) -> Self {
+        match code {
+            Some(code) => mapping.get(&code).copied().unwrap_or(if code == 0 {
+                CliExitSeverity::Success
+            } else {
+                CliExitSeverity::Failure
+            }),
+            None => CliExitSeverity::Failure,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CliColorMode {
+    #[default]
+    Never,
+    Always,
+    Auto,
+}
+
+impl CliColorMode {
+    pub fn as_arg(self) -> &'static str {
+        match self {
+            CliColorMode::Never => "never",
+            CliColorMode::Always => "always",
+            CliColorMode::Auto => "auto",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum PartialQuotaMode {
+    /// Treat the missing window as 100% remaining (previous behavior).
+    #[default]
+    FillFull,
+    /// List the missing window in `unknown_windows` so the UI can show it as unknown.
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ReplyWindowOrder {
+    #[default]
+    HourlyFirst,
+    WeeklyFirst,
+    /// The window with less remaining quota goes first.
+    MostConstrainedFirst,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CliEnvIsolation {
+    /// Inherit the app environment (previous behavior).
+    #[default]
+    Inherit,
+    /// Start empty and pass through only PATH and the home directory.
+    Isolated,
+}
+
+fn default_max_stored_message_chars() -> usize {
+    2_000
+}
+
+fn default_global_rate_max_wait_ms() -> u64 {
+    60_000
+}
+
+fn default_scheduler_enabled() -> bool {
+    true
+}
+
+fn default_http_connect_timeout_secs() -> u64 {
+    10
+}
+
+fn default_http_request_timeout_secs() -> u64 {
+    30
+}
+
+fn default_quota_refresh_concurrency() -> usize {
+    4
+}
+
+fn default_quota_retry_attempts() -> u32 {
+    3
+}
+
+fn default_quota_history_max_samples() -> usize {
+    500
+}
+
+fn default_quota_alert_warn_percent() -> i32 {
+    20
+}
+
+fn default_quota_alert_critical_percent() -> i32 {
+    5
+}
+
+fn default_max_concurrent_wakeups() -> usize {
+    4
+}
+
+fn default_failure_alert_threshold() -> u32 {
+    3
+}
+
+fn default_token_refresh_attempts() -> u32 {
+    3
+}
+
+fn default_max_prompt_chars() -> usize {
+    16_000
+}
+
+fn default_quota_poll_interval_secs() -> u64 {
+    300
+}
+
+fn default_cli_timeout_ms() -> u64 {
+    300_000
+}
+
+fn default_cli_launch_retries() -> u32 {
+    2
+}
+
+fn default_cli_launch_retry_base_delay_ms() -> u64 {
+    250
+}
+
+fn default_reset_wakeup_offset_secs() -> i64 {
+    120
+}
+
+fn default_fallback_cli_models() -> Vec<String> {
+    vec![
+        "gpt-5.3-codex".to_string(),
+        "gpt-5-codex".to_string(),
+        "gpt-5".to_string(),
+    ]
+}
+
+fn default_ignored_stdout_prefixes() -> Vec<String> {
+    vec!["tokens used".to_string()]
+}
+
+impl Default for CodexWakeupSettings {
+    fn default() -> Self {
+        Self {
+            max_stored_message_chars: default_max_stored_message_chars(),
+            ignored_stdout_prefixes: default_ignored_stdout_prefixes(),
+            global_min_interval_ms: 0,
+            global_rate_max_wait_ms: default_global_rate_max_wait_ms(),
+            cli_env_isolation: CliEnvIsolation::default(),
+            reply_cache_ttl_ms: 0,
+            fallback_cli_models: default_fallback_cli_models(),
+            scheduler_enabled: default_scheduler_enabled(),
+            reply_window_order: ReplyWindowOrder::default(),
+            show_stale_quota_in_reply: false,
+            reply_timezone: None,
+            reply_time_format: None,
+            user_agent: None,
+            http_connect_timeout_secs: default_http_connect_timeout_secs(),
+            http_request_timeout_secs: default_http_request_timeout_secs(),
+            quota_refresh_concurrency: default_quota_refresh_concurrency(),
+            quota_retry_attempts: default_quota_retry_attempts(),
+            quota_history_max_samples: default_quota_history_max_samples(),
+            quota_alert_warn_percent: default_quota_alert_warn_percent(),
+            quota_alert_critical_percent: default_quota_alert_critical_percent(),
+            usage_url: None,
+            max_concurrent_wakeups: default_max_concurrent_wakeups(),
+            partial_quota_mode: PartialQuotaMode::default(),
+            failure_alert_threshold: default_failure_alert_threshold(),
+            cli_color_mode: CliColorMode::default(),
+            token_refresh_attempts: default_token_refresh_attempts(),
+            max_prompt_chars: default_max_prompt_chars(),
+            quota_poll_enabled: false,
+            quota_poll_interval_secs: default_quota_poll_interval_secs(),
+            quota_poll_accounts: Vec::new(),
+            dedup_per_window: false,
+            cli_timeout_ms: default_cli_timeout_ms(),
+            warmup_on_launch: false,
+            post_wakeup_refresh_delay_ms: 0,
+            cli_launch_retries: default_cli_launch_retries(),
+            cli_launch_retry_base_delay_ms: default_cli_launch_retry_base_delay_ms(),
+            latency_slo_ms: 0,
+            default_max_output_tokens: 0,
+            hourly_max_output_tokens: 0,
+            weekly_max_output_tokens: 0,
+            cli_exit_code_severities: BTreeMap::new(),
+            cli_json_events: false,
+            reset_wakeup_offset_secs: default_reset_wakeup_offset_secs(),
+        }
+    }
+}
+
+static SETTINGS: OnceLock<RwLock<CodexWakeupSettings>> = OnceLock::new();
+/// Held across read-modify-write so concurrent updates never drop one another.
+static SETTINGS_WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+fn settings_state() -> &'static RwLock<CodexWakeupSettings> {
+    SETTINGS.get_or_init(|| RwLock::new(load_settings_from_disk().unwrap_or_default()))
+}
+
+fn settings_path() -> Result<PathBuf, String> {
+    let data_dir = modules::account::get_data_dir()?;
+    Ok(data_dir.join(SETTINGS_FILE))
+}
+
+fn load_settings_from_disk() -> Result<CodexWakeupSettings, String> {
+    let path = settings_path()?;
+    if !path.exists() {
+        return Ok(CodexWakeupSettings::default());
+    }
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read Codex wakeup settings: {}", e))?;
+    if content.trim().is_empty() {
+        return Ok(CodexWakeupSettings::default());
+    }
+
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse Codex wakeup settings: {}", e))
+}
+
+/// Returns the in-memory settings (loaded from disk on first access).
+pub fn get_settings() -> CodexWakeupSettings {
+    settings_state()
+        .read()
+        .map(|guard| guard.clone())
+        .unwrap_or_default()
+}
+
+pub fn save_settings(settings: &CodexWakeupSettings) -> Result<(), String> {
+    let _lock = SETTINGS_WRITE_LOCK
+        .lock()
+        .map_err(|_| "Failed to acquire Codex wakeup settings lock")?;
+    write_settings(settings)
+}
+
+/// Applies `mutate` to the current settings and saves them while holding the write lock.
+pub fn update_settings<F>(mutate: F) -> Result<CodexWakeupSettings, String>
+where
+    F: FnOnce(&mut CodexWakeupSettings),
+{
+    let _lock = SETTINGS_WRITE_LOCK
+        .lock()
+        .map_err(|_| "Failed to acquire Codex wakeup settings lock")?;
+    let mut settings = get_settings();
+    mutate(&mut settings);
+    write_settings(&settings)?;
+    Ok(settings)
+}
+
+/// Writes via a temp file and rename so a crash mid-write never leaves a torn file.
+fn write_settings(settings: &CodexWakeupSettings) -> Result<(), String> {
+    modules::account::ensure_data_dir_writable()?;
+    let path = settings_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create Codex wakeup settings dir: {}", e))?;
+    }
+    let temp_path = path.with_extension("json.tmp");
+
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize Codex wakeup settings: {}", e))?;
+    fs::write(&temp_path, content)
+        .map_err(|e| format!("Failed to write temporary Codex wakeup settings file: {}", e))?;
+    fs::rename(temp_path, &path)
+        .map_err(|e| format!("Failed to replace Codex wakeup settings file: {}", e))?;
+
+    if let Ok(mut guard) = settings_state().write() {
+        *guard = settings.clone();
+    }
+    modules::logger::log_info("[CodexWakeup] Settings saved");
+    Ok(())
+}
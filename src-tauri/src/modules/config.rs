@@ -73,6 +73,9 @@ pub struct UserConfig {
     /// 切换 Codex 时是否自动重启 OpenCode
     #[serde(default = "default_opencode_sync_on_switch")]
     pub opencode_sync_on_switch: bool,
+    /// 内存日志环形缓冲容量（供界面“最近活动”面板读取）
+    #[serde(default = "default_log_buffer_capacity")]
+    pub log_buffer_capacity: usize,
 }
 
 /// 窗口关闭行为
@@ -106,6 +109,8 @@ fn default_codex_app_path() -> String { String::new() }
 fn default_vscode_app_path() -> String { String::new() }
 fn default_opencode_sync_on_switch() -> bool { true }
 
+fn default_log_buffer_capacity() -> usize { 500 }
+
 impl Default for UserConfig {
     fn default() -> Self {
         Self {
@@ -121,6 +126,7 @@ impl Default for UserConfig {
             codex_app_path: default_codex_app_path(),
             vscode_app_path: default_vscode_app_path(),
             opencode_sync_on_switch: default_opencode_sync_on_switch(),
+            log_buffer_capacity: default_log_buffer_capacity(),
         }
     }
 }
@@ -207,6 +213,7 @@ pub fn save_user_config(config: &UserConfig) -> Result<(), String> {
     if let Ok(mut state) = get_runtime_state().write() {
         state.user_config = config.clone();
     }
+    crate::modules::logger::set_recent_log_capacity(config.log_buffer_capacity);
     
     crate::modules::logger::log_info(&format!(
         "[Config] 用户配置已保存: ws_enabled={}, ws_port={}",
@@ -1,9 +1,96 @@
-use tracing::{info, warn, error};
+use tracing::{debug, info, warn, error};
 use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
 use std::fs;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{LazyLock, Mutex};
 use crate::modules::account::get_data_dir;
 
+/// 高频 info 日志采样窗口：同一模板在窗口内只输出首条
+const SAMPLED_LOG_WINDOW_MS: i64 = 10_000;
+
+/// 模板 -> (窗口开始时间, 窗口内被折叠的条数)
+static SAMPLED_LOGS: LazyLock<Mutex<HashMap<String, (i64, u64)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 进行中的采样批次数；为 0 时 `log_info_sampled` 不折叠，直接输出
+static SAMPLED_BATCHES: AtomicUsize = AtomicUsize::new(0);
+
+/// 内存日志缓冲默认容量（条）
+const DEFAULT_RECENT_LOG_CAPACITY: usize = 500;
+
+static RECENT_LOG_CAPACITY: AtomicUsize = AtomicUsize::new(DEFAULT_RECENT_LOG_CAPACITY);
+static RECENT_LOGS: LazyLock<Mutex<VecDeque<LogEntry>>> =
+    LazyLock::new(|| Mutex::new(VecDeque::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+/// 内存环形缓冲中的一条日志
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    /// 毫秒时间戳
+    pub timestamp: i64,
+    pub level: LogLevel,
+    pub message: String,
+}
+
+fn push_recent(level: LogLevel, message: &str) {
+    let capacity = RECENT_LOG_CAPACITY.load(Ordering::Relaxed);
+    if capacity == 0 {
+        return;
+    }
+    let Ok(mut guard) = RECENT_LOGS.lock() else {
+        return;
+    };
+    while guard.len() >= capacity {
+        guard.pop_front();
+    }
+    guard.push_back(LogEntry {
+        timestamp: chrono::Utc::now().timestamp_millis(),
+        level,
+        message: message.to_string(),
+    });
+}
+
+/// 调整内存日志缓冲容量（0 表示关闭），超出部分丢弃最旧的日志
+pub fn set_recent_log_capacity(capacity: usize) {
+    RECENT_LOG_CAPACITY.store(capacity, Ordering::Relaxed);
+    if let Ok(mut guard) = RECENT_LOGS.lock() {
+        while guard.len() > capacity {
+            guard.pop_front();
+        }
+    }
+}
+
+/// 读取最近的日志（按时间正序），只保留不低于 `level_filter` 的级别，最多 `limit` 条
+pub fn recent_logs(level_filter: Option<LogLevel>, limit: usize) -> Vec<LogEntry> {
+    let Ok(guard) = RECENT_LOGS.lock() else {
+        return Vec::new();
+    };
+    let mut entries: Vec<LogEntry> = guard
+        .iter()
+        .rev()
+        .filter(|entry| match level_filter {
+            Some(min) => entry.level >= min,
+            None => true,
+        })
+        .take(limit)
+        .cloned()
+        .collect();
+    entries.reverse();
+    entries
+}
+
 struct LocalTimer;
 
 impl tracing_subscriber::fmt::time::FormatTime for LocalTimer {
@@ -69,14 +156,141 @@ pub fn init_logger() {
     info!("日志系统已完成初始化");
 }
 
+pub fn log_debug(message: &str) {
+    debug!("{}", message);
+    push_recent(LogLevel::Debug, message);
+}
+
 pub fn log_info(message: &str) {
     info!("{}", message);
+    push_recent(LogLevel::Info, message);
 }
 
 pub fn log_warn(message: &str) {
     warn!("{}", message);
+    push_recent(LogLevel::Warn, message);
 }
 
 pub fn log_error(message: &str) {
     error!("{}", message);
+    push_recent(LogLevel::Error, message);
+}
+
+/// 采样批次守卫：存活期间 `log_info_sampled` 按模板折叠日志，最后一个批次结束时输出剩余汇总
+pub struct SampledLogBatch(());
+
+/// 开始一个采样批次（批量刷新/唤醒时使用）
+pub fn begin_sampled_batch() -> SampledLogBatch {
+    SAMPLED_BATCHES.fetch_add(1, Ordering::SeqCst);
+    SampledLogBatch(())
+}
+
+impl Drop for SampledLogBatch {
+    fn drop(&mut self) {
+        if SAMPLED_BATCHES.fetch_sub(1, Ordering::SeqCst) == 1 {
+            flush_sampled_logs();
+        }
+    }
+}
+
+/// 按模板采样输出高频 info 日志；不在采样批次内时等同于 `log_info`
+///
+/// 同一 `template` 在窗口内只输出首条，其余计数折叠，下个窗口开始时输出 "xN" 汇总。
+/// warn/error 不经过采样，始终输出。
+pub fn log_info_sampled(template: &str, message: &str) {
+    if SAMPLED_BATCHES.load(Ordering::SeqCst) == 0 {
+        log_info(message);
+        return;
+    }
+    let now = chrono::Utc::now().timestamp_millis();
+    let summary = {
+        let Ok(mut guard) = SAMPLED_LOGS.lock() else {
+            log_info(message);
+            return;
+        };
+        match guard.get_mut(template) {
+            Some((started_at, suppressed)) if now - *started_at < SAMPLED_LOG_WINDOW_MS => {
+                *suppressed += 1;
+                return;
+            }
+            Some((started_at, suppressed)) => {
+                let collapsed = *suppressed;
+                *started_at = now;
+                *suppressed = 0;
+                collapsed
+            }
+            None => {
+                guard.insert(template.to_string(), (now, 0));
+                0
+            }
+        }
+    };
+    if summary > 0 {
+        log_info(&format!("{} (x{} 条相似日志已折叠)", template, summary));
+    }
+    log_info(message);
+}
+
+/// 输出所有尚未汇总的折叠计数（最后一个采样批次结束时调用）
+fn flush_sampled_logs() {
+    let pending: Vec<(String, u64)> = match SAMPLED_LOGS.lock() {
+        Ok(mut guard) => guard
+            .drain()
+            .filter(|(_, (_, suppressed))| *suppressed > 0)
+            .map(|(template, (_, suppressed))| (template, suppressed))
+            .collect(),
+        Err(_) => return,
+    };
+    for (template, suppressed) in pending {
+        log_info(&format!("{} (x{} 条相似日志已折叠)", template, suppressed));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampled_logs_collapse_only_inside_a_batch() {
+        let template = format!("sampled-log-test-{}", uuid::Uuid::new_v4().simple());
+        let ours = || -> Vec<String> {
+            recent_logs(None, usize::MAX)
+                .into_iter()
+                .filter(|entry| entry.message.starts_with(&template))
+                .map(|entry| entry.message)
+                .collect()
+        };
+
+        log_info_sampled(&template, &format!("{} a", template));
+        log_info_sampled(&template, &format!("{} b", template));
+        assert_eq!(ours().len(), 2);
+
+        let batch = begin_sampled_batch();
+        log_info_sampled(&template, &format!("{} c", template));
+        log_info_sampled(&template, &format!("{} d", template));
+        assert_eq!(ours().len(), 3);
+        drop(batch);
+        assert_eq!(ours().last().unwrap(), &format!("{} (x1 条相似日志已折叠)", template));
+    }
+
+    #[test]
+    fn test_recent_logs_filters_by_level_and_limit() {
+        let marker = format!("recent-log-test-{}", uuid::Uuid::new_v4().simple());
+        log_info(&format!("{} info", marker));
+        log_warn(&format!("{} warn", marker));
+        log_error(&format!("{} error", marker));
+
+        let ours = |entries: Vec<LogEntry>| -> Vec<LogLevel> {
+            entries
+                .into_iter()
+                .filter(|entry| entry.message.starts_with(&marker))
+                .map(|entry| entry.level)
+                .collect()
+        };
+        assert_eq!(
+            ours(recent_logs(Some(LogLevel::Warn), usize::MAX)),
+            vec![LogLevel::Warn, LogLevel::Error]
+        );
+        assert_eq!(ours(recent_logs(None, usize::MAX)).len(), 3);
+    }
 }
@@ -18,11 +18,20 @@ pub mod sync_settings;
 pub mod update_checker;
 pub mod group_settings;
 pub mod codex_account;
+pub mod codex_account_crypto;
+pub mod codex_http;
+pub mod codex_metrics;
 pub mod codex_quota;
+pub mod codex_quota_alerts;
+pub mod codex_quota_history;
+pub mod codex_quota_poller;
 pub mod codex_oauth;
 pub mod codex_wakeup;
 pub mod codex_wakeup_scheduler;
 pub mod codex_wakeup_history;
+pub mod codex_wakeup_history_csv;
+pub mod codex_wakeup_settings;
+pub mod codex_support_bundle;
 pub mod opencode_auth;
 pub mod tray;
 pub mod instance_store;
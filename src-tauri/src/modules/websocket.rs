@@ -740,6 +740,7 @@ fn handle_set_language(language: &str, source: Option<&str>) -> Result<String, S
         codex_app_path: current.codex_app_path,
         vscode_app_path: current.vscode_app_path,
         opencode_sync_on_switch: current.opencode_sync_on_switch,
+        log_buffer_capacity: current.log_buffer_capacity,
     };
 
     config::save_user_config(&new_config)?;
@@ -0,0 +1,51 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+/// 去除终端 ANSI 转义序列（颜色、光标移动、OSC 标题、进度条等）
+pub fn strip_ansi(s: &str) -> String {
+    static ANSI_RE: OnceLock<Regex> = OnceLock::new();
+    let re = ANSI_RE.get_or_init(|| {
+        // CSI: ESC [ params intermediates final
+        // OSC: ESC ] ... (BEL | ESC \)
+        // 其余两字节序列: ESC + [0-?] / [@-_]（如 ESC 7 / ESC 8 保存恢复光标）
+        Regex::new(r"\x1b(?:\[[0-?]*[ -/]*[@-~]|\][^\x07\x1b]*(?:\x07|\x1b\\)|[0-?@-Z\\-_])")
+            .expect("valid ANSI regex")
+    });
+    // 旋转进度条常用 `\r` 覆盖同一行，只保留最后一段
+    re.replace_all(s, "")
+        .lines()
+        .map(|line| line.rsplit('\r').find(|part| !part.is_empty()).unwrap_or(""))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_sgr_colors() {
+        assert_eq!(strip_ansi("\x1b[1m\x1b[32mOK\x1b[0m"), "OK");
+        assert_eq!(strip_ansi("\x1b[38;5;208mwarn\x1b[39m"), "warn");
+    }
+
+    #[test]
+    fn test_strip_cursor_and_erase_sequences() {
+        assert_eq!(strip_ansi("\x1b[2K\x1b[1Gdone"), "done");
+        assert_eq!(strip_ansi("\x1b[?25lhidden cursor\x1b[?25h"), "hidden cursor");
+    }
+
+    #[test]
+    fn test_strip_osc_and_two_byte_sequences() {
+        assert_eq!(strip_ansi("\x1b]0;codex\x07reply"), "reply");
+        assert_eq!(strip_ansi("\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x1b\\"), "link");
+        assert_eq!(strip_ansi("\x1b7saved\x1b8"), "saved");
+    }
+
+    #[test]
+    fn test_spinner_carriage_returns_keep_final_frame() {
+        assert_eq!(strip_ansi("⠋ working\r⠙ working\rOK\nnext"), "OK\nnext");
+        assert_eq!(strip_ansi("plain text"), "plain text");
+    }
+}
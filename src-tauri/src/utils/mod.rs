@@ -1,2 +1,3 @@
+pub mod ansi;
 pub mod http;
 pub mod protobuf;
@@ -710,9 +710,7 @@ export function CodexWakeupTasksPage() {
         duration,
       };
     });
-    codexService.codexWakeupAddHistoryItems(historyItems).catch((error) => {
-      console.error('Failed to persist Codex wakeup test history:', error);
-    });
+    // codex_trigger_wakeup already persisted these; only update the local list.
     appendHistoryRecords(historyItems);
     setTesting(false);
     setShowTestModal(false);